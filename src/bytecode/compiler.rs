@@ -0,0 +1,675 @@
+use std::collections::HashMap;
+
+use crate::{
+    bytecode::{
+        Chunk, Function, MAIN_FUNCTION, Op, Program, StructShape, VariantShape,
+        errors::CompileError,
+    },
+    common::{
+        ast::Ast,
+        expression::{
+            Expression, ExpressionKind, FieldInit, FunctionId, InfixOp, MatchArm, Pattern,
+            PatternKind, TypeId, TypeValue, VarId,
+        },
+        span::Span,
+    },
+    evaluating::{environment::RuntimeType, value::Value},
+};
+
+struct LoopLabel {
+    start: usize,
+    // the stack depth when the loop was entered, which `break` and `continue` unwind to
+    depth: usize,
+    breaks: Vec<usize>,
+}
+
+/// The function currently being compiled
+struct FunctionState {
+    index: usize,
+    locals: Vec<String>,
+    // the slot of each variable declared in the function
+    slots: HashMap<VarId, usize>,
+    chunk: Chunk,
+    depth: usize,
+    loops: Vec<LoopLabel>,
+}
+
+impl FunctionState {
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            locals: vec![],
+            slots: HashMap::new(),
+            chunk: Chunk::default(),
+            depth: 0,
+            loops: vec![],
+        }
+    }
+}
+
+type Compilation = Result<(), CompileError>;
+
+/// Lowers a checked `Ast` to bytecode, every expression compiles to code that leaves exactly
+/// one value on the stack
+pub struct Compiler {
+    program: Program,
+    // the index of each function declared so far, by the checker's id
+    declared: HashMap<FunctionId, usize>,
+    // the struct or enum each declared type refers to, aliases share their owner's entry
+    types: HashMap<TypeId, (String, RuntimeType)>,
+    functions: Vec<FunctionState>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            program: Program {
+                constants: vec![],
+                functions: vec![],
+                structs: vec![],
+                variants: vec![],
+            },
+            declared: HashMap::new(),
+            types: HashMap::new(),
+            functions: vec![],
+        }
+    }
+
+    pub fn compile(mut self, ast: &Ast) -> Result<Program, CompileError> {
+        let index = self.reserve_function("<main>", 0);
+        debug_assert_eq!(index, MAIN_FUNCTION);
+        self.functions.push(FunctionState::new(index));
+
+        self.compile_sequence(&ast.0)?;
+        let span = ast
+            .0
+            .last()
+            .map_or(Span::new(0, 0), |expr| expr.span.clone());
+        self.emit(Op::Return, &span);
+        self.finish_function();
+        Ok(self.program)
+    }
+
+    fn compile_expression(&mut self, expr: &Expression) -> Compilation {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => self.emit_constant(Value::Int(*value), span),
+            ExpressionKind::LiteralFloat(value) => self.emit_constant(Value::Float(*value), span),
+            ExpressionKind::LiteralBool(value) => self.emit_constant(Value::Bool(*value), span),
+            ExpressionKind::Ident { name, var } => {
+                let slot = self
+                    .resolve_local(*var)
+                    .ok_or_else(|| CompileError::unresolved_variable(name, span))?;
+                self.emit(Op::GetLocal(slot), span);
+            }
+            ExpressionKind::Prefix { op, rh } => {
+                self.compile_expression(rh)?;
+                self.emit(Op::Prefix(*op), span);
+            }
+            ExpressionKind::Infix { op, lh, rh } => match op {
+                InfixOp::And | InfixOp::Or => self.compile_logical(*op, lh, rh, span)?,
+                _ => {
+                    self.compile_expression(lh)?;
+                    self.compile_expression(rh)?;
+                    self.emit(Op::Infix(*op), span);
+                }
+            },
+            ExpressionKind::Block(exprs) => self.compile_sequence(exprs)?,
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => self.compile_if(condition, then, otherwise.as_deref(), span)?,
+            ExpressionKind::While { condition, body } => {
+                self.compile_while(condition, body, span)?;
+            }
+            ExpressionKind::Loop(body) => self.compile_loop(body)?,
+            ExpressionKind::Break(value) => self.compile_break(value.as_deref(), span)?,
+            ExpressionKind::Continue => {
+                let depth = self.state().depth;
+                let (start, loop_depth) = {
+                    let label = self.current_loop();
+                    (label.start, label.depth)
+                };
+                if depth > loop_depth {
+                    self.emit(Op::Drop(depth - loop_depth), span);
+                }
+                self.emit(Op::Jump(start), span);
+                self.state().depth = depth + 1;
+            }
+            ExpressionKind::VariableDecl { .. }
+            | ExpressionKind::VariableAssignment { .. }
+            | ExpressionKind::TypeDecl { .. }
+            | ExpressionKind::FunctionDecl { .. } => {
+                self.compile_statement(expr)?;
+                self.emit_constant(Value::Unit, span);
+            }
+            ExpressionKind::FunctionCall {
+                name,
+                args,
+                function,
+            } => {
+                self.compile_call(name, *function, &args.iter().collect::<Vec<_>>(), span)?;
+            }
+            ExpressionKind::MethodCall {
+                caller,
+                name,
+                args,
+                function,
+            } => {
+                let args = std::iter::once(caller.as_ref())
+                    .chain(args.iter())
+                    .collect::<Vec<_>>();
+                self.compile_call(name, *function, &args, span)?;
+            }
+            ExpressionKind::StructLiteral { fields, .. } => {
+                self.compile_struct_literal(expr.ty(), fields, span)?;
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                self.compile_expression(target)?;
+                let (_, layout) = self.resolve_struct(target.ty(), span)?;
+                let index = layout
+                    .iter()
+                    .position(|name| name == field)
+                    .ok_or_else(|| CompileError::unresolved_field(field, span))?;
+                self.emit(Op::GetField(index), span);
+            }
+            ExpressionKind::EnumVariant { variant, args, .. } => {
+                for arg in args {
+                    self.compile_expression(arg)?;
+                }
+                let shape = self.variant_shape(expr.ty(), variant, args.len(), span)?;
+                self.emit(Op::Variant(shape), span);
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => {
+                self.compile_match(scrutinee, arms, span)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles an expression whose value isn't used, leaving the stack as it was
+    fn compile_statement(&mut self, expr: &Expression) -> Compilation {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::VariableDecl {
+                name, value, var, ..
+            } => {
+                self.compile_expression(value)?;
+                let slot = self.declare_local(name, *var);
+                self.emit(Op::SetLocal(slot), span);
+            }
+            ExpressionKind::VariableAssignment { name, value, var } => {
+                self.compile_expression(value)?;
+                let slot = self
+                    .resolve_local(*var)
+                    .ok_or_else(|| CompileError::unresolved_variable(name, span))?;
+                self.emit(Op::SetLocal(slot), span);
+            }
+            ExpressionKind::TypeDecl {
+                name, value, ty, ..
+            } => {
+                let entry = match value {
+                    TypeValue::Alias(parent) => self.types.get(&parent.resolved().1).cloned(),
+                    TypeValue::Struct(fields) => Some((
+                        name.clone(),
+                        RuntimeType::Struct(fields.iter().map(|f| f.name.clone()).collect()),
+                    )),
+                    TypeValue::Enum(_) => Some((name.clone(), RuntimeType::Enum)),
+                };
+                // aliases of builtins have no layout to look up
+                if let (Some(ty), Some(entry)) = (ty, entry) {
+                    self.types.insert(*ty, entry);
+                }
+            }
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                body,
+                function,
+                ..
+            } => {
+                let index = self.reserve_function(name, params.len());
+                // declared before the body is compiled so that functions can recurse
+                if let Some(function) = function {
+                    self.declared.insert(*function, index);
+                }
+
+                self.functions.push(FunctionState::new(index));
+                for param in params {
+                    self.declare_local(&param.name, param.var);
+                }
+                self.compile_expression(body)?;
+                self.emit(Op::Return, &body.span);
+                self.finish_function();
+            }
+            ExpressionKind::Block(exprs) => {
+                for expr in exprs {
+                    self.compile_statement(expr)?;
+                }
+            }
+            _ => {
+                self.compile_expression(expr)?;
+                self.emit(Op::Pop, span);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the expressions of a block, its value is the value of the last one
+    fn compile_sequence(&mut self, exprs: &[Expression]) -> Compilation {
+        let Some((last, rest)) = exprs.split_last() else {
+            let span = Span::new(0, 0);
+            self.emit_constant(Value::Unit, &span);
+            return Ok(());
+        };
+        for expr in rest {
+            self.compile_statement(expr)?;
+        }
+        self.compile_expression(last)
+    }
+
+    /// Short-circuits `and` and `or`, only evaluating `rh` when it decides the result
+    fn compile_logical(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Compilation {
+        self.compile_expression(lh)?;
+        if matches!(op, InfixOp::And) {
+            let short = self.emit(Op::JumpIfFalse(0), span);
+            let depth = self.state().depth;
+            self.compile_expression(rh)?;
+            let end = self.emit(Op::Jump(0), span);
+            self.patch(short);
+            self.state().depth = depth;
+            self.emit_constant(Value::Bool(false), span);
+            self.patch(end);
+        } else {
+            let long = self.emit(Op::JumpIfFalse(0), span);
+            let depth = self.state().depth;
+            self.emit_constant(Value::Bool(true), span);
+            let end = self.emit(Op::Jump(0), span);
+            self.patch(long);
+            self.state().depth = depth;
+            self.compile_expression(rh)?;
+            self.patch(end);
+        }
+        Ok(())
+    }
+
+    fn compile_if(
+        &mut self,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: Option<&Expression>,
+        span: &Span,
+    ) -> Compilation {
+        self.compile_expression(condition)?;
+        let skip_then = self.emit(Op::JumpIfFalse(0), span);
+        let depth = self.state().depth;
+        self.compile_expression(then)?;
+        let end = self.emit(Op::Jump(0), span);
+        self.patch(skip_then);
+        self.state().depth = depth;
+        match otherwise {
+            Some(otherwise) => self.compile_expression(otherwise)?,
+            None => self.emit_constant(Value::Unit, span),
+        }
+        self.patch(end);
+        Ok(())
+    }
+
+    fn compile_while(
+        &mut self,
+        condition: &Expression,
+        body: &Expression,
+        span: &Span,
+    ) -> Compilation {
+        let depth = self.state().depth;
+        let start = self.state().chunk.code.len();
+        self.compile_expression(condition)?;
+        let exit = self.emit(Op::JumpIfFalse(0), span);
+        let label = self.compile_loop_body(body, start, depth)?;
+        self.patch(exit);
+        self.emit_constant(Value::Unit, span);
+        for jump in label.breaks {
+            self.patch(jump);
+        }
+        Ok(())
+    }
+
+    fn compile_loop(&mut self, body: &Expression) -> Compilation {
+        let depth = self.state().depth;
+        let start = self.state().chunk.code.len();
+        let label = self.compile_loop_body(body, start, depth)?;
+        // the loop's value is whatever a `break` left behind
+        self.state().depth = depth + 1;
+        for jump in label.breaks {
+            self.patch(jump);
+        }
+        Ok(())
+    }
+
+    fn compile_break(&mut self, value: Option<&Expression>, span: &Span) -> Compilation {
+        let depth = self.state().depth;
+        let loop_depth = self.current_loop().depth;
+        match value {
+            Some(value) => self.compile_expression(value)?,
+            None => self.emit_constant(Value::Unit, span),
+        }
+        if depth > loop_depth {
+            self.emit(Op::Slide(depth - loop_depth), span);
+        }
+        let jump = self.emit(Op::Jump(0), span);
+        self.current_loop().breaks.push(jump);
+        // unreachable, but the surrounding code expects a value
+        self.state().depth = depth + 1;
+        Ok(())
+    }
+
+    fn compile_loop_body(
+        &mut self,
+        body: &Expression,
+        start: usize,
+        depth: usize,
+    ) -> Result<LoopLabel, CompileError> {
+        self.state().loops.push(LoopLabel {
+            start,
+            depth,
+            breaks: vec![],
+        });
+        self.compile_statement(body)?;
+        self.emit(Op::Jump(start), &body.span);
+        self.state().depth = depth;
+        Ok(self.state().loops.pop().expect("Loop label pushed above"))
+    }
+
+    fn compile_struct_literal(
+        &mut self,
+        ty: (&str, TypeId),
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Compilation {
+        let (ty, layout) = self.resolve_struct(ty, span)?;
+        for field in fields {
+            self.compile_expression(&field.value)?;
+        }
+        let fields = layout
+            .into_iter()
+            .map(|name| match fields.iter().position(|f| f.name == name) {
+                Some(position) => Ok((name, position)),
+                None => Err(CompileError::unresolved_field(&name, span)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.program.structs.push(StructShape { ty, fields });
+        self.emit(Op::Struct(self.program.structs.len() - 1), span);
+        Ok(())
+    }
+
+    fn compile_call(
+        &mut self,
+        name: &str,
+        function: Option<FunctionId>,
+        args: &[&Expression],
+        span: &Span,
+    ) -> Compilation {
+        for arg in args {
+            self.compile_expression(arg)?;
+        }
+        match function.and_then(|function| self.declared.get(&function).copied()) {
+            Some(index) => {
+                self.emit(Op::Call(index, args.len()), span);
+            }
+            // `to_unit` is a builtin that discards its arguments
+            None if name == "to_unit" => {
+                if !args.is_empty() {
+                    self.emit(Op::Drop(args.len()), span);
+                }
+                self.emit_constant(Value::Unit, span);
+            }
+            None => return Err(CompileError::unresolved_function(name, span)),
+        }
+        Ok(())
+    }
+
+    /// Emits code that falls through when the value in `slot` matches the pattern, binding its
+    /// names, and jumps to one of `fails` otherwise
+    fn compile_match(
+        &mut self,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Compilation {
+        self.compile_expression(scrutinee)?;
+        let scrutinee_slot = self.hidden_local();
+        self.emit(Op::SetLocal(scrutinee_slot), span);
+
+        let depth = self.state().depth;
+        let mut ends = vec![];
+        for arm in arms {
+            let mut fails = vec![];
+            self.compile_pattern(&arm.pattern, scrutinee_slot, &mut fails)?;
+            self.compile_expression(&arm.body)?;
+            ends.push(self.emit(Op::Jump(0), &arm.body.span));
+            for jump in fails {
+                self.patch(jump);
+            }
+            self.state().depth = depth;
+        }
+        self.emit(Op::NoMatch, span);
+        for jump in ends {
+            self.patch(jump);
+        }
+        self.state().depth = depth + 1;
+        Ok(())
+    }
+
+    fn compile_pattern(
+        &mut self,
+        pattern: &Pattern,
+        slot: usize,
+        fails: &mut Vec<usize>,
+    ) -> Compilation {
+        let span = &pattern.span;
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding { name, var } => {
+                self.emit(Op::GetLocal(slot), span);
+                let binding = self.declare_local(name, *var);
+                self.emit(Op::SetLocal(binding), span);
+            }
+            PatternKind::LiteralInt(value) => {
+                self.compile_literal_test(Value::Int(*value), slot, span, fails);
+            }
+            PatternKind::LiteralBool(value) => {
+                self.compile_literal_test(Value::Bool(*value), slot, span, fails);
+            }
+            PatternKind::Variant { ty, name, args } => {
+                let shape = self.variant_shape(ty.resolved(), name, args.len(), span)?;
+                self.emit(Op::GetLocal(slot), span);
+                self.emit(Op::IsVariant(shape), span);
+                fails.push(self.emit(Op::JumpIfFalse(0), span));
+                for (index, arg) in args.iter().enumerate() {
+                    if matches!(arg.kind, PatternKind::Wildcard) {
+                        continue;
+                    }
+                    self.emit(Op::GetLocal(slot), &arg.span);
+                    self.emit(Op::VariantArg(index), &arg.span);
+                    if let PatternKind::Binding { name, var } = &arg.kind {
+                        let binding = self.declare_local(name, *var);
+                        self.emit(Op::SetLocal(binding), &arg.span);
+                        continue;
+                    }
+                    let arg_slot = self.hidden_local();
+                    self.emit(Op::SetLocal(arg_slot), &arg.span);
+                    self.compile_pattern(arg, arg_slot, fails)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_literal_test(
+        &mut self,
+        value: Value,
+        slot: usize,
+        span: &Span,
+        fails: &mut Vec<usize>,
+    ) {
+        self.emit(Op::GetLocal(slot), span);
+        self.emit_constant(value, span);
+        self.emit(Op::Infix(InfixOp::Equals), span);
+        fails.push(self.emit(Op::JumpIfFalse(0), span));
+    }
+
+    /// Appends an instruction, returning its offset so jumps can be patched later
+    fn emit(&mut self, op: Op, span: &Span) -> usize {
+        let effect = self.stack_effect(op);
+        let state = self.state();
+        state.depth = state.depth.wrapping_add_signed(effect);
+        state.chunk.code.push(op);
+        state.chunk.spans.push(span.clone());
+        state.chunk.code.len() - 1
+    }
+
+    fn emit_constant(&mut self, value: Value, span: &Span) {
+        let constants = &mut self.program.constants;
+        let index = constants
+            .iter()
+            .position(|c| *c == value)
+            .unwrap_or_else(|| {
+                constants.push(value);
+                constants.len() - 1
+            });
+        self.emit(Op::Constant(index), span);
+    }
+
+    /// Points the jump at `offset` to the next instruction
+    fn patch(&mut self, offset: usize) {
+        let chunk = &mut self.state().chunk;
+        let target = chunk.code.len();
+        chunk.code[offset] = match chunk.code[offset] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            op => op,
+        };
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn stack_effect(&self, op: Op) -> isize {
+        match op {
+            Op::Constant(_) | Op::GetLocal(_) => 1,
+            Op::Pop | Op::SetLocal(_) | Op::Infix(_) | Op::JumpIfFalse(_) | Op::Return => -1,
+            Op::Drop(count) | Op::Slide(count) => -(count as isize),
+            Op::Call(_, argc) => 1 - argc as isize,
+            Op::Struct(shape) => 1 - self.program.structs[shape].fields.len() as isize,
+            Op::Variant(shape) => 1 - self.program.variants[shape].arity as isize,
+            Op::Prefix(_)
+            | Op::Jump(_)
+            | Op::GetField(_)
+            | Op::IsVariant(_)
+            | Op::VariantArg(_)
+            | Op::NoMatch => 0,
+        }
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions
+            .last_mut()
+            .expect("Compiling outside of a function")
+    }
+
+    fn current_loop(&mut self) -> &mut LoopLabel {
+        self.state()
+            .loops
+            .last_mut()
+            .expect("The checker rejects `break` and `continue` outside of loops")
+    }
+
+    fn reserve_function(&mut self, name: &str, arity: usize) -> usize {
+        self.program.functions.push(Function {
+            name: name.to_string(),
+            arity,
+            locals: vec![],
+            chunk: Chunk::default(),
+        });
+        self.program.functions.len() - 1
+    }
+
+    fn finish_function(&mut self) {
+        let state = self
+            .functions
+            .pop()
+            .expect("Finishing a function that was never started");
+        let function = &mut self.program.functions[state.index];
+        function.locals = state.locals;
+        function.chunk = state.chunk;
+    }
+
+    fn declare_local(&mut self, name: &str, var: Option<VarId>) -> usize {
+        let slot = self.hidden_local();
+        let state = self.state();
+        state.locals[slot] = name.to_string();
+        if let Some(var) = var {
+            state.slots.insert(var, slot);
+        }
+        slot
+    }
+
+    /// A slot for compiler temporaries, which no name resolves to
+    fn hidden_local(&mut self) -> usize {
+        let state = self.state();
+        state.locals.push(String::new());
+        state.locals.len() - 1
+    }
+
+    /// The slot of a variable, which the checker only lets the function declaring it use
+    fn resolve_local(&self, var: Option<VarId>) -> Option<usize> {
+        let state = self.functions.last()?;
+        var.and_then(|var| state.slots.get(&var).copied())
+    }
+
+    /// The owner's name and layout of the struct a type refers to
+    fn resolve_struct(
+        &self,
+        (name, id): (&str, TypeId),
+        span: &Span,
+    ) -> Result<(String, Vec<String>), CompileError> {
+        match self.types.get(&id) {
+            Some((ty, RuntimeType::Struct(layout))) => Ok((ty.clone(), layout.clone())),
+            _ => Err(CompileError::unresolved_type(name, span)),
+        }
+    }
+
+    fn variant_shape(
+        &mut self,
+        (ty, id): (&str, TypeId),
+        name: &str,
+        arity: usize,
+        span: &Span,
+    ) -> Result<usize, CompileError> {
+        let Some((ty, RuntimeType::Enum)) = self.types.get(&id).cloned() else {
+            return Err(CompileError::unresolved_type(ty, span));
+        };
+        let existing = self
+            .program
+            .variants
+            .iter()
+            .position(|shape| shape.ty == ty && shape.name == name);
+        Ok(existing.unwrap_or_else(|| {
+            self.program.variants.push(VariantShape {
+                ty,
+                name: name.to_string(),
+                arity,
+            });
+            self.program.variants.len() - 1
+        }))
+    }
+}
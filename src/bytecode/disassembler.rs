@@ -0,0 +1,94 @@
+use std::fmt::Write;
+
+use crate::bytecode::{Function, Op, Program};
+
+/// Renders every function of a program as one instruction per line, annotated with the
+/// constants, locals and functions the operands refer to
+pub fn disassemble(program: &Program) -> String {
+    let mut output = String::new();
+    for (index, function) in program.functions.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        disassemble_function(&mut output, program, function);
+    }
+    output
+}
+
+fn disassemble_function(output: &mut String, program: &Program, function: &Function) {
+    let locals = function
+        .locals
+        .iter()
+        .enumerate()
+        .map(|(slot, name)| format!("{slot} {}", local_name(name)))
+        .collect::<Vec<_>>();
+    writeln!(
+        output,
+        "== {} ({} params) ==",
+        function.name, function.arity
+    )
+    .unwrap();
+    writeln!(output, "locals: {}", locals.join(", ")).unwrap();
+
+    for (offset, op) in function.chunk.code.iter().enumerate() {
+        let (name, operands, comment) = describe(program, function, *op);
+        let line = format!("{offset:04}  {name:<12} {operands:<8}");
+        match comment {
+            Some(comment) => writeln!(output, "{line} ; {comment}").unwrap(),
+            None => writeln!(output, "{}", line.trim_end()).unwrap(),
+        }
+    }
+}
+
+fn describe(program: &Program, function: &Function, op: Op) -> (String, String, Option<String>) {
+    let local = |slot: usize| Some(local_name(&function.locals[slot]).to_string());
+    let variant = |shape: usize| {
+        let shape = &program.variants[shape];
+        Some(format!("{}.{}", shape.ty, shape.name))
+    };
+    match op {
+        Op::Constant(index) => (
+            "Constant".into(),
+            index.to_string(),
+            Some(program.constants[index].to_string()),
+        ),
+        Op::Pop => ("Pop".into(), String::new(), None),
+        Op::Drop(count) => ("Drop".into(), count.to_string(), None),
+        Op::Slide(count) => ("Slide".into(), count.to_string(), None),
+        Op::GetLocal(slot) => ("GetLocal".into(), slot.to_string(), local(slot)),
+        Op::SetLocal(slot) => ("SetLocal".into(), slot.to_string(), local(slot)),
+        Op::Prefix(op) => ("Prefix".into(), format!("{op:?}"), None),
+        Op::Infix(op) => ("Infix".into(), format!("{op:?}"), None),
+        Op::Jump(target) => ("Jump".into(), format!("{target:04}"), None),
+        Op::JumpIfFalse(target) => ("JumpIfFalse".into(), format!("{target:04}"), None),
+        Op::Call(index, argc) => (
+            "Call".into(),
+            format!("{index} {argc}"),
+            Some(program.functions[index].name.clone()),
+        ),
+        Op::Return => ("Return".into(), String::new(), None),
+        Op::Struct(shape) => {
+            let shape = &program.structs[shape];
+            let fields = shape
+                .fields
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            (
+                "Struct".into(),
+                fields.len().to_string(),
+                Some(format!("{} {{ {} }}", shape.ty, fields.join(", "))),
+            )
+        }
+        Op::GetField(index) => ("GetField".into(), index.to_string(), None),
+        Op::Variant(shape) => ("Variant".into(), shape.to_string(), variant(shape)),
+        Op::IsVariant(shape) => ("IsVariant".into(), shape.to_string(), variant(shape)),
+        Op::VariantArg(index) => ("VariantArg".into(), index.to_string(), None),
+        Op::NoMatch => ("NoMatch".into(), String::new(), None),
+    }
+}
+
+const fn local_name(name: &str) -> &str {
+    // compiler temporaries have no name
+    if name.is_empty() { "_" } else { name }
+}
@@ -0,0 +1,43 @@
+use crate::common::span::Span;
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub span: Span,
+    pub kind: CompileErrorKind,
+}
+
+// what kind of name went unresolved, the checker resolves every one so these point at bugs
+#[derive(Debug)]
+pub enum CompileErrorKind {
+    Variable(String),
+    Function(String),
+    Type(String),
+    Field(String),
+}
+
+impl CompileError {
+    pub fn unresolved_variable(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CompileErrorKind::Variable(name.to_string()),
+        }
+    }
+    pub fn unresolved_function(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CompileErrorKind::Function(name.to_string()),
+        }
+    }
+    pub fn unresolved_type(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CompileErrorKind::Type(name.to_string()),
+        }
+    }
+    pub fn unresolved_field(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CompileErrorKind::Field(name.to_string()),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    common::{
+        expression::{InfixOp, PrefixOp},
+        span::Span,
+    },
+    evaluating::value::Value,
+};
+
+pub mod compiler;
+pub mod disassembler;
+pub mod errors;
+pub mod vm;
+
+/// Every instruction leaves the stack in a statically known shape, jump targets are offsets
+/// into the chunk of the function being run
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Constant(usize),
+    Pop,
+    /// Drops the given number of values from the top of the stack
+    Drop(usize),
+    /// Keeps the top value, dropping the given number of values beneath it
+    Slide(usize),
+    GetLocal(usize),
+    SetLocal(usize),
+    Prefix(PrefixOp),
+    Infix(InfixOp),
+    Jump(usize),
+    JumpIfFalse(usize),
+    /// Calls a function with the given number of arguments from the top of the stack
+    Call(usize, usize),
+    Return,
+    Struct(usize),
+    GetField(usize),
+    Variant(usize),
+    /// Replaces an enum value with whether it is the given variant
+    IsVariant(usize),
+    /// Replaces an enum value with one of its payload values
+    VariantArg(usize),
+    /// Reached when no match arm applies, which the checker rules out
+    NoMatch,
+}
+
+#[derive(Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    // the source span of every instruction, for runtime errors
+    pub spans: Vec<Span>,
+}
+
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    // one slot per declaration, parameters first
+    pub locals: Vec<String>,
+    pub chunk: Chunk,
+}
+
+pub struct StructShape {
+    pub ty: String,
+    /// The fields in declaration order, with the stack position each value is pushed at
+    pub fields: Vec<(String, usize)>,
+}
+
+pub struct VariantShape {
+    pub ty: String,
+    pub name: String,
+    pub arity: usize,
+}
+
+/// A compiled module, the function at `MAIN_FUNCTION` runs the top level expressions
+pub struct Program {
+    pub constants: Vec<Value>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<StructShape>,
+    pub variants: Vec<VariantShape>,
+}
+
+pub const MAIN_FUNCTION: usize = 0;
@@ -0,0 +1,180 @@
+use crate::{
+    bytecode::{MAIN_FUNCTION, Op, Program, StructShape, VariantShape},
+    common::span::Span,
+    evaluating::{MAX_CALL_DEPTH, errors::RuntimeError, operators, value::Value},
+};
+
+struct CallFrame {
+    function: usize,
+    ip: usize,
+    locals: Vec<Value>,
+}
+
+/// Runs compiled programs, calls live on the heap so deep recursion doesn't need a large stack
+pub struct Vm<'a> {
+    program: &'a Program,
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+}
+
+impl<'a> Vm<'a> {
+    pub const fn new(program: &'a Program) -> Self {
+        Self {
+            program,
+            stack: vec![],
+            frames: vec![],
+        }
+    }
+
+    /// Runs the program's top level expressions, returning the value of the last one
+    pub fn run(&mut self) -> Result<Value, RuntimeError> {
+        self.stack.clear();
+        self.frames.clear();
+        self.push_frame(MAIN_FUNCTION, vec![]);
+
+        let program = self.program;
+        loop {
+            let frame = self.frame();
+            let chunk = &program.functions[frame.function].chunk;
+            let (op, span) = (chunk.code[frame.ip], &chunk.spans[frame.ip]);
+            frame.ip += 1;
+
+            match op {
+                Op::Constant(index) => self.stack.push(program.constants[index].clone()),
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Drop(count) => {
+                    self.stack.truncate(self.stack.len() - count);
+                }
+                Op::Slide(count) => {
+                    let top = self.pop();
+                    self.stack.truncate(self.stack.len() - count);
+                    self.stack.push(top);
+                }
+                Op::GetLocal(slot) => {
+                    let value = self.frame().locals[slot].clone();
+                    self.stack.push(value);
+                }
+                Op::SetLocal(slot) => {
+                    let value = self.pop();
+                    self.frame().locals[slot] = value;
+                }
+                Op::Prefix(op) => {
+                    let rh = self.pop();
+                    self.stack.push(operators::prefix(op, rh, span)?);
+                }
+                Op::Infix(op) => {
+                    let rh = self.pop();
+                    let lh = self.pop();
+                    self.stack.push(operators::infix(op, &lh, &rh, span)?);
+                }
+                Op::Jump(target) => self.frame().ip = target,
+                Op::JumpIfFalse(target) => match self.pop() {
+                    Value::Bool(false) => self.frame().ip = target,
+                    Value::Bool(true) => {}
+                    value => {
+                        return Err(RuntimeError::internal(
+                            &format!("expected a `Bool`, found `{value}`"),
+                            span,
+                        ));
+                    }
+                },
+                Op::Call(function, arg_count) => {
+                    if self.frames.len() > MAX_CALL_DEPTH {
+                        return Err(RuntimeError::stack_overflow(MAX_CALL_DEPTH, span));
+                    }
+                    let args = self.stack.split_off(self.stack.len() - arg_count);
+                    self.push_frame(function, args);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    self.frames.pop();
+                    if self.frames.is_empty() {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                Op::Struct(shape) => self.build_struct(&program.structs[shape]),
+                Op::GetField(index) => self.get_field(index, span)?,
+                Op::Variant(shape) => self.build_variant(&program.variants[shape]),
+                Op::IsVariant(shape) => {
+                    let is_variant = matches!(
+                        self.pop(),
+                        Value::Variant { name, .. } if name == program.variants[shape].name
+                    );
+                    self.stack.push(Value::Bool(is_variant));
+                }
+                Op::VariantArg(index) => self.variant_arg(index, span)?,
+                Op::NoMatch => {
+                    return Err(RuntimeError::internal("no arm matches", span));
+                }
+            }
+        }
+    }
+
+    fn build_struct(&mut self, shape: &StructShape) {
+        let values = self.stack.split_off(self.stack.len() - shape.fields.len());
+        let fields = shape
+            .fields
+            .iter()
+            .map(|(name, position)| (name.clone(), values[*position].clone()))
+            .collect();
+        self.stack.push(Value::Struct {
+            ty: shape.ty.clone(),
+            fields,
+        });
+    }
+
+    fn get_field(&mut self, index: usize, span: &Span) -> Result<(), RuntimeError> {
+        match self.pop() {
+            Value::Struct { mut fields, .. } if index < fields.len() => {
+                self.stack.push(fields.swap_remove(index).1);
+                Ok(())
+            }
+            value => Err(RuntimeError::internal(
+                &format!("no field {index} on `{value}`"),
+                span,
+            )),
+        }
+    }
+
+    fn build_variant(&mut self, shape: &VariantShape) {
+        let args = self.stack.split_off(self.stack.len() - shape.arity);
+        self.stack.push(Value::Variant {
+            ty: shape.ty.clone(),
+            name: shape.name.clone(),
+            args,
+        });
+    }
+
+    fn variant_arg(&mut self, index: usize, span: &Span) -> Result<(), RuntimeError> {
+        match self.pop() {
+            Value::Variant { mut args, .. } if index < args.len() => {
+                self.stack.push(args.swap_remove(index));
+                Ok(())
+            }
+            value => Err(RuntimeError::internal(
+                &format!("no payload value {index} on `{value}`"),
+                span,
+            )),
+        }
+    }
+
+    fn push_frame(&mut self, function: usize, mut locals: Vec<Value>) {
+        locals.resize(self.program.functions[function].locals.len(), Value::Unit);
+        self.frames.push(CallFrame {
+            function,
+            ip: 0,
+            locals,
+        });
+    }
+
+    fn frame(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("A frame is always running")
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Stack underflow")
+    }
+}
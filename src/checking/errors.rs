@@ -1,63 +1,245 @@
 use crate::{checking::scope::ScopeTypeId, common::span::Span};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CheckError {
     pub span: Span,
     pub kind: CheckErrorKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CheckErrorKind {
     TypeMismatch(Vec<ScopeTypeId>, ScopeTypeId),
-    VariableNotFound(String),
+    VariableNotFound(String, Vec<Suggestion>),
     AssignToConstVariable(String),
-    TypeNameNotFound(String),
+    TypeNameNotFound(String, Vec<Suggestion>),
     TypeNotFound(ScopeTypeId),
-    FunctionNotFound(String),
-    MethodNotFound(String),
+    FunctionNotFound(String, Vec<Suggestion>),
+    MethodNotFound(String, Vec<Suggestion>),
+    ArgumentCountMismatch(String, usize, usize),
+    ArgumentTypeMismatch(String, usize, ScopeTypeId, ScopeTypeId),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    BreakWithValueInWhile,
+    NotAStruct(ScopeTypeId),
+    UnknownField(ScopeTypeId, String),
+    MissingFields(ScopeTypeId, Vec<String>),
+    DuplicateField(String),
+    DuplicateVariant(String),
+    UnknownVariant(ScopeTypeId, String),
+    VariantArityMismatch(String, usize, usize),
+    NonExhaustiveMatch(Vec<String>),
+    UnreachableArm,
+    ModuleNotFound(String),
+    ImportCycle(Vec<String>),
+    ImportNotAtTopLevel,
+    PrivateItem(Item, String),
+}
+
+impl CheckErrorKind {
+    /// The code `alloy explain` describes the error by, a code is never reused or renumbered.
+    /// Parse errors have the `E00` codes, check errors the `E01` ones.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::TypeMismatch(..) => "E0101",
+            Self::VariableNotFound(..) => "E0102",
+            Self::AssignToConstVariable(_) => "E0103",
+            Self::TypeNameNotFound(..) => "E0104",
+            Self::TypeNotFound(_) => "E0105",
+            Self::FunctionNotFound(..) => "E0106",
+            Self::MethodNotFound(..) => "E0107",
+            Self::ArgumentCountMismatch(..) => "E0108",
+            Self::ArgumentTypeMismatch(..) => "E0109",
+            Self::BreakOutsideLoop => "E0110",
+            Self::ContinueOutsideLoop => "E0111",
+            Self::BreakWithValueInWhile => "E0112",
+            Self::NotAStruct(_) => "E0113",
+            Self::UnknownField(..) => "E0114",
+            Self::MissingFields(..) => "E0115",
+            Self::DuplicateField(_) => "E0116",
+            Self::DuplicateVariant(_) => "E0117",
+            Self::UnknownVariant(..) => "E0118",
+            Self::VariantArityMismatch(..) => "E0119",
+            Self::NonExhaustiveMatch(_) => "E0120",
+            Self::UnreachableArm => "E0121",
+            Self::ModuleNotFound(_) => "E0122",
+            Self::ImportCycle(_) => "E0123",
+            Self::ImportNotAtTopLevel => "E0124",
+            Self::PrivateItem(..) => "E0125",
+        }
+    }
+}
+
+/// The kinds of declarations that can be `pub`
+#[derive(Debug, Clone, Copy)]
+pub enum Item {
+    Variable,
+    Type,
+    Function,
+}
+
+/// A visible name close to one that isn't declared, as it would be written in its place
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub name: String,
+    /// Where the suggested name is declared, unless it is a builtin or in another module
+    pub span: Option<Span>,
 }
 
 impl CheckError {
-    pub fn type_mismatch(expected: Vec<ScopeTypeId>, got: ScopeTypeId, span: &Span) -> CheckError {
-        CheckError {
+    pub fn type_mismatch(expected: Vec<ScopeTypeId>, got: ScopeTypeId, span: &Span) -> Self {
+        Self {
             span: span.clone(),
             kind: CheckErrorKind::TypeMismatch(expected, got),
         }
     }
-    pub fn variable_not_found(name: &str, span: &Span) -> CheckError {
-        CheckError {
+    pub fn variable_not_found(name: &str, suggestions: Vec<Suggestion>, span: &Span) -> Self {
+        Self {
             span: span.clone(),
-            kind: CheckErrorKind::VariableNotFound(name.to_string()),
+            kind: CheckErrorKind::VariableNotFound(name.to_string(), suggestions),
         }
     }
-    pub fn assign_to_const_variable(name: &str, span: &Span) -> CheckError {
-        CheckError {
+    pub fn assign_to_const_variable(name: &str, span: &Span) -> Self {
+        Self {
             span: span.clone(),
             kind: CheckErrorKind::AssignToConstVariable(name.to_string()),
         }
     }
-    pub fn type_name_not_found(name: &str, span: &Span) -> CheckError {
-        CheckError {
+    pub fn type_name_not_found(name: &str, suggestions: Vec<Suggestion>, span: &Span) -> Self {
+        Self {
             span: span.clone(),
-            kind: CheckErrorKind::TypeNameNotFound(name.to_string()),
+            kind: CheckErrorKind::TypeNameNotFound(name.to_string(), suggestions),
         }
     }
-    pub fn type_not_found(ty: ScopeTypeId, span: &Span) -> CheckError {
-        CheckError {
+    pub fn type_not_found(ty: ScopeTypeId, span: &Span) -> Self {
+        Self {
             span: span.clone(),
             kind: CheckErrorKind::TypeNotFound(ty),
         }
     }
-    pub fn function_not_found(name: &str, span: &Span) -> CheckError {
-        CheckError {
+    pub fn function_not_found(name: &str, suggestions: Vec<Suggestion>, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::FunctionNotFound(name.to_string(), suggestions),
+        }
+    }
+    pub fn method_not_found(name: &str, suggestions: Vec<Suggestion>, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::MethodNotFound(name.to_string(), suggestions),
+        }
+    }
+    pub fn argument_count_mismatch(name: &str, expected: usize, got: usize, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::ArgumentCountMismatch(name.to_string(), expected, got),
+        }
+    }
+    pub fn argument_type_mismatch(
+        name: &str,
+        index: usize,
+        expected: ScopeTypeId,
+        got: ScopeTypeId,
+        span: &Span,
+    ) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::ArgumentTypeMismatch(name.to_string(), index, expected, got),
+        }
+    }
+    pub fn break_outside_loop(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::BreakOutsideLoop,
+        }
+    }
+    pub fn continue_outside_loop(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::ContinueOutsideLoop,
+        }
+    }
+    pub fn break_with_value_in_while(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::BreakWithValueInWhile,
+        }
+    }
+    pub fn not_a_struct(ty: ScopeTypeId, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::NotAStruct(ty),
+        }
+    }
+    pub fn unknown_field(ty: ScopeTypeId, name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::UnknownField(ty, name.to_string()),
+        }
+    }
+    pub fn missing_fields(ty: ScopeTypeId, names: Vec<String>, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::MissingFields(ty, names),
+        }
+    }
+    pub fn duplicate_field(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::DuplicateField(name.to_string()),
+        }
+    }
+    pub fn duplicate_variant(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::DuplicateVariant(name.to_string()),
+        }
+    }
+    pub fn unknown_variant(ty: ScopeTypeId, name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::UnknownVariant(ty, name.to_string()),
+        }
+    }
+    pub fn variant_arity_mismatch(name: &str, expected: usize, got: usize, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::VariantArityMismatch(name.to_string(), expected, got),
+        }
+    }
+    pub fn non_exhaustive_match(missing: Vec<String>, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::NonExhaustiveMatch(missing),
+        }
+    }
+    pub fn unreachable_arm(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::UnreachableArm,
+        }
+    }
+    pub fn module_not_found(path: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::ModuleNotFound(path.to_string()),
+        }
+    }
+    pub fn import_cycle(cycle: Vec<String>, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: CheckErrorKind::ImportCycle(cycle),
+        }
+    }
+    pub fn import_not_at_top_level(span: &Span) -> Self {
+        Self {
             span: span.clone(),
-            kind: CheckErrorKind::FunctionNotFound(name.to_string()),
+            kind: CheckErrorKind::ImportNotAtTopLevel,
         }
     }
-    pub fn method_not_found(name: &str, span: &Span) -> CheckError {
-        CheckError {
+    pub fn private_item(item: Item, name: &str, span: &Span) -> Self {
+        Self {
             span: span.clone(),
-            kind: CheckErrorKind::MethodNotFound(name.to_string()),
+            kind: CheckErrorKind::PrivateItem(item, name.to_string()),
         }
     }
 }
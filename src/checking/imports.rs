@@ -0,0 +1,134 @@
+use std::{path::Path, ptr};
+
+use crate::{
+    checking::errors::CheckError,
+    common::{
+        ast::Ast,
+        expression::{Expression, ExpressionKind},
+    },
+    lexing::Lexer,
+    module::{module::Module, tree::ModuleTree},
+    parsing::{Parser, errors::ParseError},
+};
+
+/// A parsed module of a program, with its imports split off
+pub struct ProgramModule<'t> {
+    pub module: &'t Module,
+    /// `geometry.shapes` for `geometry/shapes.alloy`, relative to the entry module, which has none
+    pub namespace: Option<String>,
+    /// The name each import binds, like `shapes` for `import geometry.shapes`, and the index of
+    /// the imported module
+    pub imports: Vec<(String, usize)>,
+    /// What parsed of the module, which still gets checked when there are `parse_errors`
+    pub ast: Ast,
+    pub parse_errors: Vec<ParseError>,
+}
+
+/// Parses the entry module of a tree and every module it imports, directly or not. Modules come
+/// in dependency order, so each one's imports come before it and the entry comes last.
+pub fn load_program(tree: &ModuleTree) -> Result<Vec<ProgramModule<'_>>, CheckError> {
+    let mut loader = Loader {
+        tree,
+        root: tree.entry_path.parent().unwrap_or_else(|| Path::new("")),
+        modules: vec![],
+        stack: vec![],
+    };
+    loader.load(tree.entry())?;
+    Ok(loader.modules)
+}
+
+struct Loader<'t> {
+    tree: &'t ModuleTree,
+    root: &'t Path,
+    modules: Vec<ProgramModule<'t>>,
+    /// The modules being loaded, each importing the next
+    stack: Vec<&'t Module>,
+}
+
+impl<'t> Loader<'t> {
+    fn load(&mut self, module: &'t Module) -> Result<usize, CheckError> {
+        let (ast, parse_errors) = Parser::new(Lexer::new(module)).parse_partial();
+        self.stack.push(module);
+
+        let mut imports = vec![];
+        let mut exprs = vec![];
+        for expr in ast.0 {
+            let ExpressionKind::Import(path) = &expr.kind else {
+                exprs.push(expr);
+                continue;
+            };
+            let target = self.resolve(module, path, &expr)?;
+            let index = match self.modules.iter().position(|m| ptr::eq(m.module, target)) {
+                Some(index) => index,
+                None => self.load(target)?,
+            };
+            imports.push((path.last().cloned().unwrap_or_default(), index));
+        }
+
+        self.stack.pop();
+        self.modules.push(ProgramModule {
+            module,
+            namespace: (module.file_path != self.tree.entry_path)
+                .then(|| self.namespace(&module.file_path)),
+            imports,
+            ast: Ast(exprs),
+            parse_errors,
+        });
+        Ok(self.modules.len() - 1)
+    }
+
+    /// The module `import a.b` names, `a/b.alloy` next to the importing module
+    fn resolve(
+        &self,
+        importer: &Module,
+        path: &[String],
+        expr: &Expression,
+    ) -> Result<&'t Module, CheckError> {
+        let mut file_path = importer
+            .file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        file_path.extend(path);
+        file_path.set_extension("alloy");
+        let target = self
+            .tree
+            .modules
+            .iter()
+            .find(|m| m.file_path == file_path)
+            .ok_or_else(|| CheckError::module_not_found(&path.join("."), &expr.span))?;
+
+        if let Some(start) = self.stack.iter().position(|m| ptr::eq(*m, target)) {
+            let cycle = self.stack[start..]
+                .iter()
+                .chain([&target])
+                .map(|m| self.display_name(&m.file_path))
+                .collect();
+            return Err(CheckError::import_cycle(cycle, &expr.span));
+        }
+        Ok(target)
+    }
+
+    fn namespace(&self, file_path: &Path) -> String {
+        let relative = file_path.strip_prefix(self.root).unwrap_or(file_path);
+        relative
+            .with_extension("")
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// The entry module has no namespace, so it goes by its file name in diagnostics
+    fn display_name(&self, file_path: &Path) -> String {
+        if file_path == self.tree.entry_path {
+            file_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        } else {
+            self.namespace(file_path)
+        }
+    }
+}
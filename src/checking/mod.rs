@@ -1,46 +1,129 @@
 use crate::{
     checking::{
-        errors::CheckError,
+        errors::{CheckError, Item, Suggestion},
+        imports::ProgramModule,
+        patterns::{Coverage, Domain},
         scope::{
-            BOOL_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, ScopeManager, ScopeTypeId, ScopedVar,
-            UNIT_TYPE_ID,
+            BOOL_TYPE_ID, ERROR_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, LoopKind, NEVER_TYPE_ID,
+            ScopeFunctionId, ScopeId, ScopeManager, ScopeTypeId, ScopedField, ScopedType,
+            ScopedVar, ScopedVariant, UNIT_TYPE_ID,
         },
     },
     common::{
         ast::Ast,
-        expression::{Expression, ExpressionKind, InfixOp, PrefixOp},
+        expression::{
+            Expression, ExpressionKind, Field, FieldInit, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeIdent, TypeValue, Variant,
+        },
+        span::Span,
     },
 };
 
 pub mod errors;
+pub mod imports;
+pub mod patterns;
 pub mod scope;
+pub mod suggestions;
 
+#[derive(Clone)]
 pub struct Checker {
     scope_manager: ScopeManager,
+    /// Errors of expressions that were checked as `{error}` so checking could carry on past them
+    errors: Vec<CheckError>,
 }
 
 impl Checker {
     pub fn new() -> Self {
         Self {
             scope_manager: ScopeManager::new(),
+            errors: vec![],
+        }
+    }
+
+    pub fn type_name(&self, ty_id: ScopeTypeId) -> &str {
+        self.scope_manager.type_name(ty_id).unwrap_or("{unknown}")
+    }
+
+    /// A type name as the checked program spells it, along with the type it resolved to
+    fn type_ident(&self, ty_id: ScopeTypeId) -> TypeIdent {
+        TypeIdent {
+            name: self.type_name(ty_id).to_string(),
+            ty: Some(ty_id),
         }
     }
 
     pub fn check(&mut self, ast: Ast) -> Result<Ast, Vec<CheckError>> {
         let mut checked_exprs = vec![];
-        let mut errors = vec![];
         for expr in ast.0 {
-            match self.check_expression(&expr, None) {
+            let checked = match &expr.kind {
+                // without a module tree there is nothing to import from
+                ExpressionKind::Import(path) => {
+                    Err(CheckError::module_not_found(&path.join("."), &expr.span))
+                }
+                _ => self.check_expression(&expr, None),
+            };
+            match checked {
                 Ok(checked_expr) => checked_exprs.push(checked_expr),
-                Err(err) => errors.push(err),
+                Err(err) => self.errors.push(err),
             }
         }
+        let errors = std::mem::take(&mut self.errors);
         if !errors.is_empty() {
             return Err(errors);
         }
         Ok(Ast(checked_exprs))
     }
 
+    /// Checks the modules of a program, as `load_program` orders them, into a single `Ast` whose
+    /// declarations from imported modules are named by their namespace, like `math.pi`
+    pub fn check_program(&mut self, modules: Vec<ProgramModule>) -> Result<Ast, Vec<CheckError>> {
+        let mut scopes = vec![];
+        let mut checked_exprs = vec![];
+        for module in modules {
+            let scope_id = match &module.namespace {
+                Some(namespace) => self.scope_manager.create_module_scope(namespace),
+                None => self.scope_manager.cur,
+            };
+            let original_scope = self.scope_manager.cur;
+            self.scope_manager.cur = scope_id;
+            for (name, index) in &module.imports {
+                self.scope_manager.add_namespace(name, scopes[*index]);
+            }
+            let checked = self.check(module.ast);
+            self.scope_manager.cur = original_scope;
+            // later modules would only report errors about what failed to be declared here
+            checked_exprs.extend(checked?.0);
+            scopes.push(scope_id);
+        }
+        Ok(Ast(checked_exprs))
+    }
+
+    /// Checks an expression whose failure doesn't keep the expressions around it from being
+    /// checked, recording its error and standing in an `{error}` typed expression for it
+    fn check_recovering(
+        &mut self,
+        expr: &Expression,
+        type_hint: Option<ScopeTypeId>,
+    ) -> Expression {
+        match self.check_expression(expr, type_hint) {
+            Ok(checked) => checked,
+            Err(err) => {
+                self.errors.push(err);
+                expr.with_kind(ExpressionKind::Error)
+                    .mark_checked((self.type_name(ERROR_TYPE_ID), ERROR_TYPE_ID))
+            }
+        }
+    }
+
+    /// The id of a type a declaration names, recording the error and using `{error}` for one
+    /// that doesn't resolve so the rest of the declaration is still checked
+    fn lookup_type_id_recovering(&mut self, ty: &TypeIdent, span: &Span) -> ScopeTypeId {
+        self.lookup_type_id(&ty.name, span).unwrap_or_else(|err| {
+            self.errors.push(err);
+            ERROR_TYPE_ID
+        })
+    }
+
     fn check_expression(
         &mut self,
         expr: &Expression,
@@ -50,24 +133,35 @@ impl Checker {
             ExpressionKind::LiteralBool(_) => self.expect(expr, BOOL_TYPE_ID, type_hint),
             ExpressionKind::LiteralInt(_) => self.expect(expr, INT_TYPE_ID, type_hint),
             ExpressionKind::LiteralFloat(_) => self.expect(expr, FLOAT_TYPE_ID, type_hint),
-            ExpressionKind::Ident(name) => {
+            ExpressionKind::Ident { name, .. } => {
                 let var = self.scope_manager.lookup_var(&name, self.scope_manager.cur);
                 match var {
                     Some(ScopedVar {
-                        id: _,
-                        name: _,
+                        id,
+                        name,
                         type_id,
                         mutable: _,
                         scope_id: _,
-                    }) => self.expect(expr, *type_id, type_hint),
-                    None => {
-                        return Err(CheckError::variable_not_found(&name, &expr.span));
-                    }
+                        public: _,
+                        span: _,
+                    }) => self.expect(
+                        &expr.with_kind(ExpressionKind::Ident {
+                            name: name.clone(),
+                            var: Some(*id),
+                        }),
+                        *type_id,
+                        type_hint,
+                    ),
+                    None => Err(CheckError::variable_not_found(
+                        name,
+                        self.suggest(Item::Variable, name),
+                        &expr.span,
+                    )),
                 }
             }
             ExpressionKind::Prefix { op, rh } => match op {
                 PrefixOp::Not => {
-                    let rh = self.check_expression(&rh, None)?;
+                    let rh = self.check_expression(rh, None)?;
                     self.expect(
                         &expr.with_kind(ExpressionKind::Prefix {
                             op: *op,
@@ -77,6 +171,29 @@ impl Checker {
                         type_hint,
                     )
                 }
+                PrefixOp::Negate | PrefixOp::BitNot => {
+                    let allowed_types = match op {
+                        PrefixOp::BitNot => vec![INT_TYPE_ID],
+                        _ => vec![INT_TYPE_ID, FLOAT_TYPE_ID],
+                    };
+                    // the operand keeps its own type, so `-x` for `x: X` where `type X := Int` is an `X`
+                    let rh = self.check_expression(rh, type_hint)?;
+                    let rh_type = rh.ty().1;
+                    if !allowed_types
+                        .iter()
+                        .any(|ty| self.scope_manager.is_child_type(rh_type, *ty))
+                    {
+                        return Err(CheckError::type_mismatch(allowed_types, rh_type, &rh.span));
+                    }
+                    self.expect(
+                        &expr.with_kind(ExpressionKind::Prefix {
+                            op: *op,
+                            rh: Box::new(rh),
+                        }),
+                        rh_type,
+                        type_hint,
+                    )
+                }
             },
             ExpressionKind::Infix { op, lh, rh } => match op {
                 InfixOp::Add
@@ -118,17 +235,39 @@ impl Checker {
                         type_hint,
                     )
                 }
+                InfixOp::And | InfixOp::Or => {
+                    let lh = self.check_expression(lh, Some(BOOL_TYPE_ID))?;
+                    let rh = self.check_expression(rh, Some(BOOL_TYPE_ID))?;
+                    self.expect(
+                        &expr.with_kind(ExpressionKind::Infix {
+                            op: *op,
+                            lh: Box::new(lh),
+                            rh: Box::new(rh),
+                        }),
+                        BOOL_TYPE_ID,
+                        type_hint,
+                    )
+                }
                 InfixOp::Equals | InfixOp::NotEquals => {
                     let lh = self.check_expression(&lh, None)?;
                     let rh = self.check_expression(&rh, None)?;
-                    if lh.ty().1 != rh.ty().1 {
+                    let poisoned = lh.ty().1 == ERROR_TYPE_ID || rh.ty().1 == ERROR_TYPE_ID;
+                    if lh.ty().1 != rh.ty().1 && !poisoned {
                         Err(CheckError::type_mismatch(
                             vec![lh.ty().1],
                             rh.ty().1,
                             &expr.span,
                         ))
                     } else {
-                        self.expect(expr, BOOL_TYPE_ID, type_hint)
+                        self.expect(
+                            &expr.with_kind(ExpressionKind::Infix {
+                                op: *op,
+                                lh: Box::new(lh),
+                                rh: Box::new(rh),
+                            }),
+                            BOOL_TYPE_ID,
+                            type_hint,
+                        )
                     }
                 }
             },
@@ -140,13 +279,13 @@ impl Checker {
                 let mut checked_exprs = vec![];
 
                 for (index, expr) in exprs.iter().enumerate() {
-                    let expr = self.check_expression(
+                    let expr = self.check_recovering(
                         expr,
                         match index == len - 1 {
                             true => type_hint,
                             false => None,
                         },
-                    )?;
+                    );
                     checked_exprs.push(expr);
                 }
 
@@ -163,105 +302,969 @@ impl Checker {
                     type_hint,
                 )
             }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.check_expression(condition, Some(BOOL_TYPE_ID))?;
+                let then = self.check_expression(then, type_hint)?;
+                let (then, otherwise, if_type) = match otherwise {
+                    Some(otherwise) => {
+                        let otherwise = self.check_expression(otherwise, type_hint)?;
+                        let (then, otherwise, if_type) = self.unify(&then, &otherwise)?;
+                        (then, Some(Box::new(otherwise)), if_type)
+                    }
+                    // a missing else branch evaluates to Unit, so the then branch has to as well
+                    None => (
+                        self.expect(&then, then.ty().1, Some(UNIT_TYPE_ID))?,
+                        None,
+                        UNIT_TYPE_ID,
+                    ),
+                };
+                self.expect(
+                    &expr.with_kind(ExpressionKind::If {
+                        condition: Box::new(condition),
+                        then: Box::new(then),
+                        otherwise,
+                    }),
+                    if_type,
+                    type_hint,
+                )
+            }
+            ExpressionKind::While { condition, body } => {
+                let condition = self.check_expression(condition, Some(BOOL_TYPE_ID))?;
+                self.scope_manager.enter_loop(LoopKind::While, None);
+                let body = self.check_expression(body, Some(UNIT_TYPE_ID));
+                self.scope_manager.exit_loop();
+                self.expect(
+                    &expr.with_kind(ExpressionKind::While {
+                        condition: Box::new(condition),
+                        body: Box::new(body?),
+                    }),
+                    UNIT_TYPE_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Loop(body) => {
+                self.scope_manager.enter_loop(LoopKind::Loop, type_hint);
+                let body = self.check_expression(body, Some(UNIT_TYPE_ID));
+                let frame = self.scope_manager.exit_loop();
+                // a loop without a `break` never finishes
+                let loop_type = frame
+                    .and_then(|frame| frame.break_type_id)
+                    .unwrap_or(NEVER_TYPE_ID);
+                self.expect(
+                    &expr.with_kind(ExpressionKind::Loop(Box::new(body?))),
+                    loop_type,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Break(value) => {
+                let Some(frame) = self.scope_manager.loops.last() else {
+                    return Err(CheckError::break_outside_loop(&expr.span));
+                };
+                if value.is_some() && frame.kind == LoopKind::While {
+                    return Err(CheckError::break_with_value_in_while(&expr.span));
+                }
+
+                let break_hint = frame.break_type_id.or(frame.type_hint);
+                let value = if let Some(value) = value {
+                    Some(Box::new(self.check_expression(value, break_hint)?))
+                } else {
+                    self.expect(expr, UNIT_TYPE_ID, break_hint)?;
+                    None
+                };
+                let break_type = value.as_ref().map_or(UNIT_TYPE_ID, |value| value.ty().1);
+                if let Some(frame) = self.scope_manager.loops.last_mut() {
+                    frame.break_type_id.get_or_insert(break_type);
+                }
+
+                self.expect(
+                    &expr.with_kind(ExpressionKind::Break(value)),
+                    NEVER_TYPE_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Continue => {
+                if self.scope_manager.loops.is_empty() {
+                    return Err(CheckError::continue_outside_loop(&expr.span));
+                }
+                self.expect(expr, NEVER_TYPE_ID, type_hint)
+            }
             ExpressionKind::VariableDecl {
                 name,
                 value,
                 mutable,
                 ty,
+                public,
+                ..
             } => {
-                let value = if let Some(ty) = ty {
-                    let scope_type = self
-                        .scope_manager
-                        .lookup_type(&ty.0, self.scope_manager.cur)
-                        .ok_or(CheckError::type_name_not_found(&ty.0, &expr.span))?;
-                    self.check_expression(&value, Some((*scope_type).id))?
-                } else {
-                    self.check_expression(&value, None)?
-                };
+                let type_id = ty
+                    .as_ref()
+                    .map(|ty| self.lookup_type_id_recovering(ty, &expr.span));
+                // the variable is declared even when its value doesn't check, so its uses don't
+                // report it missing
+                let value = self.check_recovering(value, type_id);
+                let type_id = type_id.unwrap_or_else(|| value.ty().1);
+                let ty = ty.as_ref().map(|_| self.type_ident(type_id));
 
-                self.scope_manager
-                    .add_var(name.as_str(), value.ty().1, *mutable);
+                let var = self.scope_manager.add_var(
+                    name.as_str(),
+                    type_id,
+                    *mutable,
+                    *public,
+                    &expr.span,
+                );
                 self.expect(
                     &expr.with_kind(ExpressionKind::VariableDecl {
-                        name: name.clone(),
+                        name: self.scope_manager.qualify(name),
                         value: Box::new(value),
                         mutable: *mutable,
-                        ty: ty.clone(),
+                        ty,
+                        public: *public,
+                        var: Some(var),
                     }),
                     UNIT_TYPE_ID,
                     type_hint,
                 )
             }
-            ExpressionKind::VariableAssignment { name, value } => {
-                let value = match self.scope_manager.lookup_var(&name, self.scope_manager.cur) {
+            ExpressionKind::VariableAssignment { name, value, .. } => {
+                let (name, var, value) = match self
+                    .scope_manager
+                    .lookup_var(name, self.scope_manager.cur)
+                {
                     Some(var) => {
                         if !var.mutable {
                             return Err(CheckError::assign_to_const_variable(&name, &expr.span));
                         }
-                        self.check_expression(&value, Some(var.type_id))?
+                        let (name, id) = (var.name.clone(), var.id);
+                        (name, id, self.check_expression(value, Some(var.type_id))?)
                     }
                     None => {
-                        return Err(CheckError::variable_not_found(&name, &expr.span));
+                        return Err(CheckError::variable_not_found(
+                            name,
+                            self.suggest(Item::Variable, name),
+                            &expr.span,
+                        ));
                     }
                 };
                 self.expect(
                     &expr.with_kind(ExpressionKind::VariableAssignment {
-                        name: name.clone(),
+                        name,
                         value: Box::new(value),
+                        var: Some(var),
                     }),
                     UNIT_TYPE_ID,
                     type_hint,
                 )
             }
-            ExpressionKind::TypeDecl { name, value } => {
-                let parent_type = self
-                    .scope_manager
-                    .lookup_type(&value.0, self.scope_manager.cur)
-                    .ok_or(CheckError::type_name_not_found(&value.0, &expr.span))?;
-                self.scope_manager.add_type(name.as_str(), parent_type.id);
-                self.expect(expr, UNIT_TYPE_ID, type_hint)
+            ExpressionKind::TypeDecl {
+                name,
+                value,
+                public,
+                ..
+            } => {
+                let qualified = self.scope_manager.qualify(name);
+                let (ty, value) = match value {
+                    TypeValue::Alias(parent) => {
+                        let parent_type = self.lookup_type_id_recovering(parent, &expr.span);
+                        let ty = self.scope_manager.add_type(
+                            name.as_str(),
+                            parent_type,
+                            *public,
+                            &expr.span,
+                        );
+                        (ty, TypeValue::Alias(self.type_ident(parent_type)))
+                    }
+                    TypeValue::Struct(fields) => {
+                        let mut scoped_fields: Vec<ScopedField> = vec![];
+                        for field in fields {
+                            if scoped_fields.iter().any(|f| f.name == field.name) {
+                                return Err(CheckError::duplicate_field(&field.name, &expr.span));
+                            }
+                            scoped_fields.push(ScopedField {
+                                name: field.name.clone(),
+                                type_id: self.lookup_type_id_recovering(&field.ty, &expr.span),
+                            });
+                        }
+                        let fields = scoped_fields
+                            .iter()
+                            .map(|field| Field {
+                                name: field.name.clone(),
+                                ty: self.type_ident(field.type_id),
+                            })
+                            .collect();
+                        let ty = self.scope_manager.add_struct_type(
+                            name.as_str(),
+                            scoped_fields,
+                            *public,
+                            &expr.span,
+                        );
+                        (ty, TypeValue::Struct(fields))
+                    }
+                    TypeValue::Enum(variants) => {
+                        let mut scoped_variants: Vec<ScopedVariant> = vec![];
+                        for variant in variants {
+                            if scoped_variants.iter().any(|v| v.name == variant.name) {
+                                return Err(CheckError::duplicate_variant(
+                                    &variant.name,
+                                    &expr.span,
+                                ));
+                            }
+                            scoped_variants.push(ScopedVariant {
+                                name: variant.name.clone(),
+                                payload_type_ids: variant
+                                    .payload
+                                    .iter()
+                                    .map(|ty| self.lookup_type_id_recovering(ty, &expr.span))
+                                    .collect(),
+                            });
+                        }
+                        let variants = scoped_variants
+                            .iter()
+                            .map(|variant| Variant {
+                                name: variant.name.clone(),
+                                payload: variant
+                                    .payload_type_ids
+                                    .iter()
+                                    .map(|id| self.type_ident(*id))
+                                    .collect(),
+                            })
+                            .collect();
+                        let ty = self.scope_manager.add_enum_type(
+                            name.as_str(),
+                            scoped_variants,
+                            *public,
+                            &expr.span,
+                        );
+                        (ty, TypeValue::Enum(variants))
+                    }
+                };
+                self.expect(
+                    &expr.with_kind(ExpressionKind::TypeDecl {
+                        name: qualified,
+                        value,
+                        public: *public,
+                        ty: Some(ty),
+                    }),
+                    UNIT_TYPE_ID,
+                    type_hint,
+                )
             }
-            ExpressionKind::FunctionCall { name, args } => {
-                // Hardcoded for now, will implement proper function definitions and lookups later
-                // also verify argument types and length matches fn type and length
-                if name != "to_unit" {
-                    return Err(CheckError::function_not_found(&name, &expr.span));
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                return_ty,
+                body,
+                public,
+                ..
+            } => {
+                let param_type_ids = params
+                    .iter()
+                    .map(|param| self.lookup_type_id_recovering(&param.ty, &expr.span))
+                    .collect::<Vec<_>>();
+                let return_type_id = return_ty.as_ref().map_or(UNIT_TYPE_ID, |ty| {
+                    self.lookup_type_id_recovering(ty, &expr.span)
+                });
+
+                let qualified = self.scope_manager.qualify(name);
+                let mut params = params
+                    .iter()
+                    .zip(&param_type_ids)
+                    .map(|(param, type_id)| Param {
+                        name: param.name.clone(),
+                        ty: self.type_ident(*type_id),
+                        var: None,
+                    })
+                    .collect::<Vec<_>>();
+                let return_ty = return_ty.as_ref().map(|_| self.type_ident(return_type_id));
+
+                // registered before the body is checked so that functions can recurse
+                let function = self.scope_manager.add_function(
+                    name.as_str(),
+                    param_type_ids.clone(),
+                    return_type_id,
+                    *public,
+                    &expr.span,
+                );
+
+                let original_scope = self.scope_manager.cur;
+                self.scope_manager.cur = self.scope_manager.create_function_scope(original_scope);
+                // loops around the declaration can't be broken out of from inside the body
+                let outer_loops = std::mem::take(&mut self.scope_manager.loops);
+                for (param, type_id) in params.iter_mut().zip(&param_type_ids) {
+                    param.var = Some(self.scope_manager.add_var(
+                        param.name.as_str(),
+                        *type_id,
+                        false,
+                        false,
+                        &expr.span,
+                    ));
                 }
-                let args = args
-                    .into_iter()
-                    .map(|arg| self.check_expression(&arg, None))
-                    .collect::<Result<Vec<_>, _>>()?;
+                let body = self.check_expression(body, Some(return_type_id));
+                self.scope_manager.loops = outer_loops;
+                self.scope_manager.cur = original_scope;
+
+                self.expect(
+                    &expr.with_kind(ExpressionKind::FunctionDecl {
+                        name: qualified,
+                        params,
+                        return_ty,
+                        body: Box::new(body?),
+                        public: *public,
+                        function: Some(function),
+                    }),
+                    UNIT_TYPE_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::FunctionCall { name, args, .. } => {
+                let (qualified, function, args, return_type_id) =
+                    match self.lookup_signature(name, self.scope_manager.cur) {
+                        Some((qualified, function, param_type_ids, return_type_id)) => (
+                            qualified,
+                            Some(function),
+                            self.check_args(
+                                name,
+                                &args.iter().collect::<Vec<_>>(),
+                                &param_type_ids,
+                                &expr.span,
+                            )?,
+                            return_type_id,
+                        ),
+                        // `to_unit` is a builtin that accepts any arguments and discards them
+                        None if name == "to_unit" => (
+                            name.clone(),
+                            None,
+                            args.iter()
+                                .map(|arg| self.check_recovering(arg, None))
+                                .collect(),
+                            UNIT_TYPE_ID,
+                        ),
+                        None => {
+                            // the arguments can have mistakes of their own, worth reporting too
+                            for arg in args {
+                                self.check_recovering(arg, None);
+                            }
+                            return Err(CheckError::function_not_found(
+                                name,
+                                self.suggest(Item::Function, name),
+                                &expr.span,
+                            ));
+                        }
+                    };
                 self.expect(
                     &expr.with_kind(ExpressionKind::FunctionCall {
-                        name: name.clone(),
+                        name: qualified,
                         args,
+                        function,
                     }),
-                    UNIT_TYPE_ID,
+                    return_type_id,
                     type_hint,
                 )
             }
-            ExpressionKind::MethodCall { name, args, caller } => {
-                // Hardcoded for now, will implement proper function definitions and lookups later
-                // also verify argument types and length matches method type and length and caller type
-                if name != "to_unit" {
-                    return Err(CheckError::method_not_found(&name, &expr.span));
+            ExpressionKind::MethodCall {
+                name, args, caller, ..
+            } => {
+                if let Some(type_id) = self.enum_type_of(caller)? {
+                    return self.check_enum_variant(expr, type_id, name, args, type_hint);
                 }
-                let caller = self.check_expression(&caller, None)?;
-                let args = args
-                    .into_iter()
-                    .map(|arg| self.check_expression(&arg, None))
-                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some((namespace, target)) = self.namespace_of(caller) {
+                    // `shapes.area(c)` calls the function `area` of the module imported as `shapes`
+                    let written = format!("{namespace}.{name}");
+                    if let Some(function) = self.scope_manager.lookup_function(name, target)
+                        && !function.public
+                    {
+                        return Err(CheckError::private_item(
+                            Item::Function,
+                            &written,
+                            &expr.span,
+                        ));
+                    }
+                    let Some((qualified, function, param_type_ids, return_type_id)) =
+                        self.lookup_signature(name, target)
+                    else {
+                        for arg in args {
+                            self.check_recovering(arg, None);
+                        }
+                        return Err(CheckError::function_not_found(
+                            &written,
+                            self.suggest(Item::Function, &written),
+                            &expr.span,
+                        ));
+                    };
+                    let args = self.check_args(
+                        &written,
+                        &args.iter().collect::<Vec<_>>(),
+                        &param_type_ids,
+                        &expr.span,
+                    )?;
+                    return self.expect(
+                        &expr.with_kind(ExpressionKind::FunctionCall {
+                            name: qualified,
+                            args,
+                            function: Some(function),
+                        }),
+                        return_type_id,
+                        type_hint,
+                    );
+                }
+                // `caller.name(args)` calls the function `name` with the caller as its first argument
+                let (qualified, function, caller, args, return_type_id) =
+                    match self.lookup_signature(name, self.scope_manager.cur) {
+                        Some((qualified, function, param_type_ids, return_type_id)) => {
+                            let all_args = std::iter::once(caller.as_ref())
+                                .chain(args.iter())
+                                .collect::<Vec<_>>();
+                            let mut args =
+                                self.check_args(name, &all_args, &param_type_ids, &expr.span)?;
+                            let caller = args.remove(0);
+                            (qualified, Some(function), caller, args, return_type_id)
+                        }
+                        None if name == "to_unit" => (
+                            name.clone(),
+                            None,
+                            self.check_expression(caller, None)?,
+                            args.iter()
+                                .map(|arg| self.check_recovering(arg, None))
+                                .collect(),
+                            UNIT_TYPE_ID,
+                        ),
+                        None => {
+                            for arg in std::iter::once(caller.as_ref()).chain(args) {
+                                self.check_recovering(arg, None);
+                            }
+                            return Err(CheckError::method_not_found(
+                                name,
+                                self.suggest(Item::Function, name),
+                                &expr.span,
+                            ));
+                        }
+                    };
                 self.expect(
                     &expr.with_kind(ExpressionKind::MethodCall {
-                        name: name.clone(),
+                        name: qualified,
                         args,
+                        function,
                         caller: Box::new(caller),
                     }),
-                    UNIT_TYPE_ID,
+                    return_type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::StructLiteral { name, fields } => {
+                let type_id = self.lookup_type_id(name, &expr.span)?;
+                let Some(layout) = self.scope_manager.struct_fields(type_id) else {
+                    return Err(CheckError::not_a_struct(type_id, &expr.span));
+                };
+                let layout = layout
+                    .iter()
+                    .map(|field| (field.name.clone(), field.type_id))
+                    .collect::<Vec<_>>();
+
+                let mut checked_fields: Vec<FieldInit> = vec![];
+                for field in fields {
+                    if checked_fields.iter().any(|f| f.name == field.name) {
+                        return Err(CheckError::duplicate_field(&field.name, &field.value.span));
+                    }
+                    let Some((_, field_type_id)) = layout.iter().find(|(n, _)| *n == field.name)
+                    else {
+                        return Err(CheckError::unknown_field(
+                            type_id,
+                            &field.name,
+                            &field.value.span,
+                        ));
+                    };
+                    checked_fields.push(FieldInit {
+                        name: field.name.clone(),
+                        value: self.check_recovering(&field.value, Some(*field_type_id)),
+                    });
+                }
+
+                let missing = layout
+                    .iter()
+                    .filter(|(n, _)| !checked_fields.iter().any(|f| f.name == *n))
+                    .map(|(n, _)| n.clone())
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    return Err(CheckError::missing_fields(type_id, missing, &expr.span));
+                }
+
+                self.expect(
+                    &expr.with_kind(ExpressionKind::StructLiteral {
+                        name: self.type_name(type_id).to_string(),
+                        fields: checked_fields,
+                    }),
+                    type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                if let Some(type_id) = self.enum_type_of(target)? {
+                    return self.check_enum_variant(expr, type_id, field, &[], type_hint);
+                }
+                if let Some((namespace, target)) = self.namespace_of(target) {
+                    // a module's top level variables are no more visible in function bodies
+                    // through its namespace than they are in its own function bodies
+                    let var = Some(target)
+                        .filter(|_| !self.scope_manager.in_function(self.scope_manager.cur))
+                        .and_then(|target| self.scope_manager.lookup_var(field, target))
+                        .map(|var| (var.name.clone(), var.id, var.type_id, var.public));
+                    let written = format!("{namespace}.{field}");
+                    let Some((qualified, id, type_id, public)) = var else {
+                        return Err(CheckError::variable_not_found(
+                            &written,
+                            self.suggest(Item::Variable, &written),
+                            &expr.span,
+                        ));
+                    };
+                    if !public {
+                        return Err(CheckError::private_item(
+                            Item::Variable,
+                            &written,
+                            &expr.span,
+                        ));
+                    }
+                    return self.expect(
+                        &expr.with_kind(ExpressionKind::Ident {
+                            name: qualified,
+                            var: Some(id),
+                        }),
+                        type_id,
+                        type_hint,
+                    );
+                }
+                let target = self.check_expression(target, None)?;
+                let target_type = target.ty().1;
+                let field_type_id = self
+                    .scope_manager
+                    .struct_fields(target_type)
+                    .and_then(|fields| fields.iter().find(|f| f.name == *field))
+                    .map(|f| f.type_id)
+                    // the target's error is reported already
+                    .or_else(|| (target_type == ERROR_TYPE_ID).then_some(ERROR_TYPE_ID))
+                    .ok_or_else(|| CheckError::unknown_field(target_type, field, &expr.span))?;
+                self.expect(
+                    &expr.with_kind(ExpressionKind::FieldAccess {
+                        target: Box::new(target),
+                        field: field.clone(),
+                    }),
+                    field_type_id,
                     type_hint,
                 )
             }
+            ExpressionKind::EnumVariant { ty, variant, args } => {
+                let type_id = self.lookup_type_id(ty, &expr.span)?;
+                self.check_enum_variant(expr, type_id, variant, args, type_hint)
+            }
+            ExpressionKind::Import(_) => Err(CheckError::import_not_at_top_level(&expr.span)),
+            // the parse error is reported already, `{error}` keeps it from causing type errors
+            ExpressionKind::Error => self.expect(expr, ERROR_TYPE_ID, type_hint),
+            ExpressionKind::Match { scrutinee, arms } => {
+                let scrutinee = self.check_expression(scrutinee, None)?;
+                let scrutinee_type = scrutinee.ty().1;
+                let mut coverage = Coverage::new(self.domain_of(scrutinee_type));
+                let mut checked_arms: Vec<MatchArm> = vec![];
+                // an expression carrying the common type of the arms checked so far
+                let mut unified: Option<Expression> = None;
+
+                for arm in arms {
+                    if coverage.covers(&arm.pattern) {
+                        return Err(CheckError::unreachable_arm(&arm.pattern.span));
+                    }
+
+                    let original_scope = self.scope_manager.cur;
+                    self.scope_manager.cur = self.scope_manager.create_scope(original_scope);
+                    let checked =
+                        self.check_pattern(&arm.pattern, scrutinee_type)
+                            .and_then(|pattern| {
+                                Ok((pattern, self.check_expression(&arm.body, type_hint)?))
+                            });
+                    self.scope_manager.cur = original_scope;
+                    let (pattern, body) = checked?;
+
+                    coverage.add(&arm.pattern);
+                    unified = Some(match unified {
+                        Some(unified) => self.unify(&unified, &body)?.0,
+                        None => body.clone(),
+                    });
+                    checked_arms.push(MatchArm { pattern, body });
+                }
+
+                let missing = coverage.missing();
+                // which values an `{error}` scrutinee has is unknown
+                if !missing.is_empty() && scrutinee_type != ERROR_TYPE_ID {
+                    return Err(CheckError::non_exhaustive_match(missing, &expr.span));
+                }
+
+                let match_type = unified.map_or(NEVER_TYPE_ID, |unified| unified.ty().1);
+                let arms = checked_arms
+                    .into_iter()
+                    .map(|arm| {
+                        Ok(MatchArm {
+                            body: self.expect(&arm.body, arm.body.ty().1, Some(match_type))?,
+                            pattern: arm.pattern,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.expect(
+                    &expr.with_kind(ExpressionKind::Match {
+                        scrutinee: Box::new(scrutinee),
+                        arms,
+                    }),
+                    match_type,
+                    type_hint,
+                )
+            }
+        }
+    }
+
+    /// `Shape.Circle(1.0)` parses as a method call on `Shape`, this resolves `Shape`, or
+    /// `shapes.Shape` for an enum from an imported module, when it names an enum type rather
+    /// than a variable
+    fn enum_type_of(&self, caller: &Expression) -> Result<Option<ScopeTypeId>, CheckError> {
+        let ty = match &caller.kind {
+            ExpressionKind::Ident { name, .. } if !self.is_var(name) => self.resolve_type(name),
+            ExpressionKind::FieldAccess { target, field } => self
+                .namespace_of(target)
+                .and_then(|(namespace, target)| {
+                    self.scope_manager
+                        .lookup_type(field, target)
+                        .map(|ty| (ty, format!("{namespace}.{field}")))
+                })
+                .map(|(ty, written)| Self::expect_public_type(ty, &written, &caller.span))
+                .transpose()?,
+            _ => None,
+        };
+        Ok(ty.and_then(|ty| self.scope_manager.enum_variants(ty.id).map(|_| ty.id)))
+    }
+
+    /// The module a name like `shapes` in `shapes.area(c)` was imported as, unless a variable
+    /// hides it
+    fn namespace_of<'e>(&self, expr: &'e Expression) -> Option<(&'e str, ScopeId)> {
+        let ExpressionKind::Ident { name, .. } = &expr.kind else {
+            return None;
+        };
+        if self.is_var(name) {
+            return None;
         }
+        self.scope_manager
+            .lookup_namespace(name, self.scope_manager.cur)
+            .map(|target| (name.as_str(), target))
+    }
+
+    fn is_var(&self, name: &str) -> bool {
+        self.scope_manager
+            .lookup_var(name, self.scope_manager.cur)
+            .is_some()
+    }
+
+    fn check_enum_variant(
+        &mut self,
+        expr: &Expression,
+        type_id: ScopeTypeId,
+        variant: &str,
+        args: &[Expression],
+        type_hint: Option<ScopeTypeId>,
+    ) -> Result<Expression, CheckError> {
+        let payload_type_ids = self
+            .variant_payload(type_id, variant)
+            .ok_or_else(|| CheckError::unknown_variant(type_id, variant, &expr.span))?;
+        if args.len() != payload_type_ids.len() {
+            return Err(CheckError::variant_arity_mismatch(
+                variant,
+                payload_type_ids.len(),
+                args.len(),
+                &expr.span,
+            ));
+        }
+        let args = args
+            .iter()
+            .zip(&payload_type_ids)
+            .map(|(arg, type_id)| self.check_recovering(arg, Some(*type_id)))
+            .collect();
+        self.expect(
+            &expr.with_kind(ExpressionKind::EnumVariant {
+                ty: self.type_name(type_id).to_string(),
+                variant: variant.to_string(),
+                args,
+            }),
+            type_id,
+            type_hint,
+        )
+    }
+
+    fn variant_payload(&self, type_id: ScopeTypeId, variant: &str) -> Option<Vec<ScopeTypeId>> {
+        self.scope_manager
+            .enum_variants(type_id)?
+            .iter()
+            .find(|v| v.name == variant)
+            .map(|v| v.payload_type_ids.clone())
+    }
+
+    fn domain_of(&self, type_id: ScopeTypeId) -> Domain {
+        if type_id == ERROR_TYPE_ID {
+            return Domain::Open;
+        }
+        if self.scope_manager.is_child_type(type_id, BOOL_TYPE_ID) {
+            return Domain::Bool;
+        }
+        self.scope_manager
+            .enum_variants(type_id)
+            .map_or(Domain::Open, |variants| Domain::Enum {
+                ty: self.type_name(type_id).to_string(),
+                variants: variants
+                    .iter()
+                    .map(|v| (v.name.clone(), v.payload_type_ids.len()))
+                    .collect(),
+            })
+    }
+
+    /// Checks that a pattern can match values of `expected`, declaring the variables it binds
+    fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        expected: ScopeTypeId,
+    ) -> Result<Pattern, CheckError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(pattern.clone()),
+            PatternKind::Binding { name, .. } => {
+                let var = self.scope_manager.add_var(
+                    name.as_str(),
+                    expected,
+                    false,
+                    false,
+                    &pattern.span,
+                );
+                Ok(Pattern {
+                    kind: PatternKind::Binding {
+                        name: name.clone(),
+                        var: Some(var),
+                    },
+                    span: pattern.span.clone(),
+                })
+            }
+            PatternKind::LiteralInt(_) => self.expect_pattern(pattern, INT_TYPE_ID, expected),
+            PatternKind::LiteralBool(_) => self.expect_pattern(pattern, BOOL_TYPE_ID, expected),
+            PatternKind::Variant { ty, name, args } => {
+                let type_id = self.lookup_type_id(&ty.name, &pattern.span)?;
+                let owner = |id| self.scope_manager.layout_owner(id).map(|t| t.id);
+                let same_enum = owner(type_id).is_some() && owner(type_id) == owner(expected);
+                if !same_enum && expected != ERROR_TYPE_ID {
+                    return Err(CheckError::type_mismatch(
+                        vec![expected],
+                        type_id,
+                        &pattern.span,
+                    ));
+                }
+                let payload_type_ids = self
+                    .variant_payload(type_id, name)
+                    .ok_or_else(|| CheckError::unknown_variant(type_id, name, &pattern.span))?;
+                if args.len() != payload_type_ids.len() {
+                    return Err(CheckError::variant_arity_mismatch(
+                        name,
+                        payload_type_ids.len(),
+                        args.len(),
+                        &pattern.span,
+                    ));
+                }
+                let args = args
+                    .iter()
+                    .zip(payload_type_ids)
+                    .map(|(arg, type_id)| self.check_pattern(arg, type_id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Pattern {
+                    kind: PatternKind::Variant {
+                        ty: self.type_ident(type_id),
+                        name: name.clone(),
+                        args,
+                    },
+                    span: pattern.span.clone(),
+                })
+            }
+        }
+    }
+
+    fn expect_pattern(
+        &self,
+        pattern: &Pattern,
+        literal_type: ScopeTypeId,
+        expected: ScopeTypeId,
+    ) -> Result<Pattern, CheckError> {
+        if self.scope_manager.is_child_type(expected, literal_type) {
+            Ok(pattern.clone())
+        } else {
+            Err(CheckError::type_mismatch(
+                vec![expected],
+                literal_type,
+                &pattern.span,
+            ))
+        }
+    }
+
+    /// A type by the name it is written with, like `Point`, or `shapes.Point` for a type from
+    /// the module imported as `shapes`
+    fn resolve_type(&self, name: &str) -> Option<&ScopedType> {
+        match name.split_once('.') {
+            Some((namespace, name)) => {
+                let target = self
+                    .scope_manager
+                    .lookup_namespace(namespace, self.scope_manager.cur)?;
+                self.scope_manager.lookup_type(name, target)
+            }
+            None => self.scope_manager.lookup_type(name, self.scope_manager.cur),
+        }
+    }
+
+    fn lookup_type_id(&self, name: &str, span: &Span) -> Result<ScopeTypeId, CheckError> {
+        let scope_type = self.resolve_type(name).ok_or_else(|| {
+            CheckError::type_name_not_found(name, self.suggest(Item::Type, name), span)
+        })?;
+        if name.contains('.') {
+            Self::expect_public_type(scope_type, name, span)?;
+        }
+        Ok(scope_type.id)
+    }
+
+    /// Types reached through the namespace of another module have to be `pub`
+    fn expect_public_type<'s>(
+        ty: &'s ScopedType,
+        written: &str,
+        span: &Span,
+    ) -> Result<&'s ScopedType, CheckError> {
+        if ty.public {
+            Ok(ty)
+        } else {
+            Err(CheckError::private_item(Item::Type, written, span))
+        }
+    }
+
+    /// Visible names close to one that isn't declared. A name written with a namespace, like
+    /// `shapes.aera`, is looked for among the `pub` declarations of that module.
+    fn suggest(&self, item: Item, written: &str) -> Vec<Suggestion> {
+        let (namespace, name) = match written.rsplit_once('.') {
+            Some((namespace, name)) => (Some(namespace), name),
+            None => (None, written),
+        };
+        let scope_id = match namespace {
+            Some(namespace) => {
+                match self
+                    .scope_manager
+                    .lookup_namespace(namespace, self.scope_manager.cur)
+                {
+                    Some(target) => target,
+                    None => return vec![],
+                }
+            }
+            None => self.scope_manager.cur,
+        };
+        let candidates = match item {
+            // a module's variables aren't reachable through its namespace from function bodies
+            Item::Variable
+                if namespace.is_some()
+                    && self.scope_manager.in_function(self.scope_manager.cur) =>
+            {
+                vec![]
+            }
+            Item::Variable => self
+                .scope_manager
+                .visible_vars(scope_id)
+                .into_iter()
+                .map(|(name, var)| (name, var.scope_id, var.public, Some(var.span.clone())))
+                .collect(),
+            Item::Type => self
+                .scope_manager
+                .visible_types(scope_id)
+                .into_iter()
+                .map(|(name, ty)| (name, ty.scope_id, ty.public, ty.span.clone()))
+                .collect(),
+            Item::Function => self
+                .scope_manager
+                .visible_functions(scope_id)
+                .into_iter()
+                .map(|(name, function)| {
+                    let span = Some(function.span.clone());
+                    (name, function.scope_id, function.public, span)
+                })
+                .collect::<Vec<_>>(),
+        };
+        let candidates = candidates
+            .into_iter()
+            .filter(|(_, declared_in, public, _)| {
+                namespace.is_none() || (*declared_in == scope_id && *public)
+            })
+            .map(|(name, _, _, span)| Suggestion {
+                name: name.to_string(),
+                // diagnostics are rendered against the module the name is used in, so a span in
+                // another module would point at the wrong source
+                span: span.filter(|_| namespace.is_none()),
+            });
+        suggestions::rank(name, candidates)
+            .into_iter()
+            .map(|suggestion| Suggestion {
+                name: namespace.map_or_else(
+                    || suggestion.name.clone(),
+                    |namespace| format!("{namespace}.{}", suggestion.name),
+                ),
+                ..suggestion
+            })
+            .collect()
+    }
+
+    /// The name a function is emitted under, its parameter types and its return type
+    fn lookup_signature(
+        &self,
+        name: &str,
+        scope_id: ScopeId,
+    ) -> Option<(String, ScopeFunctionId, Vec<ScopeTypeId>, ScopeTypeId)> {
+        self.scope_manager
+            .lookup_function(name, scope_id)
+            .map(|function| {
+                (
+                    function.name.clone(),
+                    function.id,
+                    function.param_type_ids.clone(),
+                    function.return_type_id,
+                )
+            })
+    }
+
+    fn check_args(
+        &mut self,
+        name: &str,
+        args: &[&Expression],
+        param_type_ids: &[ScopeTypeId],
+        span: &Span,
+    ) -> Result<Vec<Expression>, CheckError> {
+        if args.len() != param_type_ids.len() {
+            return Err(CheckError::argument_count_mismatch(
+                name,
+                param_type_ids.len(),
+                args.len(),
+                span,
+            ));
+        }
+        let args = args
+            .iter()
+            .zip(param_type_ids)
+            .enumerate()
+            .map(|(index, (arg, param_type_id))| {
+                let arg = self.check_recovering(arg, None);
+                self.expect(&arg, arg.ty().1, Some(*param_type_id))
+                    .unwrap_or_else(|_| {
+                        self.errors.push(CheckError::argument_type_mismatch(
+                            name,
+                            index,
+                            *param_type_id,
+                            arg.ty().1,
+                            &arg.span,
+                        ));
+                        arg
+                    })
+            })
+            .collect();
+        Ok(args)
     }
 
     fn expect_with_types(
@@ -269,18 +1272,32 @@ impl Checker {
         expr: &Expression,
         type_hints: Vec<ScopeTypeId>,
     ) -> Result<(Expression, ScopeTypeId), CheckError> {
-        let expr_type = expr.ty();
-        for type_hint in &type_hints {
-            match self.check_expression(expr, Some(*type_hint)) {
-                Ok(checked_expr) => return Ok((checked_expr, *type_hint)),
-                Err(_) => (),
-            }
+        // checked once, as checking again for every type would be exponential in nested operators
+        let checked_expr = self.check_expression(expr, None)?;
+        let found = checked_expr.ty().1;
+        type_hints
+            .iter()
+            .find_map(|type_hint| {
+                self.expect(&checked_expr, found, Some(*type_hint))
+                    .ok()
+                    .map(|checked_expr| (checked_expr, *type_hint))
+            })
+            .ok_or_else(|| CheckError::type_mismatch(type_hints, found, &expr.span))
+    }
+
+    /// Finds a common type for two checked expressions, marking both with it
+    fn unify(
+        &self,
+        first: &Expression,
+        second: &Expression,
+    ) -> Result<(Expression, Expression, ScopeTypeId), CheckError> {
+        let (first_type, second_type) = (first.ty().1, second.ty().1);
+        if let Ok(second) = self.expect(second, second_type, Some(first_type)) {
+            return Ok((first.clone(), second, first_type));
         }
-        Err(CheckError::type_mismatch(
-            type_hints,
-            expr_type.1,
-            &expr.span,
-        ))
+        self.expect(first, first_type, Some(second_type))
+            .map(|first| (first, second.clone(), second_type))
+            .map_err(|_| CheckError::type_mismatch(vec![first_type], second_type, &second.span))
     }
 
     fn choose_btw_types(
@@ -320,6 +1337,7 @@ impl Checker {
             .scope_manager
             .is_child_type(exp_scope_type.id, got_scope_type.id)
             && got_scope_type.scope_id == 0
+            && exp_scope_type.id != NEVER_TYPE_ID
         {
             return Ok(expr.mark_checked((&exp_scope_type.name, exp_scope_type.id)));
         }
@@ -0,0 +1,84 @@
+use crate::common::expression::{Pattern, PatternKind};
+
+/// The values a match scrutinee can take, as far as exhaustiveness is concerned
+pub enum Domain {
+    Bool,
+    Enum {
+        ty: String,
+        // variant names and the number of values they carry
+        variants: Vec<(String, usize)>,
+    },
+    // Int, Float and structs can only be covered by a wildcard or binding
+    Open,
+}
+
+/// Tracks which values the arms of a match have handled so far.
+/// Sub-patterns that can fail, like `Opt.Some(0)`, don't count towards covering their variant.
+pub struct Coverage {
+    domain: Domain,
+    catch_all: bool,
+    variants: Vec<String>,
+    ints: Vec<isize>,
+    bools: Vec<bool>,
+}
+
+impl Coverage {
+    pub const fn new(domain: Domain) -> Self {
+        Self {
+            domain,
+            catch_all: false,
+            variants: vec![],
+            ints: vec![],
+            bools: vec![],
+        }
+    }
+
+    /// Whether every value the pattern matches is already matched by an earlier pattern
+    pub fn covers(&self, pattern: &Pattern) -> bool {
+        if self.missing().is_empty() {
+            return true;
+        }
+        match &pattern.kind {
+            PatternKind::Wildcard | PatternKind::Binding { .. } => false,
+            PatternKind::LiteralInt(value) => self.ints.contains(value),
+            PatternKind::LiteralBool(value) => self.bools.contains(value),
+            PatternKind::Variant { name, .. } => self.variants.contains(name),
+        }
+    }
+
+    pub fn add(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Wildcard | PatternKind::Binding { .. } => self.catch_all = true,
+            PatternKind::LiteralInt(value) => self.ints.push(*value),
+            PatternKind::LiteralBool(value) => self.bools.push(*value),
+            PatternKind::Variant { name, args, .. } => {
+                if args.iter().all(Pattern::is_irrefutable) {
+                    self.variants.push(name.clone());
+                }
+            }
+        }
+    }
+
+    /// Patterns for the values no arm matches yet
+    pub fn missing(&self) -> Vec<String> {
+        if self.catch_all {
+            return vec![];
+        }
+        match &self.domain {
+            Domain::Bool => [true, false]
+                .iter()
+                .filter(|value| !self.bools.contains(value))
+                .map(ToString::to_string)
+                .collect(),
+            Domain::Enum { ty, variants } => variants
+                .iter()
+                .filter(|(name, _)| !self.variants.contains(name))
+                .map(|(name, payload)| match payload {
+                    0 => format!("{ty}.{name}"),
+                    _ => format!("{ty}.{name}(..)"),
+                })
+                .collect(),
+            Domain::Open => vec!["_".to_string()],
+        }
+    }
+}
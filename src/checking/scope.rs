@@ -1,47 +1,149 @@
+use crate::common::span::Span;
+
 pub type ScopeId = usize;
 pub type ScopeTypeId = usize;
 pub type ScopeVarId = usize;
+pub type ScopeFunctionId = usize;
 
 pub const UNIT_TYPE_ID: ScopeTypeId = 1;
 pub const INT_TYPE_ID: ScopeTypeId = 2;
 pub const FLOAT_TYPE_ID: ScopeTypeId = 3;
 pub const BOOL_TYPE_ID: ScopeTypeId = 4;
+// the type of expressions that never produce a value, like `break`
+pub const NEVER_TYPE_ID: ScopeTypeId = 5;
+// the type of expressions that failed to check, it fits everywhere so one error doesn't cause more
+pub const ERROR_TYPE_ID: ScopeTypeId = 6;
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum ScopeKind {
+    Block,
+    // variables declared outside a function body are not visible inside it
+    Function,
+}
 
+#[derive(Clone)]
 pub struct Scope {
     pub id: ScopeId,
     pub parent: Option<ScopeId>,
+    pub kind: ScopeKind,
+    // set for the top level of an imported module, whose declarations are stored qualified with
+    // it, like `shapes.Point`, so they don't collide with other modules' in the checked program
+    pub namespace: Option<String>,
 }
 
 impl Scope {
-    pub fn child(id: ScopeId, parent: ScopeId) -> Self {
+    pub fn child(id: ScopeId, parent: ScopeId, kind: ScopeKind) -> Self {
         Scope {
             id,
             parent: Some(parent),
+            kind,
+            namespace: None,
         }
     }
+
+    /// Whether a declaration stored as `stored` in this scope is called `name` in it
+    fn declares(&self, stored: &str, name: &str) -> bool {
+        self.local_name(stored) == Some(name)
+    }
+
+    /// The name a declaration stored as `stored` in this scope is called in it
+    fn local_name<'a>(&self, stored: &'a str) -> Option<&'a str> {
+        self.namespace.as_ref().map_or(Some(stored), |namespace| {
+            stored
+                .strip_prefix(namespace.as_str())
+                .and_then(|rest| rest.strip_prefix('.'))
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct ScopedField {
+    pub name: String,
+    pub type_id: ScopeTypeId,
+}
+
+#[derive(Clone)]
+pub struct ScopedVariant {
+    pub name: String,
+    pub payload_type_ids: Vec<ScopeTypeId>,
 }
 
+#[derive(Clone)]
+pub enum TypeLayout {
+    // builtins and aliases, which share the layout of their parent
+    Nominal,
+    Struct(Vec<ScopedField>),
+    Enum(Vec<ScopedVariant>),
+}
+
+#[derive(Clone)]
 pub struct ScopedType {
     pub id: ScopeTypeId,
     pub parent_id: Option<ScopeTypeId>,
     pub name: String,
     pub scope_id: ScopeId,
+    pub layout: TypeLayout,
+    /// Visible to the modules that import the one it is declared in
+    pub public: bool,
+    /// Where the type is declared, builtins have no declaration
+    pub span: Option<Span>,
 }
 
+#[derive(Clone)]
 pub struct ScopedVar {
     pub id: ScopeVarId,
     pub name: String,
     pub type_id: ScopeTypeId,
     pub mutable: bool,
     pub scope_id: ScopeId,
+    pub public: bool,
+    pub span: Span,
+}
+
+#[derive(Clone)]
+pub struct ScopedFunction {
+    pub id: ScopeFunctionId,
+    pub name: String,
+    pub param_type_ids: Vec<ScopeTypeId>,
+    pub return_type_id: ScopeTypeId,
+    pub scope_id: ScopeId,
+    pub public: bool,
+    pub span: Span,
+}
+
+// an `import`, naming the top level scope of the imported module
+#[derive(Clone)]
+pub struct ScopedNamespace {
+    pub name: String,
+    pub target: ScopeId,
+    pub scope_id: ScopeId,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum LoopKind {
+    While,
+    Loop,
+}
+
+#[derive(Clone)]
+pub struct LoopFrame {
+    pub kind: LoopKind,
+    pub type_hint: Option<ScopeTypeId>,
+    // the type of the first `break` in the loop, if any
+    pub break_type_id: Option<ScopeTypeId>,
 }
 
+#[derive(Clone)]
 pub struct ScopeManager {
     next_type_id: ScopeTypeId,
     next_var_id: ScopeVarId,
+    next_function_id: ScopeFunctionId,
     scopes: Vec<Scope>,
     types: Vec<ScopedType>,
     vars: Vec<ScopedVar>,
+    functions: Vec<ScopedFunction>,
+    namespaces: Vec<ScopedNamespace>,
+    pub loops: Vec<LoopFrame>,
     pub cur: ScopeId,
 }
 
@@ -50,50 +152,98 @@ impl ScopeManager {
         let global_scope = Scope {
             id: 0,
             parent: None,
+            kind: ScopeKind::Block,
+            namespace: None,
         };
-        let cur_scope = Scope {
-            id: 1,
-            parent: Some(global_scope.id),
-        };
+        let cur_scope = Scope::child(1, global_scope.id, ScopeKind::Block);
         let types = vec![
             ScopedType {
                 id: UNIT_TYPE_ID,
                 parent_id: None,
                 name: "Unit".to_string(),
                 scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
             },
             ScopedType {
                 id: INT_TYPE_ID,
                 parent_id: None,
                 name: "Int".to_string(),
                 scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
             },
             ScopedType {
                 id: FLOAT_TYPE_ID,
                 parent_id: None,
                 name: "Float".to_string(),
                 scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
             },
             ScopedType {
                 id: BOOL_TYPE_ID,
                 parent_id: None,
                 name: "Bool".to_string(),
                 scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
+            },
+            ScopedType {
+                id: NEVER_TYPE_ID,
+                parent_id: None,
+                name: "Never".to_string(),
+                scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
+            },
+            ScopedType {
+                id: ERROR_TYPE_ID,
+                parent_id: None,
+                name: "{error}".to_string(),
+                scope_id: global_scope.id,
+                layout: TypeLayout::Nominal,
+                public: true,
+                span: None,
             },
         ];
         Self {
             next_type_id: types.len() + 1,
             next_var_id: 0,
+            next_function_id: 0,
             cur: cur_scope.id,
             scopes: vec![global_scope, cur_scope],
             types,
             vars: vec![],
+            functions: vec![],
+            namespaces: vec![],
+            loops: vec![],
         }
     }
 
     pub fn create_scope(&mut self, parent: ScopeId) -> ScopeId {
+        self.push_scope(parent, ScopeKind::Block)
+    }
+
+    pub fn create_function_scope(&mut self, parent: ScopeId) -> ScopeId {
+        self.push_scope(parent, ScopeKind::Function)
+    }
+
+    /// The top level scope of an imported module, beside the entry module's rather than in it
+    pub fn create_module_scope(&mut self, namespace: &str) -> ScopeId {
+        let id = self.push_scope(0, ScopeKind::Block);
+        self.scopes[id].namespace = Some(namespace.to_string());
+        id
+    }
+
+    fn push_scope(&mut self, parent: ScopeId, kind: ScopeKind) -> ScopeId {
         let id = self.scopes.len();
-        let scope = Scope::child(id, parent);
+        let scope = Scope::child(id, parent, kind);
         self.scopes.push(scope);
         id
     }
@@ -103,7 +253,8 @@ impl ScopeManager {
         if let Some(var) = self
             .types
             .iter()
-            .find(|t| t.name == ty_name && t.scope_id == scope_id)
+            .rev()
+            .find(|t| t.scope_id == scope_id && scope.declares(&t.name, ty_name))
         {
             Some(var)
         } else if let Some(parent_id) = scope.parent {
@@ -124,11 +275,48 @@ impl ScopeManager {
         } else if let Some(parent_id) = scope.parent {
             self.get_type(ty_id, parent_id)
         } else {
-            None
+            // values can outlive the import that named their type, like a `shapes.Point` returned
+            // by a function from another module, so modules' top level types are always visible
+            self.types
+                .iter()
+                .find(|t| t.id == ty_id && self.scopes[t.scope_id].namespace.is_some())
+        }
+    }
+
+    pub fn type_name(&self, ty_id: ScopeTypeId) -> Option<&str> {
+        self.types
+            .iter()
+            .find(|t| t.id == ty_id)
+            .map(|t| t.name.as_str())
+    }
+
+    /// The struct or enum type an alias ultimately refers to
+    pub fn layout_owner(&self, ty_id: ScopeTypeId) -> Option<&ScopedType> {
+        let ty = self.types.iter().find(|t| t.id == ty_id)?;
+        match &ty.layout {
+            TypeLayout::Nominal => self.layout_owner(ty.parent_id?),
+            _ => Some(ty),
+        }
+    }
+
+    pub fn struct_fields(&self, ty_id: ScopeTypeId) -> Option<&Vec<ScopedField>> {
+        match &self.layout_owner(ty_id)?.layout {
+            TypeLayout::Struct(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn enum_variants(&self, ty_id: ScopeTypeId) -> Option<&Vec<ScopedVariant>> {
+        match &self.layout_owner(ty_id)?.layout {
+            TypeLayout::Enum(variants) => Some(variants),
+            _ => None,
         }
     }
 
     pub fn is_child_type(&self, child_id: ScopeTypeId, parent_id: ScopeTypeId) -> bool {
+        if child_id == NEVER_TYPE_ID || child_id == ERROR_TYPE_ID || parent_id == ERROR_TYPE_ID {
+            return true;
+        }
         let child_type = self.types.iter().find(|t| t.id == child_id);
         if let Some(child_type) = child_type {
             if child_type.id == parent_id {
@@ -141,14 +329,29 @@ impl ScopeManager {
         false
     }
 
+    pub fn enter_loop(&mut self, kind: LoopKind, type_hint: Option<ScopeTypeId>) {
+        self.loops.push(LoopFrame {
+            kind,
+            type_hint,
+            break_type_id: None,
+        });
+    }
+
+    pub fn exit_loop(&mut self) -> Option<LoopFrame> {
+        self.loops.pop()
+    }
+
     pub fn lookup_var(&self, var_name: &str, scope_id: ScopeId) -> Option<&ScopedVar> {
         let scope = self.scopes.iter().find(|s| s.id == scope_id)?;
         if let Some(var) = self
             .vars
             .iter()
-            .find(|v| v.name == var_name && v.scope_id == scope_id)
+            .rev()
+            .find(|v| v.scope_id == scope_id && scope.declares(&v.name, var_name))
         {
             Some(var)
+        } else if scope.kind == ScopeKind::Function {
+            None
         } else if let Some(parent_id) = scope.parent {
             self.lookup_var(var_name, parent_id)
         } else {
@@ -156,26 +359,215 @@ impl ScopeManager {
         }
     }
 
-    pub fn add_var(&mut self, var_name: &str, ty_id: ScopeTypeId, mutable: bool) {
+    pub fn lookup_function(&self, fn_name: &str, scope_id: ScopeId) -> Option<&ScopedFunction> {
+        let scope = self.scopes.iter().find(|s| s.id == scope_id)?;
+        self.functions
+            .iter()
+            .rev()
+            .find(|f| f.scope_id == scope_id && scope.declares(&f.name, fn_name))
+            .or_else(|| {
+                scope
+                    .parent
+                    .and_then(|parent_id| self.lookup_function(fn_name, parent_id))
+            })
+    }
+
+    /// The top level scope of the module imported as `name`
+    pub fn lookup_namespace(&self, name: &str, scope_id: ScopeId) -> Option<ScopeId> {
+        let scope = self.scopes.iter().find(|s| s.id == scope_id)?;
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|n| n.name == name && n.scope_id == scope_id)
+            .map(|namespace| namespace.target)
+            .or_else(|| {
+                scope
+                    .parent
+                    .and_then(|parent_id| self.lookup_namespace(name, parent_id))
+            })
+    }
+
+    /// The variables visible in a scope by the names they are called in it, innermost first
+    pub fn visible_vars(&self, scope_id: ScopeId) -> Vec<(&str, &ScopedVar)> {
+        self.visible(&self.vars, scope_id, |v| (&v.name, v.scope_id), true)
+    }
+
+    /// The types visible in a scope by the names they are called in it, innermost first
+    pub fn visible_types(&self, scope_id: ScopeId) -> Vec<(&str, &ScopedType)> {
+        self.visible(&self.types, scope_id, |t| (&t.name, t.scope_id), false)
+            .into_iter()
+            .filter(|(_, ty)| ty.id != ERROR_TYPE_ID)
+            .collect()
+    }
+
+    /// The functions visible in a scope by the names they are called in it, innermost first
+    pub fn visible_functions(&self, scope_id: ScopeId) -> Vec<(&str, &ScopedFunction)> {
+        self.visible(&self.functions, scope_id, |f| (&f.name, f.scope_id), false)
+    }
+
+    /// Walks out from a scope like the lookups do, keeping the innermost of declarations that
+    /// share a name
+    fn visible<'a, T>(
+        &'a self,
+        declarations: &'a [T],
+        scope_id: ScopeId,
+        declared: impl Fn(&'a T) -> (&'a str, ScopeId),
+        stop_at_function: bool,
+    ) -> Vec<(&'a str, &'a T)> {
+        let mut visible: Vec<(&str, &T)> = vec![];
+        let mut scope = self.scopes.iter().find(|s| s.id == scope_id);
+        while let Some(current) = scope {
+            for declaration in declarations.iter().rev() {
+                let (stored, declared_in) = declared(declaration);
+                if declared_in != current.id {
+                    continue;
+                }
+                if let Some(name) = current.local_name(stored)
+                    && !visible.iter().any(|(seen, _)| *seen == name)
+                {
+                    visible.push((name, declaration));
+                }
+            }
+            if stop_at_function && current.kind == ScopeKind::Function {
+                break;
+            }
+            scope = current
+                .parent
+                .and_then(|parent| self.scopes.iter().find(|s| s.id == parent));
+        }
+        visible
+    }
+
+    /// Whether a scope is inside a function body, where top level variables are not visible
+    pub fn in_function(&self, scope_id: ScopeId) -> bool {
+        let Some(scope) = self.scopes.iter().find(|s| s.id == scope_id) else {
+            return false;
+        };
+        scope.kind == ScopeKind::Function || scope.parent.is_some_and(|id| self.in_function(id))
+    }
+
+    /// The name a declaration in the current scope is stored and emitted under
+    pub fn qualify(&self, name: &str) -> String {
+        self.scopes[self.cur].namespace.as_ref().map_or_else(
+            || name.to_string(),
+            |namespace| format!("{namespace}.{name}"),
+        )
+    }
+
+    pub fn add_var(
+        &mut self,
+        var_name: &str,
+        ty_id: ScopeTypeId,
+        mutable: bool,
+        public: bool,
+        span: &Span,
+    ) -> ScopeVarId {
         let var = ScopedVar {
             id: self.next_var_id,
-            name: var_name.to_string(),
+            name: self.qualify(var_name),
             type_id: ty_id,
             mutable,
             scope_id: self.cur,
+            public,
+            span: span.clone(),
         };
         self.vars.push(var);
         self.next_var_id += 1;
+        self.next_var_id - 1
     }
 
-    pub fn add_type(&mut self, ty_name: &str, parent_id: ScopeTypeId) {
+    pub fn add_type(
+        &mut self,
+        ty_name: &str,
+        parent_id: ScopeTypeId,
+        public: bool,
+        span: &Span,
+    ) -> ScopeTypeId {
+        let id = self.next_type_id;
         let ty = ScopedType {
-            id: self.next_type_id,
-            name: ty_name.to_string(),
+            id,
+            name: self.qualify(ty_name),
             parent_id: Some(parent_id),
             scope_id: self.cur,
+            layout: TypeLayout::Nominal,
+            public,
+            span: Some(span.clone()),
         };
         self.types.push(ty);
         self.next_type_id += 1;
+        id
+    }
+
+    pub fn add_struct_type(
+        &mut self,
+        ty_name: &str,
+        fields: Vec<ScopedField>,
+        public: bool,
+        span: &Span,
+    ) -> ScopeTypeId {
+        self.add_compound_type(ty_name, TypeLayout::Struct(fields), public, span)
+    }
+
+    pub fn add_enum_type(
+        &mut self,
+        ty_name: &str,
+        variants: Vec<ScopedVariant>,
+        public: bool,
+        span: &Span,
+    ) -> ScopeTypeId {
+        self.add_compound_type(ty_name, TypeLayout::Enum(variants), public, span)
+    }
+
+    fn add_compound_type(
+        &mut self,
+        ty_name: &str,
+        layout: TypeLayout,
+        public: bool,
+        span: &Span,
+    ) -> ScopeTypeId {
+        let id = self.next_type_id;
+        let ty = ScopedType {
+            id,
+            name: self.qualify(ty_name),
+            parent_id: None,
+            scope_id: self.cur,
+            layout,
+            public,
+            span: Some(span.clone()),
+        };
+        self.types.push(ty);
+        self.next_type_id += 1;
+        id
+    }
+
+    pub fn add_namespace(&mut self, name: &str, target: ScopeId) {
+        self.namespaces.push(ScopedNamespace {
+            name: name.to_string(),
+            target,
+            scope_id: self.cur,
+        });
+    }
+
+    pub fn add_function(
+        &mut self,
+        fn_name: &str,
+        param_type_ids: Vec<ScopeTypeId>,
+        return_type_id: ScopeTypeId,
+        public: bool,
+        span: &Span,
+    ) -> ScopeFunctionId {
+        let id = self.next_function_id;
+        let function = ScopedFunction {
+            id,
+            name: self.qualify(fn_name),
+            param_type_ids,
+            return_type_id,
+            scope_id: self.cur,
+            public,
+            span: span.clone(),
+        };
+        self.functions.push(function);
+        self.next_function_id += 1;
+        id
     }
 }
@@ -0,0 +1,57 @@
+use crate::checking::errors::Suggestion;
+
+// past a few, suggestions are more noise than help
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The candidates close enough to `name` to be what was meant by it, closest first
+pub fn rank(name: &str, candidates: impl IntoIterator<Item = Suggestion>) -> Vec<Suggestion> {
+    // a short name is allowed a single typo, a longer one a typo in every three characters, but
+    // never so many that nothing of the name is left
+    let length = name.chars().count();
+    let max_distance = (length / 3).max(1).min(length.saturating_sub(1));
+    let mut ranked = candidates
+        .into_iter()
+        .filter(|candidate| candidate.name != name)
+        .map(|candidate| (edit_distance(name, &candidate.name), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(first_distance, first), (second_distance, second)| {
+        first_distance
+            .cmp(second_distance)
+            .then_with(|| first.name.cmp(&second.name))
+    });
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// The number of characters to insert, delete or replace, or pairs of neighbouring characters to
+/// swap, to turn one name into the other
+fn edit_distance(from: &str, to: &str) -> usize {
+    let from = from.chars().collect::<Vec<_>>();
+    let to = to.chars().collect::<Vec<_>>();
+    // distances[i][j] is the distance from the first `i` characters of `from` to the first `j`
+    // of `to`
+    let mut distances = vec![vec![0; to.len() + 1]; from.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=from.len() {
+        for j in 1..=to.len() {
+            let replace = distances[i - 1][j - 1] + usize::from(from[i - 1] != to[j - 1]);
+            let mut distance = replace
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[from.len()][to.len()]
+}
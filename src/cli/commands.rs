@@ -0,0 +1,236 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{self, ExitCode},
+};
+
+use crate::{
+    bytecode::{Program, compiler::Compiler, disassembler::disassemble, vm::Vm},
+    checking::{Checker, imports::load_program},
+    cli::{Cli, Command, MessageFormat, Target},
+    common::ast::Ast,
+    diagnostics::{Diagnostic, explain::explain},
+    emitting::{c::CEmitter, llvm::LlvmEmitter, wat::WatEmitter},
+    evaluating::{Evaluator, value::Value, with_evaluation_stack},
+    ir::{lowering::Lowerer, passes::PassManager, printer, verifier},
+    lexing::Lexer,
+    module::tree::ModuleTree,
+    parsing::Parser,
+    repl::Repl,
+};
+
+pub fn run(cli: Cli) -> ExitCode {
+    let format = cli.message_format;
+    let result = match cli.command {
+        Command::Check { path } => check(&path, format),
+        Command::Tokens { path } => tokens(&path),
+        Command::Ast { path } => ast(&path, format),
+        Command::Bytecode { path } => bytecode(&path, format),
+        Command::Ir { path, opt_level } => ir(&path, opt_level, format),
+        Command::Run { path, vm } => run_program(&path, vm, format),
+        Command::Repl => repl(),
+        Command::Emit { path, target } => emit(&path, target, format),
+        Command::Build { path, output } => build(&path, output, format),
+        Command::Explain { code } => explain_code(&code),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(()) => ExitCode::FAILURE,
+    }
+}
+
+fn check(path: &Path, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    check_program(&tree, format)?;
+    Ok(())
+}
+
+fn tokens(path: &Path) -> Result<(), ()> {
+    let tree = load(path)?;
+    for token in Lexer::new(tree.entry()) {
+        println!(
+            "{:?} {:?} {}..{}",
+            token.kind, token.text, token.span.0, token.span.1
+        );
+    }
+    Ok(())
+}
+
+fn ast(path: &Path, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let ast = parse_module(&tree, format)?;
+    println!("{ast:#?}");
+    Ok(())
+}
+
+fn bytecode(path: &Path, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let program = compile_program(&tree, format)?;
+    print!("{}", disassemble(&program));
+    Ok(())
+}
+
+fn ir(path: &Path, opt_level: u8, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let ast = check_program(&tree, format)?;
+    let mut ir = Lowerer::new()
+        .lower(&ast)
+        .map_err(|err| report(&Diagnostic::from_lower_error(&err), &tree, format))?;
+    PassManager::for_level(opt_level).run(&mut ir);
+    // printed even when it is invalid, since that is when it is most worth reading
+    print!("{}", printer::print(&ir));
+    verifier::verify(&ir).map_err(|errors| {
+        for err in errors {
+            let block = err
+                .block
+                .map_or_else(String::new, |block| format!(" in bb{}", block.0));
+            eprintln!(
+                "error: internal compiler error: invalid IR in `{}`{block}: {}",
+                err.function, err.message
+            );
+        }
+    })
+}
+
+fn run_program(path: &Path, vm: bool, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let value = if vm {
+        let program = compile_program(&tree, format)?;
+        Vm::new(&program).run()
+    } else {
+        let ast = check_program(&tree, format)?;
+        with_evaluation_stack(|| Evaluator::new().evaluate(&ast))
+    }
+    .map_err(|err| report(&Diagnostic::from_runtime_error(&err), &tree, format))?;
+    if value != Value::Unit {
+        println!("{value}");
+    }
+    Ok(())
+}
+
+fn repl() -> Result<(), ()> {
+    println!(
+        "alloy {}, enter :help for commands",
+        env!("CARGO_PKG_VERSION")
+    );
+    with_evaluation_stack(|| Repl::new().run(io::stdin().lock(), &mut io::stdout()))
+        .map_err(|err| eprintln!("error: {err}"))
+}
+
+fn emit(path: &Path, target: Target, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let ast = check_program(&tree, format)?;
+    let source = match target {
+        Target::C => CEmitter::new(&tree).emit(&ast),
+        Target::Llvm => LlvmEmitter::new(&tree).emit(&ast),
+        Target::Wat => WatEmitter::new().emit(&ast),
+    }
+    .map_err(|err| report(&Diagnostic::from_emit_error(&err), &tree, format))?;
+    print!("{source}");
+    Ok(())
+}
+
+fn build(path: &Path, output: Option<PathBuf>, format: MessageFormat) -> Result<(), ()> {
+    let tree = load(path)?;
+    let ast = check_program(&tree, format)?;
+    let source = CEmitter::new(&tree)
+        .emit(&ast)
+        .map_err(|err| report(&Diagnostic::from_emit_error(&err), &tree, format))?;
+
+    // a project's executable is named after its package and goes in the project directory, a
+    // file's goes next to it and is named after it, like `hello.alloy` to `hello`
+    let output = output.unwrap_or_else(|| {
+        tree.manifest.as_ref().map_or_else(
+            || tree.entry_path.with_extension(""),
+            |manifest| path.join(&manifest.name),
+        )
+    });
+    let c_path = output.with_extension("c");
+    fs::write(&c_path, source)
+        .map_err(|err| eprintln!("error: could not write {}: {err}", c_path.display()))?;
+    compile_c(&c_path, &output)
+}
+
+fn explain_code(code: &str) -> Result<(), ()> {
+    let explanation = explain(code).ok_or_else(|| {
+        eprintln!("error: `{code}` is not an error code, codes look like `E0102`");
+    })?;
+    print!("{}", explanation.render());
+    Ok(())
+}
+
+/// Compiles generated C with `$CC`, or `cc` when it isn't set
+fn compile_c(source: &Path, output: &Path) -> Result<(), ()> {
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = process::Command::new(&compiler)
+        .args(["-std=c99", "-O2", "-o"])
+        .arg(output)
+        .arg(source)
+        .arg("-lm")
+        .status()
+        .map_err(|err| eprintln!("error: could not run `{compiler}`: {err}"))?;
+    if !status.success() {
+        eprintln!("error: `{compiler}` failed to compile {}", source.display());
+        return Err(());
+    }
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<ModuleTree, ()> {
+    ModuleTree::load(path, None).map_err(|err| eprintln!("error: {err}"))
+}
+
+fn parse_module(tree: &ModuleTree, format: MessageFormat) -> Result<Ast, ()> {
+    let module = tree.entry();
+    let mut parser = Parser::new(Lexer::new(module));
+    parser.parse().map_err(|errors| {
+        for err in &errors {
+            report(&Diagnostic::from_parse_error(err, module), tree, format);
+        }
+    })
+}
+
+/// Checks the entry module along with every module it imports. Modules with parse errors are
+/// still checked, so that one run reports every error it can.
+fn check_program(tree: &ModuleTree, format: MessageFormat) -> Result<Ast, ()> {
+    let mut checker = Checker::new();
+    let modules = load_program(tree)
+        .map_err(|err| report(&Diagnostic::from_check_error(&err, &checker), tree, format))?;
+    let mut parsed = true;
+    for module in &modules {
+        for err in &module.parse_errors {
+            report(
+                &Diagnostic::from_parse_error(err, module.module),
+                tree,
+                format,
+            );
+            parsed = false;
+        }
+    }
+    let ast = checker.check_program(modules).map_err(|errors| {
+        for err in &errors {
+            report(&Diagnostic::from_check_error(err, &checker), tree, format);
+        }
+    })?;
+    if parsed { Ok(ast) } else { Err(()) }
+}
+
+fn compile_program(tree: &ModuleTree, format: MessageFormat) -> Result<Program, ()> {
+    let ast = check_program(tree, format)?;
+    Compiler::new()
+        .compile(&ast)
+        .map_err(|err| report(&Diagnostic::from_compile_error(&err), tree, format))
+}
+
+/// Prints a diagnostic against the module its span is in
+fn report(diagnostic: &Diagnostic, tree: &ModuleTree, format: MessageFormat) {
+    let module = diagnostic
+        .span
+        .as_ref()
+        .map_or_else(|| tree.entry(), |span| tree.module_at(span.0));
+    match format {
+        MessageFormat::Human => eprintln!("{}", diagnostic.render(module)),
+        MessageFormat::Json => eprintln!("{}", diagnostic.to_json(module)),
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum, value_parser};
+
+use crate::ir::passes::MAX_OPT_LEVEL;
+
+pub mod commands;
+
+#[derive(Parser)]
+#[command(name = "alloy", version, about)]
+pub struct Cli {
+    /// How diagnostics are printed
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Type-check a file or project
+    Check {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print the tokens produced by the lexer
+    Tokens {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print the syntax tree produced by the parser
+    Ast {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print the bytecode compiled for a program
+    Bytecode {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print the SSA intermediate representation lowered from a program
+    Ir {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Which passes to run on the IR before printing it: 0 for none, 1 to fold constants and
+        /// remove dead code, 2 to also simplify control flow. Only this printout is affected,
+        /// `run`, `emit` and `build` don't go through the IR
+        #[arg(
+            short = 'O',
+            default_value_t = 0,
+            value_parser = value_parser!(u8).range(..=i64::from(MAX_OPT_LEVEL))
+        )]
+        opt_level: u8,
+    },
+    /// Check and run a program
+    Run {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Compile to bytecode and run it on the virtual machine instead of the tree-walker
+        #[arg(long)]
+        vm: bool,
+    },
+    /// Start an interactive session that checks and runs each input as it is entered
+    Repl,
+    /// Print the source generated for a program by one of the code generators
+    Emit {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// The language to generate
+        #[arg(long, value_enum, default_value_t = Target::C)]
+        target: Target,
+    },
+    /// Compile a program to C and build a native executable with the system C compiler
+    Build {
+        /// An .alloy file, or a project directory with an alloy.toml or a main.alloy
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Where to write the executable, the generated C source is written next to it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Describe an error code, like E0102, with an example of the error and its fix
+    Explain {
+        /// The code shown in brackets after `error`
+        code: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Target {
+    /// Portable C99
+    C,
+    /// Textual LLVM IR, using opaque pointers
+    Llvm,
+    /// WebAssembly text format
+    Wat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MessageFormat {
+    /// Rendered with the source lines they point at
+    Human,
+    /// One JSON object per line, see `Diagnostic::to_json`
+    Json,
+}
@@ -1,11 +1,24 @@
 use super::span::Span;
 
+/// The declaration a variable refers to, numbered by the checker so that back ends don't have to
+/// resolve names again. Unchecked expressions have none.
+pub type VarId = usize;
+
+/// The declaration a function call refers to, numbered by the checker like `VarId`s
+pub type FunctionId = usize;
+
+/// The declaration a type name refers to, the same ids as in `ExpressionState::Checked`
+pub type TypeId = usize;
+
 #[derive(Clone, Debug)]
 pub enum ExpressionKind {
     LiteralInt(isize),
     LiteralFloat(f32),
     LiteralBool(bool),
-    Ident(String),
+    Ident {
+        name: String,
+        var: Option<VarId>,
+    },
 
     Prefix {
         op: PrefixOp,
@@ -20,38 +33,104 @@ pub enum ExpressionKind {
 
     Block(Vec<Expression>),
 
+    If {
+        condition: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Option<Box<Expression>>,
+    },
+
+    While {
+        condition: Box<Expression>,
+        body: Box<Expression>,
+    },
+
+    Loop(Box<Expression>),
+
+    Break(Option<Box<Expression>>),
+
+    Continue,
+
     VariableDecl {
         name: String,
         value: Box<Expression>,
         mutable: bool,
         ty: Option<TypeIdent>,
+        // `pub`, visible to the modules that import this one
+        public: bool,
+        var: Option<VarId>,
     },
 
     TypeDecl {
         name: String,
-        value: TypeIdent,
+        value: TypeValue,
+        public: bool,
+        ty: Option<TypeId>,
+    },
+
+    FunctionDecl {
+        name: String,
+        params: Vec<Param>,
+        return_ty: Option<TypeIdent>,
+        body: Box<Expression>,
+        public: bool,
+        function: Option<FunctionId>,
     },
 
     VariableAssignment {
         name: String,
         value: Box<Expression>,
+        var: Option<VarId>,
     },
 
     FunctionCall {
         name: String,
         args: Vec<Expression>,
+        // builtins like `to_unit` have none
+        function: Option<FunctionId>,
     },
 
     MethodCall {
         caller: Box<Expression>,
         name: String,
         args: Vec<Expression>,
+        function: Option<FunctionId>,
+    },
+
+    StructLiteral {
+        name: String,
+        fields: Vec<FieldInit>,
+    },
+
+    FieldAccess {
+        target: Box<Expression>,
+        field: String,
+    },
+
+    // only produced by the checker, the parser sees `Shape.Circle(1.0)` as a method call
+    EnumVariant {
+        ty: String,
+        variant: String,
+        args: Vec<Expression>,
+    },
+
+    Match {
+        scrutinee: Box<Expression>,
+        arms: Vec<MatchArm>,
     },
+
+    // `import geometry.shapes` reads `geometry/shapes.alloy` next to the importing module, whose
+    // declarations are then reached through `shapes.`. The checker resolves imports away.
+    Import(Vec<String>),
+
+    // where an expression failed to parse, the `ParseError` says why
+    Error,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum PrefixOp {
     Not,
+    Negate,
+    BitNot,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -67,11 +146,15 @@ pub enum InfixOp {
     GreaterThanOrEqual,
     Equals,
     NotEquals,
+    /// Short-circuits: the right hand side is only evaluated when the left hand side is `true`
+    And,
+    /// Short-circuits: the right hand side is only evaluated when the left hand side is `false`
+    Or,
 }
 
 #[derive(Clone, Debug)]
 pub enum ExpressionState {
-    Checked(String, usize),
+    Checked(String, TypeId),
     Unchecked,
 }
 
@@ -114,4 +197,88 @@ impl Expression {
 }
 
 #[derive(Clone, Debug)]
-pub struct TypeIdent(pub String);
+pub struct TypeIdent {
+    pub name: String,
+    pub ty: Option<TypeId>,
+}
+
+impl TypeIdent {
+    pub const fn new(name: String) -> Self {
+        Self { name, ty: None }
+    }
+
+    /// The name and the id the checker resolved it to, like `Expression::ty`
+    pub fn resolved(&self) -> (&str, TypeId) {
+        (self.name.as_str(), self.ty.unwrap_or(0))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeValue {
+    Alias(TypeIdent),
+    Struct(Vec<Field>),
+    Enum(Vec<Variant>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Variant {
+    pub name: String,
+    pub payload: Vec<TypeIdent>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeIdent,
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldInit {
+    pub name: String,
+    pub value: Expression,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expression,
+}
+
+#[derive(Clone, Debug)]
+pub enum PatternKind {
+    Wildcard,
+    Binding {
+        name: String,
+        var: Option<VarId>,
+    },
+    LiteralInt(isize),
+    LiteralBool(bool),
+    Variant {
+        ty: TypeIdent,
+        name: String,
+        args: Vec<Pattern>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl Pattern {
+    /// Whether the pattern matches every value of its type
+    pub const fn is_irrefutable(&self) -> bool {
+        matches!(
+            self.kind,
+            PatternKind::Wildcard | PatternKind::Binding { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    pub ty: TypeIdent,
+    pub var: Option<VarId>,
+}
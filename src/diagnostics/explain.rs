@@ -0,0 +1,396 @@
+use std::fmt::Write;
+
+/// The long form of an error code, printed by `alloy explain`
+pub struct Explanation {
+    pub code: &'static str,
+    /// What the error is about, in a few words
+    pub title: &'static str,
+    pub description: &'static str,
+    /// Modules, as file names and sources with the entry module first, that fail with the code
+    pub failing: &'static [(&'static str, &'static str)],
+    /// The same modules with the error fixed
+    pub fixed: &'static [(&'static str, &'static str)],
+}
+
+/// The explanation of an error code, `e0102` works as well as `E0102`
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+impl Explanation {
+    pub fn render(&self) -> String {
+        let mut out = format!("{}: {}\n\n{}\n", self.code, self.title, self.description);
+        let _ = write!(out, "\nAn example that fails:\n{}", example(self.failing));
+        let _ = write!(out, "\nThe example fixed:\n{}", example(self.fixed));
+        out
+    }
+}
+
+/// The modules of an example indented, each under its file name when there are several
+fn example(modules: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    for (file_name, source) in modules {
+        if modules.len() > 1 {
+            let _ = write!(out, "\n  {file_name}:\n");
+        }
+        out.push('\n');
+        for line in source.lines() {
+            if line.is_empty() {
+                out.push('\n');
+            } else {
+                let _ = writeln!(out, "    {line}");
+            }
+        }
+    }
+    out
+}
+
+const MAIN: &str = "main.alloy";
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        title: "expected an expression",
+        description: "\
+The parser expected an expression, like a literal, a name, a call or a block, but found a token
+that can't start one. This is often an operator with a missing left operand, or a stray closing
+bracket.",
+        failing: &[(MAIN, "total :: * 2\n")],
+        fixed: &[(MAIN, "total :: 3 * 2\n")],
+    },
+    Explanation {
+        code: "E0002",
+        title: "expected a different token",
+        description: "\
+The parser needed a particular token to continue, like the `:` between a parameter's name and its
+type, or the `)` closing a call, and found something else.",
+        failing: &[(MAIN, "fn double(x Int): Int {\n    x * 2\n}\n")],
+        fixed: &[(MAIN, "fn double(x: Int): Int {\n    x * 2\n}\n")],
+    },
+    Explanation {
+        code: "E0003",
+        title: "syntax error",
+        description: "\
+The tokens can't form valid syntax. The message says what is wrong, like a literal that is out of
+range, an illegal character, or `pub` on something other than a declaration.",
+        failing: &[(MAIN, "count :: 1\npub count\n")],
+        fixed: &[(MAIN, "pub count :: 1\n")],
+    },
+    Explanation {
+        code: "E0004",
+        title: "unexpected end of file",
+        description: "\
+The file ended in the middle of an expression, usually because a `}` or `)` was never closed.",
+        failing: &[(MAIN, "fn one(): Int {\n    1\n")],
+        fixed: &[(MAIN, "fn one(): Int {\n    1\n}\n")],
+    },
+    Explanation {
+        code: "E0101",
+        title: "mismatched types",
+        description: "\
+An expression has a different type than where it is used expects. Values are never converted
+implicitly, an `Int` can't be used where a `Bool` or a `Float` is expected.",
+        failing: &[(MAIN, "ready: Bool : 1\n")],
+        fixed: &[(MAIN, "ready: Bool : true\n")],
+    },
+    Explanation {
+        code: "E0102",
+        title: "variable not found",
+        description: "\
+A name is used that no variable visible from there is declared with. Variables have to be declared
+before they are used, and top level variables are not visible inside function bodies, pass them
+as arguments instead.",
+        failing: &[(MAIN, "width :: 2\narea :: width * height\n")],
+        fixed: &[(MAIN, "width :: 2\nheight :: 3\narea :: width * height\n")],
+    },
+    Explanation {
+        code: "E0103",
+        title: "assignment to a constant variable",
+        description: "\
+A variable declared with `::` is constant and can't be assigned to. Declare it with `:=` to make
+it mutable.",
+        failing: &[(MAIN, "count :: 0\ncount = 1\n")],
+        fixed: &[(MAIN, "count := 0\ncount = 1\n")],
+    },
+    Explanation {
+        code: "E0104",
+        title: "type not found",
+        description: "\
+A type name is used that no type visible from there is declared with. Types have to be declared
+before they are used.",
+        failing: &[(MAIN, "distance: Metres : 1.0\n")],
+        fixed: &[(MAIN, "type Metres := Float\ndistance: Metres : 1.0\n")],
+    },
+    Explanation {
+        code: "E0105",
+        title: "type not visible",
+        description: "\
+A value's type was declared in a block that has ended, so the value can't be used outside of it.
+Declare the type where every use of its values can see it.",
+        failing: &[(
+            MAIN,
+            "origin :: {\n    type Point := struct { x: Int }\n    Point { x: 0 }\n}\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Point := struct { x: Int }\norigin :: {\n    Point { x: 0 }\n}\n",
+        )],
+    },
+    Explanation {
+        code: "E0106",
+        title: "function not found",
+        description: "\
+A function is called that isn't declared. Functions can be called before their declaration, but
+only from where the declaration is visible.",
+        failing: &[(MAIN, "half(4.0)\n")],
+        fixed: &[(
+            MAIN,
+            "fn half(x: Float): Float {\n    x / 2.0\n}\nhalf(4.0)\n",
+        )],
+    },
+    Explanation {
+        code: "E0107",
+        title: "method not found",
+        description: "\
+`value.name(args)` calls the function `name` with `value` as its first argument, and no function
+with that name is declared.",
+        failing: &[(MAIN, "size :: 4.0\nsize.half()\n")],
+        fixed: &[(
+            MAIN,
+            "fn half(x: Float): Float {\n    x / 2.0\n}\nsize :: 4.0\nsize.half()\n",
+        )],
+    },
+    Explanation {
+        code: "E0108",
+        title: "wrong number of arguments",
+        description: "\
+A function is called with more or fewer arguments than it has parameters. Parameters have no
+default values, every one needs an argument.",
+        failing: &[(
+            MAIN,
+            "fn add(a: Int, b: Int): Int {\n    a + b\n}\nadd(1)\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "fn add(a: Int, b: Int): Int {\n    a + b\n}\nadd(1, 2)\n",
+        )],
+    },
+    Explanation {
+        code: "E0109",
+        title: "mismatched argument type",
+        description: "\
+An argument has a different type than the parameter it is passed for.",
+        failing: &[(
+            MAIN,
+            "fn add(a: Int, b: Int): Int {\n    a + b\n}\nadd(1, true)\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "fn add(a: Int, b: Int): Int {\n    a + b\n}\nadd(1, 2)\n",
+        )],
+    },
+    Explanation {
+        code: "E0110",
+        title: "`break` outside of a loop",
+        description: "\
+`break` leaves the innermost `while` or `loop`, so it can only be used inside one. Loops around a
+function declaration don't count for its body.",
+        failing: &[(MAIN, "break\n")],
+        fixed: &[(MAIN, "loop {\n    break\n}\n")],
+    },
+    Explanation {
+        code: "E0111",
+        title: "`continue` outside of a loop",
+        description: "\
+`continue` starts the next iteration of the innermost `while` or `loop`, so it can only be used
+inside one.",
+        failing: &[(MAIN, "continue\n")],
+        fixed: &[(MAIN, "while false {\n    continue\n}\n")],
+    },
+    Explanation {
+        code: "E0112",
+        title: "`break` with a value in a `while` loop",
+        description: "\
+A `while` loop can end because its condition is false, when no `break` gives it a value, so it
+always evaluates to `Unit`. Use `loop` to produce a value with `break`.",
+        failing: &[(MAIN, "answer :: while true {\n    break 42\n}\n")],
+        fixed: &[(MAIN, "answer :: loop {\n    break 42\n}\n")],
+    },
+    Explanation {
+        code: "E0113",
+        title: "not a struct type",
+        description: "\
+A struct literal names a type that isn't a struct, so it has no fields to initialize.",
+        failing: &[(MAIN, "Int { x: 1 }\n")],
+        fixed: &[(MAIN, "type Point := struct { x: Int }\nPoint { x: 1 }\n")],
+    },
+    Explanation {
+        code: "E0114",
+        title: "unknown field",
+        description: "\
+A field is accessed or initialized that the struct type doesn't declare.",
+        failing: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\np :: Point { x: 1.0, y: 2.0 }\np.z\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\np :: Point { x: 1.0, y: 2.0 }\np.y\n",
+        )],
+    },
+    Explanation {
+        code: "E0115",
+        title: "missing fields",
+        description: "\
+A struct literal has to initialize every field of its type, fields have no default values.",
+        failing: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\nPoint { x: 1.0 }\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\nPoint { x: 1.0, y: 0.0 }\n",
+        )],
+    },
+    Explanation {
+        code: "E0116",
+        title: "duplicate field",
+        description: "\
+A field is declared twice in a struct type, or initialized twice in a struct literal.",
+        failing: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\nPoint { x: 1.0, x: 2.0, y: 3.0 }\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Point := struct { x: Float, y: Float }\nPoint { x: 1.0, y: 3.0 }\n",
+        )],
+    },
+    Explanation {
+        code: "E0117",
+        title: "duplicate variant",
+        description: "\
+A variant is declared twice in an enum type, every variant needs a name of its own.",
+        failing: &[(MAIN, "type Answer := enum { Yes, Yes }\n")],
+        fixed: &[(MAIN, "type Answer := enum { Yes, No }\n")],
+    },
+    Explanation {
+        code: "E0118",
+        title: "unknown variant",
+        description: "\
+A variant is constructed or matched that the enum type doesn't declare.",
+        failing: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float) }\nShape.Triangle(1.0)\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float), Triangle(Float) }\nShape.Triangle(1.0)\n",
+        )],
+    },
+    Explanation {
+        code: "E0119",
+        title: "wrong number of variant fields",
+        description: "\
+A variant is constructed or matched with more or fewer fields than it declares.",
+        failing: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float) }\nShape.Circle(1.0, 2.0)\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float) }\nShape.Circle(1.0)\n",
+        )],
+    },
+    Explanation {
+        code: "E0120",
+        title: "non-exhaustive match",
+        description: "\
+A `match` has to have an arm for every value its scrutinee can have. Add arms for the values the
+message lists, or a `_` arm to match everything else.",
+        failing: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float) }\ns :: Shape.Square(2.0)\nmatch s {\n    Shape.Circle(r) => r,\n}\n",
+        )],
+        fixed: &[(
+            MAIN,
+            "type Shape := enum { Circle(Float), Square(Float) }\ns :: Shape.Square(2.0)\nmatch s {\n    Shape.Circle(r) => r,\n    Shape.Square(w) => w,\n}\n",
+        )],
+    },
+    Explanation {
+        code: "E0121",
+        title: "unreachable match arm",
+        description: "\
+The arms before a `match` arm already match every value it could, so it can never be taken. Arms
+are tried in order, put the more specific ones first.",
+        failing: &[(MAIN, "match 3 {\n    _ => 0,\n    3 => 1,\n}\n")],
+        fixed: &[(MAIN, "match 3 {\n    3 => 1,\n    _ => 0,\n}\n")],
+    },
+    Explanation {
+        code: "E0122",
+        title: "module not found",
+        description: "\
+`import a.b` reads the module `a/b.alloy` in the directory of the importing module, and there is
+no such file.",
+        failing: &[(MAIN, "import geometry\ngeometry.pi\n")],
+        fixed: &[
+            (MAIN, "import geometry\ngeometry.pi\n"),
+            ("geometry.alloy", "pub pi :: 3.14\n"),
+        ],
+    },
+    Explanation {
+        code: "E0123",
+        title: "import cycle",
+        description: "\
+Modules import each other, directly or through other modules. A module's imports are checked
+before it, so they can't depend on it. Move what they share into a module both import.",
+        failing: &[
+            (MAIN, "import a\na.x\n"),
+            ("a.alloy", "import b\npub x :: b.y\n"),
+            ("b.alloy", "import a\npub y :: 1\n"),
+        ],
+        fixed: &[
+            (MAIN, "import a\na.x\n"),
+            ("a.alloy", "import b\npub x :: b.y\n"),
+            ("b.alloy", "pub y :: 1\n"),
+        ],
+    },
+    Explanation {
+        code: "E0124",
+        title: "`import` not at the top level",
+        description: "\
+Imports are resolved before a module is checked, so they can only be at the top level of a
+module, not inside blocks or function bodies.",
+        failing: &[
+            (
+                MAIN,
+                "fn area(r: Float): Float {\n    import math\n    r * r * math.pi()\n}\n",
+            ),
+            ("math.alloy", "pub fn pi(): Float {\n    3.14\n}\n"),
+        ],
+        fixed: &[
+            (
+                MAIN,
+                "import math\nfn area(r: Float): Float {\n    r * r * math.pi()\n}\n",
+            ),
+            ("math.alloy", "pub fn pi(): Float {\n    3.14\n}\n"),
+        ],
+    },
+    Explanation {
+        code: "E0125",
+        title: "private item",
+        description: "\
+A variable, type or function of another module is used that isn't declared `pub`. Only `pub`
+declarations can be used from the modules that import theirs.",
+        failing: &[
+            (MAIN, "import math\nmath.pi\n"),
+            ("math.alloy", "pi :: 3.14\n"),
+        ],
+        fixed: &[
+            (MAIN, "import math\nmath.pi\n"),
+            ("math.alloy", "pub pi :: 3.14\n"),
+        ],
+    },
+];
@@ -0,0 +1,62 @@
+use std::fmt::Write;
+
+use crate::{diagnostics::Diagnostic, module::module::Module};
+
+impl Diagnostic {
+    /// The diagnostic as a single line JSON object, for tools to read instead of `render`
+    ///
+    /// ```text
+    /// {"severity":"error","code":"E0102","message":"cannot find variable `b` in this scope",
+    ///  "span":{"file":"main.alloy","line":2,"column":6,"end_line":2,"end_column":7},
+    ///  "children":[]}
+    /// ```
+    ///
+    /// (wrapped here, the output has no line breaks). Lines and columns are 1-based and the end
+    /// is exclusive, `code` and `span` are `null` when a diagnostic has none.
+    pub fn to_json(&self, module: &Module) -> String {
+        let mut out = String::from("{");
+        let _ = write!(out, "\"severity\":{}", string(self.severity.label()));
+        let code = self.code.map_or_else(|| "null".to_string(), string);
+        let _ = write!(out, ",\"code\":{code}");
+        let _ = write!(out, ",\"message\":{}", string(&self.message));
+        let span = self.span.as_ref().map_or_else(
+            || "null".to_string(),
+            |span| {
+                let (line, column) = module.location(span.0);
+                let (end_line, end_column) = module.location(span.1);
+                format!(
+                    "{{\"file\":{},\"line\":{line},\"column\":{column},\"end_line\":{end_line},\"end_column\":{end_column}}}",
+                    string(&module.file_path.display().to_string())
+                )
+            },
+        );
+        let _ = write!(out, ",\"span\":{span}");
+        let children = self
+            .children
+            .iter()
+            .map(|child| child.to_json(module))
+            .collect::<Vec<_>>();
+        let _ = write!(out, ",\"children\":[{}]}}", children.join(","));
+        out
+    }
+}
+
+/// A JSON string literal
+fn string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
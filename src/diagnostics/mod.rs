@@ -0,0 +1,331 @@
+use crate::{
+    bytecode::errors::{CompileError, CompileErrorKind},
+    checking::{
+        Checker,
+        errors::{CheckError, CheckErrorKind, Item, Suggestion},
+    },
+    common::span::Span,
+    emitting::errors::{EmitError, EmitErrorKind},
+    evaluating::errors::{RuntimeError, RuntimeErrorKind},
+    ir::errors::{LowerError, LowerErrorKind},
+    lexing::token::{Token, TokenKind},
+    module::module::Module,
+    parsing::errors::ParseError,
+};
+
+pub mod explain;
+pub mod json;
+pub mod render;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The code of a parse or check error, which `alloy explain` describes
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub children: Vec<Self>,
+}
+
+impl Diagnostic {
+    pub const fn new(severity: Severity, message: String, span: Option<Span>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message,
+            span,
+            children: vec![],
+        }
+    }
+
+    pub const fn error(message: String, span: Span) -> Self {
+        Self::new(Severity::Error, message, Some(span))
+    }
+
+    pub const fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_child(mut self, child: Self) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn with_note(self, message: &str) -> Self {
+        self.with_child(Self::new(Severity::Note, message.to_string(), None))
+    }
+
+    /// A help for each suggested name, pointing at its declaration where it has one
+    pub fn with_suggestions(self, suggestions: &[Suggestion]) -> Self {
+        suggestions.iter().fold(self, |diagnostic, suggestion| {
+            diagnostic.with_child(Self::new(
+                Severity::Help,
+                format!("did you mean `{}`?", suggestion.name),
+                suggestion.span.clone(),
+            ))
+        })
+    }
+
+    pub fn from_parse_error(err: &ParseError, module: &Module) -> Self {
+        let diagnostic = match err {
+            ParseError::NoPrefixParse(token) => Self::error(
+                format!("expected an expression, found {}", describe_token(token)),
+                token.span.clone(),
+            ),
+            ParseError::Expected(token, expected) => {
+                let expected = expected.iter().map(TokenKind::describe).collect::<Vec<_>>();
+                Self::error(
+                    format!(
+                        "expected {}, found {}",
+                        join_or(&expected),
+                        describe_token(token)
+                    ),
+                    token.span.clone(),
+                )
+            }
+            ParseError::Syntax(token, message) => Self::error(message.clone(), token.span.clone()),
+            ParseError::UnexpectedEof => {
+                let end = module.end();
+                Self::error("unexpected end of file".to_string(), Span::new(end, end))
+            }
+        };
+        diagnostic.with_code(err.code())
+    }
+
+    pub fn from_check_error(err: &CheckError, checker: &Checker) -> Self {
+        let diagnostic = Self::error(check_error_message(&err.kind, checker), err.span.clone());
+        let diagnostic = match &err.kind {
+            CheckErrorKind::VariableNotFound(_, suggestions)
+            | CheckErrorKind::TypeNameNotFound(_, suggestions)
+            | CheckErrorKind::FunctionNotFound(_, suggestions)
+            | CheckErrorKind::MethodNotFound(_, suggestions) => {
+                diagnostic.with_suggestions(suggestions)
+            }
+            CheckErrorKind::AssignToConstVariable(_) => diagnostic.with_note(
+                "variables declared with `::` are constant, use `:=` to declare a mutable variable",
+            ),
+            CheckErrorKind::BreakWithValueInWhile => diagnostic
+                .with_note("only `loop` can produce a value, `while` always evaluates to `Unit`"),
+            CheckErrorKind::NonExhaustiveMatch(_) => diagnostic
+                .with_note("add the missing arms, or a `_` arm to match everything else"),
+            CheckErrorKind::ModuleNotFound(path) => diagnostic.with_note(&format!(
+                "`import {path}` reads `{}.alloy` next to the importing file",
+                path.replace('.', "/")
+            )),
+            CheckErrorKind::ImportCycle(_) => diagnostic.with_note(
+                "modules cannot import each other, move the shared declarations into a third module",
+            ),
+            CheckErrorKind::PrivateItem(..) => {
+                diagnostic.with_note("only `pub` declarations can be used from other modules")
+            }
+            _ => diagnostic,
+        };
+        diagnostic.with_code(err.kind.code())
+    }
+
+    pub fn from_compile_error(err: &CompileError) -> Self {
+        let (what, name) = match &err.kind {
+            CompileErrorKind::Variable(name) => ("variable", name),
+            CompileErrorKind::Function(name) => ("function", name),
+            CompileErrorKind::Type(name) => ("type", name),
+            CompileErrorKind::Field(name) => ("field", name),
+        };
+        Self::error(
+            format!("internal compiler error: unresolved {what} `{name}`"),
+            err.span.clone(),
+        )
+        .with_note("this is a bug, the checker should have rejected the program")
+    }
+
+    pub fn from_lower_error(err: &LowerError) -> Self {
+        let (what, name) = match &err.kind {
+            LowerErrorKind::Variable(name) => ("variable", name),
+            LowerErrorKind::Function(name) => ("function", name),
+            LowerErrorKind::Type(name) => ("type", name),
+            LowerErrorKind::Field(name) => ("field", name),
+        };
+        Self::error(
+            format!("internal compiler error: unresolved {what} `{name}`"),
+            err.span.clone(),
+        )
+        .with_note("this is a bug, the checker should have rejected the program")
+    }
+
+    pub fn from_emit_error(err: &EmitError) -> Self {
+        let message = match &err.kind {
+            EmitErrorKind::UnresolvedVariable(name) => {
+                format!("internal compiler error: unresolved variable `{name}`")
+            }
+            EmitErrorKind::UnresolvedFunction(name) => {
+                format!("internal compiler error: unresolved function `{name}`")
+            }
+            EmitErrorKind::UnresolvedType(name) => {
+                format!("internal compiler error: unresolved type `{name}`")
+            }
+            EmitErrorKind::Unsupported(construct) => {
+                return Self::error(
+                    format!("{construct} is not supported by this back end"),
+                    err.span.clone(),
+                );
+            }
+        };
+        Self::error(message, err.span.clone())
+            .with_note("this is a bug, the checker should have rejected the program")
+    }
+
+    pub fn from_runtime_error(err: &RuntimeError) -> Self {
+        let span = err.span.clone();
+        match &err.kind {
+            RuntimeErrorKind::IntegerOverflow => Self::error(
+                "attempt to compute a value that overflows `Int`".to_string(),
+                span,
+            ),
+            RuntimeErrorKind::NegativeExponent => Self::error(
+                "attempt to raise an `Int` to a negative power".to_string(),
+                span,
+            )
+            .with_note("convert the base to a `Float` to get a fractional result"),
+            RuntimeErrorKind::StackOverflow(depth) => Self::error(
+                format!("call stack overflowed after {depth} nested calls"),
+                span,
+            ),
+            RuntimeErrorKind::Internal(message) => {
+                Self::error(format!("internal evaluator error: {message}"), span)
+                    .with_note("this is a bug, the checker should have rejected the program")
+            }
+        }
+    }
+}
+
+fn describe_token(token: &Token) -> String {
+    match token.kind {
+        TokenKind::Ident | TokenKind::Number | TokenKind::Boolean | TokenKind::Illegal => {
+            format!("`{}`", token.text)
+        }
+        _ => token.kind.describe().to_string(),
+    }
+}
+
+/// The headline of a check error, the notes and suggestions that follow it are added separately
+fn check_error_message(kind: &CheckErrorKind, checker: &Checker) -> String {
+    let ty = |id| format!("`{}`", checker.type_name(id));
+    let quoted = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+    };
+    match kind {
+        CheckErrorKind::TypeMismatch(expected, got) => {
+            let expected = expected.iter().map(|id| ty(*id)).collect::<Vec<_>>();
+            let expected = expected.iter().map(String::as_str).collect::<Vec<_>>();
+            format!(
+                "mismatched types: expected {}, found {}",
+                join_or(&expected),
+                ty(*got)
+            )
+        }
+        CheckErrorKind::VariableNotFound(name, _) => {
+            format!("cannot find variable `{name}` in this scope")
+        }
+        CheckErrorKind::AssignToConstVariable(name) => {
+            format!("cannot assign to constant variable `{name}`")
+        }
+        CheckErrorKind::TypeNameNotFound(name, _) => {
+            format!("cannot find type `{name}` in this scope")
+        }
+        CheckErrorKind::TypeNotFound(id) => {
+            format!("type {} is not visible in this scope", ty(*id))
+        }
+        CheckErrorKind::FunctionNotFound(name, _) => {
+            format!("cannot find function `{name}` in this scope")
+        }
+        CheckErrorKind::MethodNotFound(name, _) => {
+            format!("no method named `{name}` found in this scope")
+        }
+        CheckErrorKind::ArgumentCountMismatch(name, expected, got) => format!(
+            "function `{name}` takes {expected} {} but {got} {} supplied",
+            plural(*expected, "argument", "arguments"),
+            plural(*got, "was", "were")
+        ),
+        CheckErrorKind::ArgumentTypeMismatch(name, index, expected, got) => format!(
+            "mismatched types: argument {} of `{name}` expects {}, found {}",
+            index + 1,
+            ty(*expected),
+            ty(*got)
+        ),
+        CheckErrorKind::BreakOutsideLoop => "`break` outside of a loop".to_string(),
+        CheckErrorKind::ContinueOutsideLoop => "`continue` outside of a loop".to_string(),
+        CheckErrorKind::BreakWithValueInWhile => {
+            "`break` with a value inside a `while` loop".to_string()
+        }
+        CheckErrorKind::NotAStruct(id) => format!("{} is not a struct type", ty(*id)),
+        CheckErrorKind::UnknownField(id, name) => format!("no field `{name}` on type {}", ty(*id)),
+        CheckErrorKind::MissingFields(id, names) => format!(
+            "missing {} {} in initializer of {}",
+            plural(names.len(), "field", "fields"),
+            quoted(names).join(", "),
+            ty(*id)
+        ),
+        CheckErrorKind::DuplicateField(name) => {
+            format!("field `{name}` is specified more than once")
+        }
+        CheckErrorKind::DuplicateVariant(name) => {
+            format!("variant `{name}` is declared more than once")
+        }
+        CheckErrorKind::UnknownVariant(id, name) => {
+            format!("no variant `{name}` on type {}", ty(*id))
+        }
+        CheckErrorKind::VariantArityMismatch(name, expected, got) => format!(
+            "variant `{name}` has {expected} {} but {got} {} given",
+            plural(*expected, "field", "fields"),
+            plural(*got, "was", "were")
+        ),
+        CheckErrorKind::NonExhaustiveMatch(missing) => format!(
+            "non-exhaustive patterns: {} not covered",
+            join_or(
+                &quoted(missing)
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+            )
+        ),
+        CheckErrorKind::UnreachableArm => {
+            "unreachable pattern: earlier arms already match every value".to_string()
+        }
+        CheckErrorKind::ModuleNotFound(path) => format!("cannot find module `{path}`"),
+        CheckErrorKind::ImportCycle(cycle) => format!("import cycle: {}", cycle.join(" -> ")),
+        CheckErrorKind::ImportNotAtTopLevel => {
+            "`import` is only allowed at the top level of a module".to_string()
+        }
+        CheckErrorKind::PrivateItem(item, name) => {
+            let item = match item {
+                Item::Variable => "variable",
+                Item::Type => "type",
+                Item::Function => "function",
+            };
+            format!("{item} `{name}` is private")
+        }
+    }
+}
+
+fn join_or(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => (*only).to_string(),
+        [rest @ .., last] => format!("{} or {last}", rest.join(", ")),
+    }
+}
+
+const fn plural<'a>(count: usize, one: &'a str, many: &'a str) -> &'a str {
+    if count == 1 { one } else { many }
+}
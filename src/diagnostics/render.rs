@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use colored::{ColoredString, Colorize};
+
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    module::module::Module,
+};
+
+impl Severity {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+            Self::Help => "help",
+        }
+    }
+
+    fn paint(self, text: &str) -> ColoredString {
+        match self {
+            Self::Error => text.red().bold(),
+            Self::Warning => text.yellow().bold(),
+            Self::Note => text.cyan().bold(),
+            Self::Help => text.green().bold(),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with the offending source line and an underline of its span
+    ///
+    /// ```text
+    /// error[E0102]: cannot find variable `b` in this scope
+    ///  --> main.alloy:2:6
+    ///   |
+    /// 2 | a :: b
+    ///   |      ^
+    /// ```
+    pub fn render(&self, module: &Module) -> String {
+        let label = self.code.map_or_else(
+            || self.severity.label().to_string(),
+            |code| format!("{}[{code}]", self.severity.label()),
+        );
+        let mut out = format!(
+            "{}{}\n",
+            self.severity.paint(&label),
+            format!(": {}", self.message).bold()
+        );
+
+        let gutter_width = self.gutter_width(module);
+        if let Some(span) = &self.span {
+            let (line, col) = module.location(span.0);
+            let (end_line, end_col) = module.location(span.1);
+            let text = module.line(line);
+            let width = if end_line == line {
+                end_col.saturating_sub(col).max(1)
+            } else {
+                (text.chars().count() + 1).saturating_sub(col).max(1)
+            };
+            let pad = " ".repeat(gutter_width);
+            let bar = "|".blue().bold();
+
+            let _ = writeln!(
+                out,
+                "{pad}{} {}:{line}:{col}",
+                "-->".blue().bold(),
+                module.file_path.display()
+            );
+            let _ = writeln!(out, "{pad} {bar}");
+            let _ = writeln!(
+                out,
+                "{} {bar} {text}",
+                format!("{line:>gutter_width$}").blue().bold()
+            );
+            let indent: String = text
+                .chars()
+                .take(col - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let _ = writeln!(
+                out,
+                "{pad} {bar} {indent}{}",
+                self.severity.paint(&"^".repeat(width))
+            );
+        }
+
+        for child in &self.children {
+            if child.span.is_some() {
+                out += &child.render(module);
+            } else {
+                let _ = writeln!(
+                    out,
+                    "{} {} {}: {}",
+                    " ".repeat(gutter_width),
+                    "=".blue().bold(),
+                    child.severity.label().bold(),
+                    child.message
+                );
+            }
+        }
+
+        out
+    }
+
+    fn gutter_width(&self, module: &Module) -> usize {
+        self.span
+            .as_ref()
+            .map_or(1, |span| module.location(span.0).0.to_string().len())
+    }
+}
@@ -0,0 +1,1114 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    checking::scope::{BOOL_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, NEVER_TYPE_ID, UNIT_TYPE_ID},
+    common::{
+        ast::Ast,
+        expression::{
+            Expression, ExpressionKind, FieldInit, FunctionId, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeId, TypeIdent, TypeValue, VarId,
+        },
+        span::Span,
+    },
+    emitting::errors::EmitError,
+    module::tree::ModuleTree,
+};
+
+pub const PRELUDE: &str = include_str!("prelude.h");
+
+const KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "bool", "true", "false",
+];
+
+#[derive(Clone)]
+enum Layout {
+    Unit,
+    Int,
+    Float,
+    Bool,
+    Struct {
+        owner: String,
+        display: String,
+        fields: Vec<(String, CType)>,
+    },
+    Enum {
+        owner: String,
+        display: String,
+        variants: Vec<(String, Vec<CType>)>,
+    },
+}
+
+/// An alloy type as it is spelled in C, aliases share the layout of the type they refer to
+#[derive(Clone)]
+struct CType {
+    name: String,
+    layout: Layout,
+}
+
+impl CType {
+    fn builtin(name: &str, layout: Layout) -> Self {
+        Self {
+            name: name.to_string(),
+            layout,
+        }
+    }
+
+    fn print_fn(&self) -> String {
+        match &self.layout {
+            Layout::Unit => "alloy_print_unit".to_string(),
+            Layout::Int => "alloy_print_int".to_string(),
+            Layout::Float => "alloy_print_float".to_string(),
+            Layout::Bool => "alloy_print_bool".to_string(),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => {
+                format!("alloy_print_{owner}")
+            }
+        }
+    }
+
+    fn equals(&self, lh: &str, rh: &str) -> String {
+        match &self.layout {
+            Layout::Unit => "true".to_string(),
+            Layout::Int | Layout::Float | Layout::Bool => format!("({lh} == {rh})"),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => {
+                format!("alloy_eq_{owner}({lh}, {rh})")
+            }
+        }
+    }
+}
+
+/// How safe it is to move a C expression relative to the statements around it
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Purity {
+    // literals and temporaries, nothing can change their value
+    Stable,
+    // reads variables, which later statements may assign
+    Pure,
+    // calls functions
+    Impure,
+}
+
+struct CExpr {
+    code: String,
+    purity: Purity,
+}
+
+impl CExpr {
+    const fn new(code: String, purity: Purity) -> Self {
+        Self { code, purity }
+    }
+
+    fn unit() -> Self {
+        Self::new("0".to_string(), Purity::Stable)
+    }
+}
+
+/// `None` when control never reaches the end of the expression, like after a `break`
+type Emission = Result<Option<CExpr>, EmitError>;
+
+/// The statements of the function being emitted
+struct Body {
+    lines: Vec<String>,
+    indent: usize,
+    // the variable each enclosing loop's `break` value is stored in
+    loops: Vec<Option<String>>,
+}
+
+impl Body {
+    const fn new() -> Self {
+        Self {
+            lines: vec![],
+            indent: 1,
+            loops: vec![],
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        self.lines
+            .push(format!("{}{text}", "    ".repeat(self.indent)));
+    }
+}
+
+/// Translates a checked `Ast` into a single C99 translation unit whose `main` runs the top level
+/// expressions and prints the value of the last one, like `alloy run`
+///
+/// Every declaration gets a unique C name, so alloy blocks flatten into the enclosing C block
+/// and their values are plain expressions or temporaries
+pub struct CEmitter<'a> {
+    tree: &'a ModuleTree,
+    types: String,
+    prototypes: String,
+    functions: String,
+    // the C name of every variable and function, and the C type of every type
+    vars: HashMap<VarId, String>,
+    declared_functions: HashMap<FunctionId, String>,
+    declared_types: HashMap<TypeId, CType>,
+    body: Body,
+    next_id: usize,
+}
+
+impl<'a> CEmitter<'a> {
+    pub fn new(tree: &'a ModuleTree) -> Self {
+        let declared_types = HashMap::from([
+            (UNIT_TYPE_ID, CType::builtin("alloy_unit", Layout::Unit)),
+            (INT_TYPE_ID, CType::builtin("alloy_int", Layout::Int)),
+            (FLOAT_TYPE_ID, CType::builtin("alloy_float", Layout::Float)),
+            (BOOL_TYPE_ID, CType::builtin("alloy_bool", Layout::Bool)),
+            (NEVER_TYPE_ID, CType::builtin("alloy_unit", Layout::Unit)),
+        ]);
+        Self {
+            tree,
+            types: String::new(),
+            prototypes: String::new(),
+            functions: String::new(),
+            vars: HashMap::new(),
+            declared_functions: HashMap::new(),
+            declared_types,
+            body: Body::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn emit(mut self, ast: &Ast) -> Result<String, EmitError> {
+        let value = self.emit_sequence(&ast.0)?;
+        if let (Some(value), Some(last)) = (value, ast.0.last()) {
+            let ty = self.type_of(last)?;
+            if !matches!(ty.layout, Layout::Unit) {
+                self.body
+                    .line(&format!("{}({});", ty.print_fn(), value.code));
+                self.body.line("putchar('\\n');");
+            }
+        }
+        self.body.line("return 0;");
+
+        let mut output = PRELUDE.to_string();
+        for section in [&self.types, &self.prototypes, &self.functions] {
+            if !section.is_empty() {
+                output.push('\n');
+                output.push_str(section);
+            }
+        }
+        output.push_str("\nint main(void) {\n");
+        for line in &self.body.lines {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push_str("}\n");
+        Ok(output)
+    }
+
+    fn emit_expression(&mut self, expr: &Expression) -> Emission {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => {
+                Ok(Some(CExpr::new(value.to_string(), Purity::Stable)))
+            }
+            ExpressionKind::LiteralFloat(value) => {
+                Ok(Some(CExpr::new(format!("{value:?}f"), Purity::Stable)))
+            }
+            ExpressionKind::LiteralBool(value) => {
+                Ok(Some(CExpr::new(value.to_string(), Purity::Stable)))
+            }
+            ExpressionKind::Ident { name, var } => {
+                let c_name = self
+                    .resolve_var(*var)
+                    .ok_or_else(|| EmitError::unresolved_variable(name, span))?;
+                Ok(Some(CExpr::new(c_name, Purity::Pure)))
+            }
+            ExpressionKind::Prefix { op, rh } => self.emit_prefix(*op, rh, span),
+            ExpressionKind::Infix { op, lh, rh } => match op {
+                InfixOp::And | InfixOp::Or => self.emit_logical(*op, lh, rh),
+                _ => self.emit_arithmetic(*op, lh, rh, span),
+            },
+            ExpressionKind::Block(exprs) => self.emit_sequence(exprs),
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => self.emit_if(expr, condition, then, otherwise.as_deref()),
+            ExpressionKind::While { condition, body } => self.emit_while(condition, body),
+            ExpressionKind::Loop(body) => self.emit_loop(expr, body),
+            ExpressionKind::Break(value) => self.emit_break(value.as_deref()),
+            ExpressionKind::Continue => {
+                self.body.line("continue;");
+                Ok(None)
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                ty,
+                var,
+                ..
+            } => self.emit_variable_decl(name, value, ty.as_ref(), *var, span),
+            ExpressionKind::VariableAssignment { name, value, var } => {
+                self.emit_assignment(name, value, *var, span)
+            }
+            ExpressionKind::TypeDecl {
+                name, value, ty, ..
+            } => {
+                self.emit_type_decl(name, value, *ty, span)?;
+                Ok(Some(CExpr::unit()))
+            }
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                return_ty,
+                body,
+                function,
+                ..
+            } => {
+                self.emit_function(name, params, return_ty.as_ref(), body, *function, span)?;
+                Ok(Some(CExpr::unit()))
+            }
+            ExpressionKind::FunctionCall {
+                name,
+                args,
+                function,
+            } => self.emit_call(name, *function, &args.iter().collect::<Vec<_>>(), span),
+            ExpressionKind::MethodCall {
+                caller,
+                name,
+                args,
+                function,
+            } => {
+                let args = std::iter::once(caller.as_ref())
+                    .chain(args.iter())
+                    .collect::<Vec<_>>();
+                self.emit_call(name, *function, &args, span)
+            }
+            ExpressionKind::StructLiteral { fields, .. } => self.emit_struct_literal(expr, fields),
+            ExpressionKind::FieldAccess { target, field } => {
+                let Some(value) = self.emit_expression(target)? else {
+                    return Ok(None);
+                };
+                Ok(Some(CExpr::new(
+                    format!("{}.{}", value.code, c_ident(field)),
+                    value.purity.max(Purity::Pure),
+                )))
+            }
+            ExpressionKind::EnumVariant { variant, args, .. } => {
+                self.emit_enum_variant(expr, variant, args, span)
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => self.emit_match(expr, scrutinee, arms),
+        }
+    }
+
+    /// Emits an expression whose value isn't used
+    fn emit_statement(&mut self, expr: &Expression) -> Result<(), EmitError> {
+        if let Some(value) = self.emit_expression(expr)? {
+            self.discard(&value);
+        }
+        Ok(())
+    }
+
+    fn emit_sequence(&mut self, exprs: &[Expression]) -> Emission {
+        let Some((last, rest)) = exprs.split_last() else {
+            return Ok(Some(CExpr::unit()));
+        };
+        for expr in rest {
+            self.emit_statement(expr)?;
+        }
+        self.emit_expression(last)
+    }
+
+    /// Emits one arm of a conditional one level deeper, storing its value in `result`
+    fn emit_branch(&mut self, expr: &Expression, result: Option<&str>) -> Result<(), EmitError> {
+        self.body.indent += 1;
+        match (self.emit_expression(expr)?, result) {
+            (Some(value), Some(result)) => self.body.line(&format!("{result} = {};", value.code)),
+            (Some(value), None) => self.discard(&value),
+            (None, _) => {}
+        }
+        self.body.indent -= 1;
+        Ok(())
+    }
+
+    fn emit_prefix(&mut self, op: PrefixOp, rh: &Expression, span: &Span) -> Emission {
+        let Some(value) = self.emit_expression(rh)? else {
+            return Ok(None);
+        };
+        let code = match (op, self.type_of(rh)?.layout) {
+            (PrefixOp::Not, _) => format!("(!{})", value.code),
+            (PrefixOp::Negate, Layout::Int) => {
+                format!("alloy_neg({}, {})", value.code, self.location(span))
+            }
+            (PrefixOp::Negate, _) => format!("(-{})", value.code),
+            (PrefixOp::BitNot, _) => format!("(~{})", value.code),
+        };
+        Ok(Some(CExpr::new(code, value.purity.max(Purity::Pure))))
+    }
+
+    /// Emits every infix operator except the short-circuiting ones
+    fn emit_arithmetic(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Emission {
+        let Some(values) = self.emit_operands(&[lh, rh])? else {
+            return Ok(None);
+        };
+        let purity = Self::combined(&values);
+        let (a, b) = (&values[0].code, &values[1].code);
+        let lh_type = self.type_of(lh)?;
+        let ints = matches!(
+            (&lh_type.layout, &self.type_of(rh)?.layout),
+            (Layout::Int, Layout::Int)
+        );
+        let code = match op {
+            InfixOp::Add | InfixOp::Subtract | InfixOp::Multiply | InfixOp::Power if ints => {
+                let helper = match op {
+                    InfixOp::Add => "add",
+                    InfixOp::Subtract => "sub",
+                    InfixOp::Multiply => "mul",
+                    _ => "pow",
+                };
+                format!("alloy_{helper}({a}, {b}, {})", self.location(span))
+            }
+            InfixOp::Add => format!("({a} + {b})"),
+            InfixOp::Subtract => format!("({a} - {b})"),
+            InfixOp::Multiply => format!("({a} * {b})"),
+            InfixOp::Power => format!("powf({a}, {b})"),
+            InfixOp::Divide => format!("((alloy_float){a} / {b})"),
+            InfixOp::LessThan => format!("({a} < {b})"),
+            InfixOp::LessThanOrEqual => format!("({a} <= {b})"),
+            InfixOp::GreaterThan => format!("({a} > {b})"),
+            InfixOp::GreaterThanOrEqual => format!("({a} >= {b})"),
+            InfixOp::Equals => lh_type.equals(a, b),
+            InfixOp::NotEquals => format!("(!{})", lh_type.equals(a, b)),
+            InfixOp::And | InfixOp::Or => unreachable!("emitted by emit_logical"),
+        };
+        Ok(Some(CExpr::new(code, purity.max(Purity::Pure))))
+    }
+
+    fn emit_if(
+        &mut self,
+        expr: &Expression,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: Option<&Expression>,
+    ) -> Emission {
+        let Some(condition) = self.emit_expression(condition)? else {
+            return Ok(None);
+        };
+        let result = self.result_var(expr)?;
+        self.body.line(&format!("if ({}) {{", condition.code));
+        self.emit_branch(then, result.as_deref())?;
+        if let Some(otherwise) = otherwise {
+            self.body.line("} else {");
+            self.emit_branch(otherwise, result.as_deref())?;
+        }
+        self.body.line("}");
+        Ok(Self::result_value(expr, result))
+    }
+
+    fn emit_while(&mut self, condition: &Expression, body: &Expression) -> Emission {
+        let mark = self.body.lines.len();
+        self.body.indent += 1;
+        let condition = self.emit_expression(condition)?;
+        match condition {
+            // a condition without statements of its own can be tested by the loop itself
+            Some(condition) if self.body.lines.len() == mark => {
+                self.body.indent -= 1;
+                self.body.line(&format!("while ({}) {{", condition.code));
+                self.body.indent += 1;
+            }
+            condition => {
+                let header = format!("{}while (1) {{", "    ".repeat(self.body.indent - 1));
+                self.body.lines.insert(mark, header);
+                if let Some(condition) = condition {
+                    self.body.line(&format!("if (!{}) break;", condition.code));
+                }
+            }
+        }
+        self.body.loops.push(None);
+        self.emit_statement(body)?;
+        self.body.loops.pop();
+        self.body.indent -= 1;
+        self.body.line("}");
+        Ok(Some(CExpr::unit()))
+    }
+
+    fn emit_loop(&mut self, expr: &Expression, body: &Expression) -> Emission {
+        let result = self.result_var(expr)?;
+        self.body.line("while (1) {");
+        self.body.indent += 1;
+        self.body.loops.push(result.clone());
+        self.emit_statement(body)?;
+        self.body.loops.pop();
+        self.body.indent -= 1;
+        self.body.line("}");
+        Ok(Self::result_value(expr, result))
+    }
+
+    fn emit_variable_decl(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        ty: Option<&TypeIdent>,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Emission {
+        let Some(value_code) = self.emit_expression(value)? else {
+            return Ok(None);
+        };
+        let ty = match ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.type_of(value)?,
+        };
+        let c_name = self.declare_var(name, var);
+        self.body
+            .line(&format!("{} {c_name} = {};", ty.name, value_code.code));
+        Ok(Some(CExpr::unit()))
+    }
+
+    fn emit_assignment(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Emission {
+        let Some(value) = self.emit_expression(value)? else {
+            return Ok(None);
+        };
+        let c_name = self
+            .resolve_var(var)
+            .ok_or_else(|| EmitError::unresolved_variable(name, span))?;
+        self.body.line(&format!("{c_name} = {};", value.code));
+        Ok(Some(CExpr::unit()))
+    }
+
+    fn emit_break(&mut self, value: Option<&Expression>) -> Emission {
+        let target = self.body.loops.last().cloned().flatten();
+        if let Some(value) = value {
+            match (self.emit_expression(value)?, target) {
+                (Some(value), Some(target)) => {
+                    self.body.line(&format!("{target} = {};", value.code));
+                }
+                (Some(value), None) => self.discard(&value),
+                (None, _) => {}
+            }
+        }
+        self.body.line("break;");
+        Ok(None)
+    }
+
+    fn emit_struct_literal(&mut self, expr: &Expression, fields: &[FieldInit]) -> Emission {
+        let ty = self.type_of(expr)?;
+        let Some(values) =
+            self.emit_operands(&fields.iter().map(|f| &f.value).collect::<Vec<_>>())?
+        else {
+            return Ok(None);
+        };
+        let inits = fields
+            .iter()
+            .zip(&values)
+            .map(|(field, value)| format!(".{} = {}", c_ident(&field.name), value.code))
+            .collect::<Vec<_>>();
+        Ok(Some(CExpr::new(
+            format!("({}){{ {} }}", ty.name, inits.join(", ")),
+            Self::combined(&values).max(Purity::Pure),
+        )))
+    }
+
+    fn emit_enum_variant(
+        &mut self,
+        expr: &Expression,
+        variant: &str,
+        args: &[Expression],
+        span: &Span,
+    ) -> Emission {
+        let ty = self.type_of(expr)?;
+        let tag = Self::variant_tag(&ty, variant, span)?;
+        let Some(values) = self.emit_operands(&args.iter().collect::<Vec<_>>())? else {
+            return Ok(None);
+        };
+        let code = if values.is_empty() {
+            format!("({}){{ .tag = {tag} }}", ty.name)
+        } else {
+            let payload = values.iter().map(|v| v.code.as_str()).collect::<Vec<_>>();
+            format!(
+                "({}){{ .tag = {tag}, .as.{} = {{ {} }} }}",
+                ty.name,
+                c_ident(variant),
+                payload.join(", ")
+            )
+        };
+        Ok(Some(CExpr::new(
+            code,
+            Self::combined(&values).max(Purity::Pure),
+        )))
+    }
+
+    /// Emits a match as an if/else chain over the arms' pattern tests
+    fn emit_match(
+        &mut self,
+        expr: &Expression,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+    ) -> Emission {
+        let span = &expr.span;
+        let Some(value) = self.emit_expression(scrutinee)? else {
+            return Ok(None);
+        };
+        let scrutinee_type = self.type_of(scrutinee)?;
+        let subject = self.spill(&scrutinee_type, &value.code);
+        let result = self.result_var(expr)?;
+
+        let mut exhaustive = false;
+        for (index, arm) in arms.iter().enumerate() {
+            let mut conditions = vec![];
+            let mut bindings = vec![];
+            Self::emit_pattern(
+                &arm.pattern,
+                &subject,
+                &scrutinee_type,
+                &mut conditions,
+                &mut bindings,
+            )?;
+            let condition = conditions.join(" && ");
+            exhaustive = conditions.is_empty();
+            let header = match (index, exhaustive) {
+                (0, true) => "{".to_string(),
+                (0, false) => format!("if ({condition}) {{"),
+                (_, true) => "} else {".to_string(),
+                (_, false) => format!("}} else if ({condition}) {{"),
+            };
+            self.body.line(&header);
+
+            self.body.indent += 1;
+            for (name, var, ty, access) in bindings {
+                let c_name = self.declare_var(&name, var);
+                self.body.line(&format!("{} {c_name} = {access};", ty.name));
+            }
+            self.body.indent -= 1;
+            self.emit_branch(&arm.body, result.as_deref())?;
+
+            if exhaustive {
+                break;
+            }
+        }
+        if !exhaustive {
+            // unreachable for checked programs, it keeps C compilers from warning
+            self.body.line("} else {");
+            self.body.indent += 1;
+            let location = self.location(span);
+            self.body.line(&format!(
+                "alloy_panic(\"no match arm applies\", {location});"
+            ));
+            self.body.indent -= 1;
+        }
+        self.body.line("}");
+        Ok(Self::result_value(expr, result))
+    }
+
+    fn emit_logical(&mut self, op: InfixOp, lh: &Expression, rh: &Expression) -> Emission {
+        let Some(lh) = self.emit_expression(lh)? else {
+            return Ok(None);
+        };
+        let outer = std::mem::take(&mut self.body.lines);
+        self.body.indent += 1;
+        let rh = self.emit_expression(rh)?;
+        self.body.indent -= 1;
+        let rh_lines = std::mem::replace(&mut self.body.lines, outer);
+
+        let symbol = if matches!(op, InfixOp::And) {
+            "&&"
+        } else {
+            "||"
+        };
+        if let Some(rh) = &rh
+            && rh_lines.is_empty()
+        {
+            return Ok(Some(CExpr::new(
+                format!("({} {symbol} {})", lh.code, rh.code),
+                lh.purity.max(rh.purity).max(Purity::Pure),
+            )));
+        }
+
+        // the right hand side has statements of its own, so it only runs behind an `if`
+        let result = self.spill(&CType::builtin("alloy_bool", Layout::Bool), &lh.code);
+        let test = if symbol == "&&" {
+            result.clone()
+        } else {
+            format!("!{result}")
+        };
+        self.body.line(&format!("if ({test}) {{"));
+        self.body.lines.extend(rh_lines);
+        if let Some(rh) = rh {
+            self.body.indent += 1;
+            self.body.line(&format!("{result} = {};", rh.code));
+            self.body.indent -= 1;
+        }
+        self.body.line("}");
+        Ok(Some(CExpr::new(result, Purity::Stable)))
+    }
+
+    fn emit_call(
+        &mut self,
+        name: &str,
+        function: Option<FunctionId>,
+        args: &[&Expression],
+        span: &Span,
+    ) -> Emission {
+        let Some(c_name) =
+            function.and_then(|function| self.declared_functions.get(&function).cloned())
+        else {
+            // `to_unit` is a builtin that discards its arguments
+            if name == "to_unit" {
+                for arg in args {
+                    self.emit_statement(arg)?;
+                }
+                return Ok(Some(CExpr::unit()));
+            }
+            return Err(EmitError::unresolved_function(name, span));
+        };
+        let Some(values) = self.emit_operands(args)? else {
+            return Ok(None);
+        };
+        let args = values.iter().map(|v| v.code.as_str()).collect::<Vec<_>>();
+        Ok(Some(CExpr::new(
+            format!("{c_name}({})", args.join(", ")),
+            Purity::Impure,
+        )))
+    }
+
+    /// Emits operands that are evaluated left to right, C leaves the order of operands and
+    /// arguments unspecified so anything order dependent is moved into temporaries first
+    fn emit_operands(&mut self, exprs: &[&Expression]) -> Result<Option<Vec<CExpr>>, EmitError> {
+        let mut values: Vec<(CExpr, CType)> = vec![];
+        for expr in exprs {
+            let mark = self.body.lines.len();
+            let Some(value) = self.emit_expression(expr)? else {
+                return Ok(None);
+            };
+            if self.body.lines.len() > mark {
+                // earlier operands have to be read before this operand's statements run
+                let mut spills = vec![];
+                for (earlier, ty) in &mut values {
+                    if earlier.purity != Purity::Stable {
+                        let temp = self.fresh("t");
+                        spills.push(format!(
+                            "{}{} {temp} = {};",
+                            "    ".repeat(self.body.indent),
+                            ty.name,
+                            earlier.code
+                        ));
+                        *earlier = CExpr::new(temp, Purity::Stable);
+                    }
+                }
+                self.body.lines.splice(mark..mark, spills);
+            }
+            values.push((value, self.type_of(expr)?));
+        }
+
+        let impure = values
+            .iter()
+            .filter(|(value, _)| value.purity == Purity::Impure)
+            .count();
+        let mut remaining = impure;
+        for (value, ty) in &mut values {
+            // every call but the last one runs up front, in order
+            if value.purity == Purity::Impure && remaining > 1 {
+                let temp = self.spill(ty, &value.code);
+                *value = CExpr::new(temp, Purity::Stable);
+                remaining -= 1;
+            }
+        }
+        Ok(Some(values.into_iter().map(|(value, _)| value).collect()))
+    }
+
+    fn emit_pattern(
+        pattern: &Pattern,
+        access: &str,
+        ty: &CType,
+        conditions: &mut Vec<String>,
+        bindings: &mut Vec<(String, Option<VarId>, CType, String)>,
+    ) -> Result<(), EmitError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding { name, var } => {
+                bindings.push((name.clone(), *var, ty.clone(), access.to_string()));
+            }
+            PatternKind::LiteralInt(value) => conditions.push(format!("{access} == {value}")),
+            PatternKind::LiteralBool(true) => conditions.push(access.to_string()),
+            PatternKind::LiteralBool(false) => conditions.push(format!("!{access}")),
+            PatternKind::Variant { name, args, .. } => {
+                let tag = Self::variant_tag(ty, name, &pattern.span)?;
+                conditions.push(format!("{access}.tag == {tag}"));
+                let Layout::Enum { variants, .. } = &ty.layout else {
+                    return Err(EmitError::unresolved_type(&ty.name, &pattern.span));
+                };
+                for (index, (arg, arg_type)) in args.iter().zip(&variants[tag].1).enumerate() {
+                    let arg_access = format!("{access}.as.{}._{index}", c_ident(name));
+                    Self::emit_pattern(arg, &arg_access, arg_type, conditions, bindings)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_type_decl(
+        &mut self,
+        name: &str,
+        value: &TypeValue,
+        declared: Option<TypeId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let c_name = self.fresh(name);
+        let ty = match value {
+            TypeValue::Alias(parent) => {
+                let parent = self.resolve_type(parent.resolved(), span)?;
+                writeln!(self.types, "typedef {} {c_name};", parent.name).unwrap();
+                CType {
+                    name: c_name,
+                    layout: parent.layout,
+                }
+            }
+            TypeValue::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        Ok((
+                            field.name.clone(),
+                            self.resolve_type(field.ty.resolved(), span)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let ty = CType {
+                    name: c_name.clone(),
+                    layout: Layout::Struct {
+                        owner: c_name,
+                        display: name.to_string(),
+                        fields,
+                    },
+                };
+                self.emit_struct_helpers(&ty);
+                ty
+            }
+            TypeValue::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let payload = variant
+                            .payload
+                            .iter()
+                            .map(|ty| self.resolve_type(ty.resolved(), span))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok((variant.name.clone(), payload))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let ty = CType {
+                    name: c_name.clone(),
+                    layout: Layout::Enum {
+                        owner: c_name,
+                        display: name.to_string(),
+                        variants,
+                    },
+                };
+                self.emit_enum_helpers(&ty);
+                ty
+            }
+        };
+        if let Some(declared) = declared {
+            self.declared_types.insert(declared, ty);
+        }
+        Ok(())
+    }
+
+    fn emit_struct_helpers(&mut self, ty: &CType) {
+        let Layout::Struct {
+            owner,
+            display,
+            fields,
+        } = &ty.layout
+        else {
+            return;
+        };
+        let out = &mut self.types;
+
+        writeln!(out, "typedef struct {owner} {{").unwrap();
+        for (name, field_type) in fields {
+            writeln!(out, "    {} {};", field_type.name, c_ident(name)).unwrap();
+        }
+        if fields.is_empty() {
+            // C99 has no empty structs
+            writeln!(out, "    alloy_unit unused;").unwrap();
+        }
+        writeln!(out, "}} {owner};\n").unwrap();
+
+        let equal = fields
+            .iter()
+            .map(|(name, field_type)| {
+                let name = c_ident(name);
+                field_type.equals(&format!("a.{name}"), &format!("b.{name}"))
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "static bool alloy_eq_{owner}({owner} a, {owner} b) {{").unwrap();
+        if equal.is_empty() {
+            writeln!(out, "    (void)a;\n    (void)b;\n    return true;").unwrap();
+        } else {
+            writeln!(out, "    return {};", equal.join(" && ")).unwrap();
+        }
+        writeln!(out, "}}\n").unwrap();
+
+        writeln!(out, "static void alloy_print_{owner}({owner} value) {{").unwrap();
+        writeln!(out, "    fputs(\"{display} {{ \", stdout);").unwrap();
+        for (index, (name, field_type)) in fields.iter().enumerate() {
+            let separator = if index > 0 { ", " } else { "" };
+            writeln!(out, "    fputs(\"{separator}{name}: \", stdout);").unwrap();
+            writeln!(
+                out,
+                "    {}(value.{});",
+                field_type.print_fn(),
+                c_ident(name)
+            )
+            .unwrap();
+        }
+        writeln!(out, "    fputs(\" }}\", stdout);\n}}\n").unwrap();
+    }
+
+    fn emit_enum_helpers(&mut self, ty: &CType) {
+        let Layout::Enum {
+            owner,
+            display,
+            variants,
+        } = &ty.layout
+        else {
+            return;
+        };
+        let out = &mut self.types;
+
+        writeln!(out, "typedef struct {owner} {{").unwrap();
+        writeln!(out, "    alloy_int tag;").unwrap();
+        if variants.iter().any(|(_, payload)| !payload.is_empty()) {
+            writeln!(out, "    union {{").unwrap();
+            for (name, payload) in variants.iter().filter(|(_, p)| !p.is_empty()) {
+                let members = payload
+                    .iter()
+                    .enumerate()
+                    .map(|(index, ty)| format!("{} _{index};", ty.name))
+                    .collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "        struct {{ {} }} {};",
+                    members.join(" "),
+                    c_ident(name)
+                )
+                .unwrap();
+            }
+            writeln!(out, "    }} as;").unwrap();
+        }
+        writeln!(out, "}} {owner};\n").unwrap();
+
+        writeln!(out, "static bool alloy_eq_{owner}({owner} a, {owner} b) {{").unwrap();
+        writeln!(
+            out,
+            "    if (a.tag != b.tag) {{\n        return false;\n    }}"
+        )
+        .unwrap();
+        writeln!(out, "    switch (a.tag) {{").unwrap();
+        for (tag, (name, payload)) in variants.iter().enumerate() {
+            if payload.is_empty() {
+                continue;
+            }
+            let equal = payload
+                .iter()
+                .enumerate()
+                .map(|(index, ty)| {
+                    let access = format!("as.{}._{index}", c_ident(name));
+                    ty.equals(&format!("a.{access}"), &format!("b.{access}"))
+                })
+                .collect::<Vec<_>>();
+            writeln!(
+                out,
+                "    case {tag}:\n        return {};",
+                equal.join(" && ")
+            )
+            .unwrap();
+        }
+        writeln!(out, "    default:\n        return true;\n    }}\n}}\n").unwrap();
+
+        writeln!(out, "static void alloy_print_{owner}({owner} value) {{").unwrap();
+        writeln!(out, "    switch (value.tag) {{").unwrap();
+        for (tag, (name, payload)) in variants.iter().enumerate() {
+            writeln!(out, "    case {tag}:").unwrap();
+            if payload.is_empty() {
+                writeln!(out, "        fputs(\"{display}.{name}\", stdout);").unwrap();
+            } else {
+                writeln!(out, "        fputs(\"{display}.{name}(\", stdout);").unwrap();
+                for (index, ty) in payload.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(out, "        fputs(\", \", stdout);").unwrap();
+                    }
+                    writeln!(
+                        out,
+                        "        {}(value.as.{}._{index});",
+                        ty.print_fn(),
+                        c_ident(name)
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "        fputs(\")\", stdout);").unwrap();
+            }
+            writeln!(out, "        break;").unwrap();
+        }
+        writeln!(out, "    }}\n}}\n").unwrap();
+    }
+
+    fn emit_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        return_ty: Option<&TypeIdent>,
+        body: &Expression,
+        declared: Option<FunctionId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let c_name = self.fresh(name);
+        let return_type = match return_ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.resolve_type(("Unit", UNIT_TYPE_ID), span)?,
+        };
+        let param_types = params
+            .iter()
+            .map(|param| self.resolve_type(param.ty.resolved(), span))
+            .collect::<Result<Vec<_>, _>>()?;
+        // declared before the body is emitted so that functions can recurse
+        if let Some(declared) = declared {
+            self.declared_functions.insert(declared, c_name.clone());
+        }
+
+        let outer = std::mem::replace(&mut self.body, Body::new());
+        let params = params
+            .iter()
+            .zip(&param_types)
+            .map(|(param, ty)| format!("{} {}", ty.name, self.declare_var(&param.name, param.var)))
+            .collect::<Vec<_>>();
+        let value = self.emit_expression(body);
+        let function_body = std::mem::replace(&mut self.body, outer);
+        let value = value?;
+
+        let params = if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        };
+        let signature = format!("static {} {c_name}({params})", return_type.name);
+        writeln!(self.prototypes, "{signature};").unwrap();
+        writeln!(self.functions, "{signature} {{").unwrap();
+        for line in &function_body.lines {
+            writeln!(self.functions, "{line}").unwrap();
+        }
+        if let Some(value) = value {
+            writeln!(self.functions, "    return {};", value.code).unwrap();
+        }
+        writeln!(self.functions, "}}\n").unwrap();
+        Ok(())
+    }
+
+    /// Declares the variable a conditional or loop stores its value in, if it has one
+    fn result_var(&mut self, expr: &Expression) -> Result<Option<String>, EmitError> {
+        let ty = self.type_of(expr)?;
+        if expr.ty().1 == NEVER_TYPE_ID || matches!(ty.layout, Layout::Unit) {
+            return Ok(None);
+        }
+        let name = self.fresh("t");
+        self.body.line(&format!("{} {name};", ty.name));
+        Ok(Some(name))
+    }
+
+    fn result_value(expr: &Expression, result: Option<String>) -> Option<CExpr> {
+        if expr.ty().1 == NEVER_TYPE_ID {
+            return None;
+        }
+        Some(result.map_or_else(CExpr::unit, |result| CExpr::new(result, Purity::Stable)))
+    }
+
+    /// Evaluates a value into a new temporary
+    fn spill(&mut self, ty: &CType, code: &str) -> String {
+        let name = self.fresh("t");
+        self.body.line(&format!("{} {name} = {code};", ty.name));
+        name
+    }
+
+    fn discard(&mut self, value: &CExpr) {
+        if value.purity == Purity::Impure {
+            self.body.line(&format!("{};", value.code));
+        }
+    }
+
+    fn combined(values: &[CExpr]) -> Purity {
+        values
+            .iter()
+            .map(|value| value.purity)
+            .max()
+            .unwrap_or(Purity::Stable)
+    }
+
+    fn fresh(&mut self, base: &str) -> String {
+        self.next_id += 1;
+        // declarations of imported modules are named like `math.pi`
+        format!("{}_{}", base.replace('.', "_"), self.next_id)
+    }
+
+    fn location(&self, span: &Span) -> String {
+        let module = self.tree.module_at(span.0);
+        let (line, col) = module.location(span.0);
+        let path = module.file_path.display().to_string();
+        format!(
+            "\"{}:{line}:{col}\"",
+            path.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    }
+
+    fn declare_var(&mut self, name: &str, var: Option<VarId>) -> String {
+        let c_name = self.fresh(name);
+        if let Some(var) = var {
+            self.vars.insert(var, c_name.clone());
+        }
+        c_name
+    }
+
+    fn resolve_var(&self, var: Option<VarId>) -> Option<String> {
+        var.and_then(|var| self.vars.get(&var).cloned())
+    }
+
+    fn resolve_type(&self, (name, id): (&str, TypeId), span: &Span) -> Result<CType, EmitError> {
+        self.declared_types
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| EmitError::unresolved_type(name, span))
+    }
+
+    fn type_of(&self, expr: &Expression) -> Result<CType, EmitError> {
+        self.resolve_type(expr.ty(), &expr.span)
+    }
+
+    fn variant_tag(ty: &CType, variant: &str, span: &Span) -> Result<usize, EmitError> {
+        match &ty.layout {
+            Layout::Enum { variants, .. } => variants
+                .iter()
+                .position(|(name, _)| name == variant)
+                .ok_or_else(|| EmitError::unresolved_type(&format!("{}.{variant}", ty.name), span)),
+            _ => Err(EmitError::unresolved_type(&ty.name, span)),
+        }
+    }
+}
+
+/// Alloy identifiers that are C keywords get a trailing underscore
+fn c_ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,44 @@
+use crate::common::span::Span;
+
+#[derive(Debug)]
+pub struct EmitError {
+    pub span: Span,
+    pub kind: EmitErrorKind,
+}
+
+#[derive(Debug)]
+pub enum EmitErrorKind {
+    // the checker resolves every name, so these point at bugs rather than faulty programs
+    UnresolvedVariable(String),
+    UnresolvedFunction(String),
+    UnresolvedType(String),
+    // a construct the target has no lowering for
+    Unsupported(String),
+}
+
+impl EmitError {
+    pub fn unresolved_variable(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: EmitErrorKind::UnresolvedVariable(name.to_string()),
+        }
+    }
+    pub fn unresolved_function(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: EmitErrorKind::UnresolvedFunction(name.to_string()),
+        }
+    }
+    pub fn unresolved_type(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: EmitErrorKind::UnresolvedType(name.to_string()),
+        }
+    }
+    pub fn unsupported(construct: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: EmitErrorKind::Unsupported(construct.to_string()),
+        }
+    }
+}
@@ -0,0 +1,1318 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    checking::scope::{BOOL_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, NEVER_TYPE_ID, UNIT_TYPE_ID},
+    common::{
+        ast::Ast,
+        expression::{
+            Expression, ExpressionKind, FieldInit, FunctionId, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeId, TypeIdent, TypeValue, VarId,
+        },
+        span::Span,
+    },
+    emitting::errors::EmitError,
+    module::tree::ModuleTree,
+};
+
+pub const PRELUDE: &str = include_str!("prelude.ll");
+
+const UNIT: &str = "zeroinitializer";
+
+#[derive(Clone)]
+enum Layout {
+    Unit,
+    Int,
+    Float,
+    Bool,
+    Struct {
+        owner: String,
+        display: String,
+        fields: Vec<(String, LlvmType)>,
+    },
+    // the tag comes first, followed by the payloads of every variant one after the other
+    Enum {
+        owner: String,
+        display: String,
+        variants: Vec<(String, Vec<LlvmType>)>,
+    },
+}
+
+/// An alloy type as it is spelled in LLVM IR, aliases share the type they refer to
+#[derive(Clone)]
+struct LlvmType {
+    name: String,
+    layout: Layout,
+}
+
+impl LlvmType {
+    fn builtin(name: &str, layout: Layout) -> Self {
+        Self {
+            name: name.to_string(),
+            layout,
+        }
+    }
+
+    const fn is_unit(&self) -> bool {
+        matches!(self.layout, Layout::Unit)
+    }
+
+    fn print_fn(&self) -> String {
+        match &self.layout {
+            Layout::Unit => "@alloy_print_unit".to_string(),
+            Layout::Int => "@alloy_print_int".to_string(),
+            Layout::Float => "@alloy_print_float".to_string(),
+            Layout::Bool => "@alloy_print_bool".to_string(),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => {
+                format!("@alloy_print_{owner}")
+            }
+        }
+    }
+
+    /// The instruction comparing two values of this type, `None` when they're always equal
+    fn equality(&self, lh: &str, rh: &str) -> Option<String> {
+        match &self.layout {
+            Layout::Unit => None,
+            Layout::Int | Layout::Bool => Some(format!("icmp eq {} {lh}, {rh}", self.name)),
+            Layout::Float => Some(format!("fcmp oeq float {lh}, {rh}")),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => Some(format!(
+                "call i1 @alloy_eq_{owner}({0} {lh}, {0} {rh})",
+                self.name
+            )),
+        }
+    }
+
+    /// Position of a variant's payload in an enum's aggregate
+    fn payload_offset(variants: &[(String, Vec<Self>)], tag: usize) -> usize {
+        1 + variants[..tag]
+            .iter()
+            .map(|(_, payload)| payload.len())
+            .sum::<usize>()
+    }
+}
+
+type Emission = Result<Option<String>, EmitError>;
+
+#[derive(Clone)]
+enum Slot {
+    // immutable variables are the SSA value they were declared with
+    Value(String),
+    // mutable variables live in an `alloca`
+    Pointer(String),
+}
+
+struct LoopTarget {
+    next: String,
+    exit: String,
+    // the value of every `break` along with the block it jumps from
+    breaks: Vec<(String, String)>,
+}
+
+/// The function being emitted
+struct Function {
+    allocas: Vec<String>,
+    lines: Vec<String>,
+    block: String,
+    loops: Vec<LoopTarget>,
+    // where each variable declared in the function lives
+    vars: HashMap<VarId, (Slot, LlvmType)>,
+}
+
+impl Function {
+    fn new() -> Self {
+        Self {
+            allocas: vec![],
+            lines: vec![],
+            block: "entry".to_string(),
+            loops: vec![],
+            vars: HashMap::new(),
+        }
+    }
+
+    fn render(&self, signature: &str) -> String {
+        let mut output = format!("{signature} {{\nentry:\n");
+        for line in self.allocas.iter().chain(&self.lines) {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push_str("}\n");
+        output
+    }
+}
+
+/// Translates a checked `Ast` into a textual LLVM module whose `main` runs the top level
+/// expressions and prints the value of the last one, like `alloy run`
+///
+/// The output uses opaque pointers, so LLVM 14 tools need `-opaque-pointers` to read it
+pub struct LlvmEmitter<'a> {
+    tree: &'a ModuleTree,
+    types: String,
+    strings: Vec<(String, String)>,
+    helpers: String,
+    functions: String,
+    // every function along with its return type, and every type
+    declared_functions: HashMap<FunctionId, (String, LlvmType)>,
+    declared_types: HashMap<TypeId, LlvmType>,
+    function: Function,
+    next_id: usize,
+}
+
+impl<'a> LlvmEmitter<'a> {
+    pub fn new(tree: &'a ModuleTree) -> Self {
+        let declared_types = HashMap::from([
+            (UNIT_TYPE_ID, LlvmType::builtin("{}", Layout::Unit)),
+            (INT_TYPE_ID, LlvmType::builtin("i64", Layout::Int)),
+            (FLOAT_TYPE_ID, LlvmType::builtin("float", Layout::Float)),
+            (BOOL_TYPE_ID, LlvmType::builtin("i1", Layout::Bool)),
+            (NEVER_TYPE_ID, LlvmType::builtin("{}", Layout::Unit)),
+        ]);
+        Self {
+            tree,
+            types: String::new(),
+            strings: vec![],
+            helpers: String::new(),
+            functions: String::new(),
+            declared_functions: HashMap::new(),
+            declared_types,
+            function: Function::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn emit(mut self, ast: &Ast) -> Result<String, EmitError> {
+        if let Some(value) = self.emit_sequence(&ast.0)? {
+            if let Some(last) = ast.0.last() {
+                let ty = self.type_of(last)?;
+                if !ty.is_unit() {
+                    self.instr(&format!("call void {}({} {value})", ty.print_fn(), ty.name));
+                    self.instr("call void @alloy_print_str(ptr @alloy.newline)");
+                }
+            }
+            self.instr("ret i32 0");
+        }
+        let main = self.function.render("define i32 @main()");
+
+        let mut output = PRELUDE.to_string();
+        let strings = self
+            .strings
+            .iter()
+            .map(|(text, name)| string_constant(name, text))
+            .collect::<String>();
+        for section in [&self.types, &strings, &self.helpers, &self.functions] {
+            if !section.is_empty() {
+                output.push('\n');
+                output.push_str(section);
+            }
+        }
+        output.push('\n');
+        output.push_str(&main);
+        Ok(output)
+    }
+
+    fn emit_expression(&mut self, expr: &Expression) -> Emission {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => Ok(Some(value.to_string())),
+            ExpressionKind::LiteralFloat(value) => Ok(Some(float_constant(*value))),
+            ExpressionKind::LiteralBool(value) => Ok(Some(value.to_string())),
+            ExpressionKind::Ident { name, var } => {
+                let (slot, ty) = self
+                    .resolve_var(*var)
+                    .ok_or_else(|| EmitError::unresolved_variable(name, span))?;
+                Ok(Some(match slot {
+                    Slot::Value(value) => value,
+                    Slot::Pointer(pointer) => {
+                        self.assign(name, &format!("load {}, ptr {pointer}", ty.name))
+                    }
+                }))
+            }
+            ExpressionKind::Prefix { op, rh } => self.emit_prefix(*op, rh, span),
+            ExpressionKind::Infix { op, lh, rh } => match op {
+                InfixOp::And | InfixOp::Or => self.emit_logical(*op, lh, rh),
+                _ => self.emit_infix(*op, lh, rh, span),
+            },
+            ExpressionKind::Block(exprs) => self.emit_sequence(exprs),
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => self.emit_if(expr, condition, then, otherwise.as_deref()),
+            ExpressionKind::While { condition, body } => self.emit_while(condition, body),
+            ExpressionKind::Loop(body) => self.emit_loop(expr, body),
+            ExpressionKind::Break(value) => self.emit_break(value.as_deref(), span),
+            ExpressionKind::Continue => {
+                let next = self
+                    .function
+                    .loops
+                    .last()
+                    .map(|target| target.next.clone())
+                    .ok_or_else(|| EmitError::unsupported("`continue` outside of a loop", span))?;
+                self.instr(&format!("br label %{next}"));
+                Ok(None)
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                var,
+                ..
+            } => self.emit_variable_decl(name, value, *mutable, ty.as_ref(), *var, span),
+            ExpressionKind::VariableAssignment { name, value, var } => {
+                self.emit_assignment(name, value, *var, span)
+            }
+            ExpressionKind::TypeDecl {
+                name, value, ty, ..
+            } => {
+                self.emit_type_decl(name, value, *ty, span)?;
+                Ok(Some(UNIT.to_string()))
+            }
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                return_ty,
+                body,
+                function,
+                ..
+            } => {
+                self.emit_function(name, params, return_ty.as_ref(), body, *function, span)?;
+                Ok(Some(UNIT.to_string()))
+            }
+            ExpressionKind::FunctionCall {
+                name,
+                args,
+                function,
+            } => self.emit_call(name, *function, &args.iter().collect::<Vec<_>>(), span),
+            ExpressionKind::MethodCall {
+                caller,
+                name,
+                args,
+                function,
+            } => {
+                let args = std::iter::once(caller.as_ref())
+                    .chain(args.iter())
+                    .collect::<Vec<_>>();
+                self.emit_call(name, *function, &args, span)
+            }
+            ExpressionKind::StructLiteral { fields, .. } => {
+                self.emit_struct_literal(expr, fields, span)
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                self.emit_field_access(target, field, span)
+            }
+            ExpressionKind::EnumVariant { variant, args, .. } => {
+                self.emit_enum_variant(expr, variant, args, span)
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => self.emit_match(expr, scrutinee, arms),
+        }
+    }
+
+    fn emit_prefix(&mut self, op: PrefixOp, rh: &Expression, span: &Span) -> Emission {
+        let Some(value) = self.emit_expression(rh)? else {
+            return Ok(None);
+        };
+        let instruction = match (op, self.type_of(rh)?.layout) {
+            (PrefixOp::Not, Layout::Int) => format!("icmp eq i64 {value}, 0"),
+            (PrefixOp::Not, Layout::Float) => {
+                format!("fcmp oeq float {value}, {}", float_constant(0.0))
+            }
+            (PrefixOp::Not, _) => format!("xor i1 {value}, true"),
+            (PrefixOp::Negate, Layout::Int) => {
+                return Ok(Some(self.checked("ssub", "0", &value, span)));
+            }
+            (PrefixOp::Negate, _) => format!("fneg float {value}"),
+            (PrefixOp::BitNot, _) => format!("xor i64 {value}, -1"),
+        };
+        Ok(Some(self.assign("t", &instruction)))
+    }
+
+    fn emit_if(
+        &mut self,
+        expr: &Expression,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: Option<&Expression>,
+    ) -> Emission {
+        let Some(condition) = self.emit_expression(condition)? else {
+            return Ok(None);
+        };
+        let then_label = self.fresh("then");
+        let end_label = self.fresh("end");
+        let else_label = match otherwise {
+            Some(_) => self.fresh("else"),
+            None => end_label.clone(),
+        };
+        self.instr(&format!(
+            "br i1 {condition}, label %{then_label}, label %{else_label}"
+        ));
+
+        let mut incoming = vec![];
+        self.label(&then_label);
+        self.emit_branch(then, &end_label, &mut incoming)?;
+        if let Some(otherwise) = otherwise {
+            self.label(&else_label);
+            self.emit_branch(otherwise, &end_label, &mut incoming)?;
+        } else {
+            // the condition jumps straight past `then`
+            incoming.push((UNIT.to_string(), String::new()));
+        }
+        if incoming.is_empty() {
+            return Ok(None);
+        }
+        self.label(&end_label);
+        self.merge(expr, &incoming)
+    }
+
+    fn emit_while(&mut self, condition: &Expression, body: &Expression) -> Emission {
+        let header = self.fresh("while");
+        let body_label = self.fresh("body");
+        let exit = self.fresh("exit");
+        self.instr(&format!("br label %{header}"));
+        self.label(&header);
+        let Some(condition) = self.emit_expression(condition)? else {
+            return Ok(None);
+        };
+        self.instr(&format!(
+            "br i1 {condition}, label %{body_label}, label %{exit}"
+        ));
+        self.label(&body_label);
+        self.function.loops.push(LoopTarget {
+            next: header.clone(),
+            exit: exit.clone(),
+            breaks: vec![],
+        });
+        let value = self.emit_expression(body);
+        self.function.loops.pop();
+        if value?.is_some() {
+            self.instr(&format!("br label %{header}"));
+        }
+        self.label(&exit);
+        Ok(Some(UNIT.to_string()))
+    }
+
+    fn emit_loop(&mut self, expr: &Expression, body: &Expression) -> Emission {
+        let body_label = self.fresh("loop");
+        let exit = self.fresh("exit");
+        self.instr(&format!("br label %{body_label}"));
+        self.label(&body_label);
+        self.function.loops.push(LoopTarget {
+            next: body_label.clone(),
+            exit: exit.clone(),
+            breaks: vec![],
+        });
+        let value = self.emit_expression(body);
+        let target = self.function.loops.pop().expect("Loop target was pushed");
+        if value?.is_some() {
+            self.instr(&format!("br label %{body_label}"));
+        }
+        // a loop without a `break` never finishes
+        if target.breaks.is_empty() {
+            return Ok(None);
+        }
+        self.label(&exit);
+        self.merge(expr, &target.breaks)
+    }
+
+    fn emit_break(&mut self, value: Option<&Expression>, span: &Span) -> Emission {
+        let value = match value {
+            Some(value) => match self.emit_expression(value)? {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            None => UNIT.to_string(),
+        };
+        let block = self.function.block.clone();
+        let target = self
+            .function
+            .loops
+            .last_mut()
+            .ok_or_else(|| EmitError::unsupported("`break` outside of a loop", span))?;
+        target.breaks.push((value, block));
+        let exit = target.exit.clone();
+        self.instr(&format!("br label %{exit}"));
+        Ok(None)
+    }
+
+    fn emit_variable_decl(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        mutable: bool,
+        ty: Option<&TypeIdent>,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Emission {
+        let Some(value_code) = self.emit_expression(value)? else {
+            return Ok(None);
+        };
+        let ty = match ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.type_of(value)?,
+        };
+        let slot = if mutable {
+            let pointer = self.fresh_local(name);
+            self.function
+                .allocas
+                .push(format!("  {pointer} = alloca {}", ty.name));
+            self.instr(&format!("store {} {value_code}, ptr {pointer}", ty.name));
+            Slot::Pointer(pointer)
+        } else {
+            Slot::Value(value_code)
+        };
+        self.bind(var, slot, ty);
+        Ok(Some(UNIT.to_string()))
+    }
+
+    fn emit_assignment(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Emission {
+        let Some(value) = self.emit_expression(value)? else {
+            return Ok(None);
+        };
+        let Some((Slot::Pointer(pointer), ty)) = self.resolve_var(var) else {
+            return Err(EmitError::unresolved_variable(name, span));
+        };
+        self.instr(&format!("store {} {value}, ptr {pointer}", ty.name));
+        Ok(Some(UNIT.to_string()))
+    }
+
+    fn emit_struct_literal(
+        &mut self,
+        expr: &Expression,
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Emission {
+        let ty = self.type_of(expr)?;
+        let Layout::Struct {
+            fields: declared, ..
+        } = &ty.layout
+        else {
+            return Err(EmitError::unresolved_type(expr.ty().0, span));
+        };
+        let mut values = vec![];
+        for field in fields {
+            let Some(value) = self.emit_expression(&field.value)? else {
+                return Ok(None);
+            };
+            values.push((field.name.as_str(), value));
+        }
+        let mut aggregate = if declared.is_empty() { UNIT } else { "undef" }.to_string();
+        for (index, (field, field_type)) in declared.iter().enumerate() {
+            let Some((_, value)) = values.iter().find(|(name, _)| name == field) else {
+                return Err(EmitError::unsupported(
+                    &format!("missing field `{field}`"),
+                    span,
+                ));
+            };
+            aggregate = self.assign(
+                "t",
+                &format!(
+                    "insertvalue {} {aggregate}, {} {value}, {index}",
+                    ty.name, field_type.name
+                ),
+            );
+        }
+        Ok(Some(aggregate))
+    }
+
+    fn emit_field_access(&mut self, target: &Expression, field: &str, span: &Span) -> Emission {
+        let Some(value) = self.emit_expression(target)? else {
+            return Ok(None);
+        };
+        let ty = self.type_of(target)?;
+        let index = match &ty.layout {
+            Layout::Struct { fields, .. } => fields.iter().position(|(name, _)| name == field),
+            _ => None,
+        }
+        .ok_or_else(|| EmitError::unresolved_variable(field, span))?;
+        Ok(Some(self.assign(
+            field,
+            &format!("extractvalue {} {value}, {index}", ty.name),
+        )))
+    }
+
+    fn emit_enum_variant(
+        &mut self,
+        expr: &Expression,
+        variant: &str,
+        args: &[Expression],
+        span: &Span,
+    ) -> Emission {
+        let ty = self.type_of(expr)?;
+        let Layout::Enum { variants, .. } = &ty.layout else {
+            return Err(EmitError::unresolved_type(&ty.name, span));
+        };
+        let tag = Self::variant_tag(&ty, variant, span)?;
+        let mut values = vec![];
+        for arg in args {
+            let Some(value) = self.emit_expression(arg)? else {
+                return Ok(None);
+            };
+            values.push(value);
+        }
+        let offset = LlvmType::payload_offset(variants, tag);
+        let mut aggregate =
+            self.assign("t", &format!("insertvalue {} undef, i64 {tag}, 0", ty.name));
+        for (index, (value, arg_type)) in values.iter().zip(&variants[tag].1).enumerate() {
+            aggregate = self.assign(
+                "t",
+                &format!(
+                    "insertvalue {} {aggregate}, {} {value}, {}",
+                    ty.name,
+                    arg_type.name,
+                    offset + index
+                ),
+            );
+        }
+        Ok(Some(aggregate))
+    }
+
+    /// Tests the arms' patterns in order, falling through to the next arm's label
+    fn emit_match(
+        &mut self,
+        expr: &Expression,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+    ) -> Emission {
+        let span = &expr.span;
+        let Some(value) = self.emit_expression(scrutinee)? else {
+            return Ok(None);
+        };
+        let scrutinee_type = self.type_of(scrutinee)?;
+        let end_label = self.fresh("end");
+        let mut labels = arms
+            .iter()
+            .skip(1)
+            .map(|_| self.fresh("arm"))
+            .collect::<Vec<_>>();
+        labels.push(self.fresh("no_match"));
+
+        let mut incoming = vec![];
+        let mut exhaustive = false;
+        for (index, arm) in arms.iter().enumerate() {
+            if index > 0 {
+                self.label(&labels[index - 1]);
+            }
+            self.emit_pattern(&arm.pattern, &value, &scrutinee_type, &labels[index])?;
+            self.emit_branch(&arm.body, &end_label, &mut incoming)?;
+
+            if arm.pattern.is_irrefutable() {
+                exhaustive = true;
+                break;
+            }
+        }
+        if !exhaustive {
+            // unreachable for checked programs
+            self.label(labels.last().expect("There is always a no match label"));
+            let location = self.location(span);
+            let message = self.string("no match arm applies");
+            self.instr(&format!(
+                "call void @alloy_panic(ptr {message}, ptr {location})"
+            ));
+            self.instr("unreachable");
+        }
+        if incoming.is_empty() {
+            return Ok(None);
+        }
+        self.label(&end_label);
+        self.merge(expr, &incoming)
+    }
+
+    fn emit_sequence(&mut self, exprs: &[Expression]) -> Emission {
+        let mut value = Some(UNIT.to_string());
+        for expr in exprs {
+            value = self.emit_expression(expr)?;
+            // nothing after a `break` or `continue` can run
+            if value.is_none() {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Emits one arm of a conditional, jumping to `end` with its value if it has one
+    fn emit_branch(
+        &mut self,
+        expr: &Expression,
+        end: &str,
+        incoming: &mut Vec<(String, String)>,
+    ) -> Result<(), EmitError> {
+        if let Some(value) = self.emit_expression(expr)? {
+            incoming.push((value, self.function.block.clone()));
+            self.instr(&format!("br label %{end}"));
+        }
+        Ok(())
+    }
+
+    /// The value of a conditional or loop, a `phi` over the blocks that jump to `end`
+    fn merge(&mut self, expr: &Expression, incoming: &[(String, String)]) -> Emission {
+        let ty = self.type_of(expr)?;
+        if expr.ty().1 == NEVER_TYPE_ID {
+            self.instr("unreachable");
+            return Ok(None);
+        }
+        if ty.is_unit() {
+            return Ok(Some(UNIT.to_string()));
+        }
+        let incoming = incoming
+            .iter()
+            .map(|(value, block)| format!("[ {value}, %{block} ]"))
+            .collect::<Vec<_>>();
+        Ok(Some(self.assign(
+            "t",
+            &format!("phi {} {}", ty.name, incoming.join(", ")),
+        )))
+    }
+
+    fn emit_infix(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Emission {
+        let Some(lh_value) = self.emit_expression(lh)? else {
+            return Ok(None);
+        };
+        let Some(rh_value) = self.emit_expression(rh)? else {
+            return Ok(None);
+        };
+        let lh_type = self.type_of(lh)?;
+        let rh_type = self.type_of(rh)?;
+
+        if matches!(op, InfixOp::Equals | InfixOp::NotEquals) {
+            let equal = lh_type.equality(&lh_value, &rh_value).map_or_else(
+                || "true".to_string(),
+                |equality| self.assign("t", &equality),
+            );
+            if matches!(op, InfixOp::Equals) {
+                return Ok(Some(equal));
+            }
+            return Ok(Some(self.assign("t", &format!("xor i1 {equal}, true"))));
+        }
+
+        let ints = matches!(
+            (&lh_type.layout, &rh_type.layout),
+            (Layout::Int, Layout::Int)
+        );
+        if ints && !matches!(op, InfixOp::Divide) {
+            let instruction = match op {
+                InfixOp::Add => return Ok(Some(self.checked("sadd", &lh_value, &rh_value, span))),
+                InfixOp::Subtract => {
+                    return Ok(Some(self.checked("ssub", &lh_value, &rh_value, span)));
+                }
+                InfixOp::Multiply => {
+                    return Ok(Some(self.checked("smul", &lh_value, &rh_value, span)));
+                }
+                InfixOp::Power => {
+                    let location = self.location(span);
+                    format!("call i64 @alloy_pow(i64 {lh_value}, i64 {rh_value}, ptr {location})")
+                }
+                InfixOp::LessThan => format!("icmp slt i64 {lh_value}, {rh_value}"),
+                InfixOp::LessThanOrEqual => format!("icmp sle i64 {lh_value}, {rh_value}"),
+                InfixOp::GreaterThan => format!("icmp sgt i64 {lh_value}, {rh_value}"),
+                InfixOp::GreaterThanOrEqual => format!("icmp sge i64 {lh_value}, {rh_value}"),
+                _ => unreachable!("handled above"),
+            };
+            return Ok(Some(self.assign("t", &instruction)));
+        }
+
+        // mixing an `Int` with a `Float`, or dividing, computes in `Float`
+        let a = self.promote(lh_value, &lh_type);
+        let b = self.promote(rh_value, &rh_type);
+        let instruction = match op {
+            InfixOp::Add => format!("fadd float {a}, {b}"),
+            InfixOp::Subtract => format!("fsub float {a}, {b}"),
+            InfixOp::Multiply => format!("fmul float {a}, {b}"),
+            InfixOp::Divide => format!("fdiv float {a}, {b}"),
+            InfixOp::Power => format!("call float @llvm.pow.f32(float {a}, float {b})"),
+            InfixOp::LessThan => format!("fcmp olt float {a}, {b}"),
+            InfixOp::LessThanOrEqual => format!("fcmp ole float {a}, {b}"),
+            InfixOp::GreaterThan => format!("fcmp ogt float {a}, {b}"),
+            InfixOp::GreaterThanOrEqual => format!("fcmp oge float {a}, {b}"),
+            _ => unreachable!("handled above"),
+        };
+        Ok(Some(self.assign("t", &instruction)))
+    }
+
+    fn emit_logical(&mut self, op: InfixOp, lh: &Expression, rh: &Expression) -> Emission {
+        let Some(lh) = self.emit_expression(lh)? else {
+            return Ok(None);
+        };
+        let rh_label = self.fresh("rhs");
+        let end_label = self.fresh("end");
+        let (short_circuit, targets) = match op {
+            InfixOp::And => ("false", format!("label %{rh_label}, label %{end_label}")),
+            _ => ("true", format!("label %{end_label}, label %{rh_label}")),
+        };
+        let lh_block = self.function.block.clone();
+        self.instr(&format!("br i1 {lh}, {targets}"));
+
+        self.label(&rh_label);
+        let Some(rh) = self.emit_expression(rh)? else {
+            // only the short circuit reaches the end
+            self.label(&end_label);
+            return Ok(Some(short_circuit.to_string()));
+        };
+        let rh_block = self.function.block.clone();
+        self.instr(&format!("br label %{end_label}"));
+        self.label(&end_label);
+        Ok(Some(self.assign(
+            "t",
+            &format!("phi i1 [ {short_circuit}, %{lh_block} ], [ {rh}, %{rh_block} ]"),
+        )))
+    }
+
+    fn emit_call(
+        &mut self,
+        name: &str,
+        function: Option<FunctionId>,
+        args: &[&Expression],
+        span: &Span,
+    ) -> Emission {
+        let Some((function, return_type)) =
+            function.and_then(|function| self.declared_functions.get(&function).cloned())
+        else {
+            // `to_unit` is a builtin that discards its arguments
+            if name == "to_unit" {
+                for arg in args {
+                    if self.emit_expression(arg)?.is_none() {
+                        return Ok(None);
+                    }
+                }
+                return Ok(Some(UNIT.to_string()));
+            }
+            return Err(EmitError::unresolved_function(name, span));
+        };
+        let mut values = vec![];
+        for arg in args {
+            let Some(value) = self.emit_expression(arg)? else {
+                return Ok(None);
+            };
+            values.push(format!("{} {value}", self.type_of(arg)?.name));
+        }
+        Ok(Some(self.assign(
+            "t",
+            &format!(
+                "call {} {function}({})",
+                return_type.name,
+                values.join(", ")
+            ),
+        )))
+    }
+
+    /// Emits the tests of a pattern, jumping to `fail` as soon as one of them doesn't hold
+    fn emit_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &str,
+        ty: &LlvmType,
+        fail: &str,
+    ) -> Result<(), EmitError> {
+        let test = match &pattern.kind {
+            PatternKind::Wildcard => return Ok(()),
+            PatternKind::Binding { var, .. } => {
+                self.bind(*var, Slot::Value(value.to_string()), ty.clone());
+                return Ok(());
+            }
+            PatternKind::LiteralInt(literal) => format!("icmp eq i64 {value}, {literal}"),
+            PatternKind::LiteralBool(literal) => format!("icmp eq i1 {value}, {literal}"),
+            PatternKind::Variant { name, args, .. } => {
+                let Layout::Enum { variants, .. } = &ty.layout else {
+                    return Err(EmitError::unresolved_type(&ty.name, &pattern.span));
+                };
+                let tag = Self::variant_tag(ty, name, &pattern.span)?;
+                let actual = self.assign("tag", &format!("extractvalue {} {value}, 0", ty.name));
+                self.test(&format!("icmp eq i64 {actual}, {tag}"), fail);
+
+                let offset = LlvmType::payload_offset(variants, tag);
+                for (index, (arg, arg_type)) in args.iter().zip(&variants[tag].1).enumerate() {
+                    // wildcards don't need the value
+                    if matches!(arg.kind, PatternKind::Wildcard) {
+                        continue;
+                    }
+                    let arg_value = self.assign(
+                        "t",
+                        &format!("extractvalue {} {value}, {}", ty.name, offset + index),
+                    );
+                    self.emit_pattern(arg, &arg_value, arg_type, fail)?;
+                }
+                return Ok(());
+            }
+        };
+        self.test(&test, fail);
+        Ok(())
+    }
+
+    fn test(&mut self, instruction: &str, fail: &str) {
+        let holds = self.assign("t", instruction);
+        let next = self.fresh("matched");
+        self.instr(&format!("br i1 {holds}, label %{next}, label %{fail}"));
+        self.label(&next);
+    }
+
+    fn emit_type_decl(
+        &mut self,
+        name: &str,
+        value: &TypeValue,
+        declared: Option<TypeId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let owner = self.fresh(name);
+        let ty = match value {
+            TypeValue::Alias(parent) => self.resolve_type(parent.resolved(), span)?,
+            TypeValue::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        Ok((
+                            field.name.clone(),
+                            self.resolve_type(field.ty.resolved(), span)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let members = fields
+                    .iter()
+                    .map(|(_, ty)| ty.name.as_str())
+                    .collect::<Vec<_>>();
+                writeln!(self.types, "%{owner} = type {{ {} }}", members.join(", ")).unwrap();
+                let ty = LlvmType {
+                    name: format!("%{owner}"),
+                    layout: Layout::Struct {
+                        owner,
+                        display: name.to_string(),
+                        fields,
+                    },
+                };
+                self.emit_struct_helpers(&ty);
+                ty
+            }
+            TypeValue::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let payload = variant
+                            .payload
+                            .iter()
+                            .map(|ty| self.resolve_type(ty.resolved(), span))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok((variant.name.clone(), payload))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let members = std::iter::once("i64")
+                    .chain(
+                        variants
+                            .iter()
+                            .flat_map(|(_, payload)| payload.iter().map(|ty| ty.name.as_str())),
+                    )
+                    .collect::<Vec<_>>();
+                writeln!(self.types, "%{owner} = type {{ {} }}", members.join(", ")).unwrap();
+                let ty = LlvmType {
+                    name: format!("%{owner}"),
+                    layout: Layout::Enum {
+                        owner,
+                        display: name.to_string(),
+                        variants,
+                    },
+                };
+                self.emit_enum_helpers(&ty);
+                ty
+            }
+        };
+        if let Some(declared) = declared {
+            self.declared_types.insert(declared, ty);
+        }
+        Ok(())
+    }
+
+    fn emit_struct_helpers(&mut self, ty: &LlvmType) {
+        let Layout::Struct {
+            owner,
+            display,
+            fields,
+        } = &ty.layout
+        else {
+            return;
+        };
+        let name = &ty.name;
+
+        let mut eq = Function::new();
+        let mut all = "true".to_string();
+        for (index, (_, field_type)) in fields.iter().enumerate() {
+            eq.lines
+                .push(format!("  %a.{index} = extractvalue {name} %a, {index}"));
+            eq.lines
+                .push(format!("  %b.{index} = extractvalue {name} %b, {index}"));
+            let Some(equality) =
+                field_type.equality(&format!("%a.{index}"), &format!("%b.{index}"))
+            else {
+                continue;
+            };
+            eq.lines.push(format!("  %eq.{index} = {equality}"));
+            eq.lines
+                .push(format!("  %all.{index} = and i1 {all}, %eq.{index}"));
+            all = format!("%all.{index}");
+        }
+        eq.lines.push(format!("  ret i1 {all}"));
+        writeln!(
+            self.helpers,
+            "{}",
+            eq.render(&format!(
+                "define private i1 @alloy_eq_{owner}({name} %a, {name} %b)"
+            ))
+        )
+        .unwrap();
+
+        let mut print = Function::new();
+        let open = self.string(&format!("{display} {{ "));
+        print
+            .lines
+            .push(format!("  call void @alloy_print_str(ptr {open})"));
+        for (index, (field, field_type)) in fields.iter().enumerate() {
+            let separator = if index > 0 { ", " } else { "" };
+            let label = self.string(&format!("{separator}{field}: "));
+            print.lines.extend([
+                format!("  call void @alloy_print_str(ptr {label})"),
+                format!("  %field.{index} = extractvalue {name} %value, {index}"),
+                format!(
+                    "  call void {}({} %field.{index})",
+                    field_type.print_fn(),
+                    field_type.name
+                ),
+            ]);
+        }
+        let close = self.string(" }");
+        print
+            .lines
+            .push(format!("  call void @alloy_print_str(ptr {close})"));
+        print.lines.push("  ret void".to_string());
+        writeln!(
+            self.helpers,
+            "{}",
+            print.render(&format!(
+                "define private void @alloy_print_{owner}({name} %value)"
+            ))
+        )
+        .unwrap();
+    }
+
+    fn emit_enum_helpers(&mut self, ty: &LlvmType) {
+        let Layout::Enum {
+            owner,
+            display,
+            variants,
+        } = &ty.layout
+        else {
+            return;
+        };
+        let name = &ty.name;
+        let cases = (0..variants.len())
+            .map(|tag| format!("i64 {tag}, label %variant.{tag}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.emit_enum_eq(name, owner, variants, &cases);
+        self.emit_enum_print(name, owner, display, variants, &cases);
+    }
+
+    /// `alloy_eq_*` compares the tags, then the payload of the variant both share
+    fn emit_enum_eq(
+        &mut self,
+        name: &str,
+        owner: &str,
+        variants: &[(String, Vec<LlvmType>)],
+        cases: &str,
+    ) {
+        let mut eq = Function::new();
+        eq.lines.extend([
+            format!("  %a.tag = extractvalue {name} %a, 0"),
+            format!("  %b.tag = extractvalue {name} %b, 0"),
+            "  %same = icmp eq i64 %a.tag, %b.tag".to_string(),
+            "  br i1 %same, label %compare, label %different".to_string(),
+            "different:".to_string(),
+            "  ret i1 false".to_string(),
+            "compare:".to_string(),
+            format!("  switch i64 %a.tag, label %equal [ {cases} ]"),
+        ]);
+        for (tag, (_, payload)) in variants.iter().enumerate() {
+            eq.lines.push(format!("variant.{tag}:"));
+            let offset = LlvmType::payload_offset(variants, tag);
+            let mut all = "true".to_string();
+            for (index, arg_type) in payload.iter().enumerate() {
+                let position = offset + index;
+                eq.lines.extend([
+                    format!("  %a.{position} = extractvalue {name} %a, {position}"),
+                    format!("  %b.{position} = extractvalue {name} %b, {position}"),
+                ]);
+                let Some(equality) =
+                    arg_type.equality(&format!("%a.{position}"), &format!("%b.{position}"))
+                else {
+                    continue;
+                };
+                eq.lines.extend([
+                    format!("  %eq.{position} = {equality}"),
+                    format!("  %all.{position} = and i1 {all}, %eq.{position}"),
+                ]);
+                all = format!("%all.{position}");
+            }
+            eq.lines.push(format!("  ret i1 {all}"));
+        }
+        eq.lines
+            .extend(["equal:".to_string(), "  ret i1 true".to_string()]);
+        writeln!(
+            self.helpers,
+            "{}",
+            eq.render(&format!(
+                "define private i1 @alloy_eq_{owner}({name} %a, {name} %b)"
+            ))
+        )
+        .unwrap();
+    }
+
+    fn emit_enum_print(
+        &mut self,
+        name: &str,
+        owner: &str,
+        display: &str,
+        variants: &[(String, Vec<LlvmType>)],
+        cases: &str,
+    ) {
+        let mut print = Function::new();
+        print.lines.extend([
+            format!("  %tag = extractvalue {name} %value, 0"),
+            format!("  switch i64 %tag, label %end [ {cases} ]"),
+        ]);
+        for (tag, (variant, payload)) in variants.iter().enumerate() {
+            print.lines.push(format!("variant.{tag}:"));
+            if payload.is_empty() {
+                let text = self.string(&format!("{display}.{variant}"));
+                print
+                    .lines
+                    .push(format!("  call void @alloy_print_str(ptr {text})"));
+            } else {
+                let open = self.string(&format!("{display}.{variant}("));
+                print
+                    .lines
+                    .push(format!("  call void @alloy_print_str(ptr {open})"));
+                let offset = LlvmType::payload_offset(variants, tag);
+                for (index, arg_type) in payload.iter().enumerate() {
+                    let position = offset + index;
+                    if index > 0 {
+                        let separator = self.string(", ");
+                        print
+                            .lines
+                            .push(format!("  call void @alloy_print_str(ptr {separator})"));
+                    }
+                    print.lines.extend([
+                        format!("  %arg.{position} = extractvalue {name} %value, {position}"),
+                        format!(
+                            "  call void {}({} %arg.{position})",
+                            arg_type.print_fn(),
+                            arg_type.name
+                        ),
+                    ]);
+                }
+                let close = self.string(")");
+                print
+                    .lines
+                    .push(format!("  call void @alloy_print_str(ptr {close})"));
+            }
+            print.lines.push("  br label %end".to_string());
+        }
+        print
+            .lines
+            .extend(["end:".to_string(), "  ret void".to_string()]);
+        writeln!(
+            self.helpers,
+            "{}",
+            print.render(&format!(
+                "define private void @alloy_print_{owner}({name} %value)"
+            ))
+        )
+        .unwrap();
+    }
+
+    fn emit_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        return_ty: Option<&TypeIdent>,
+        body: &Expression,
+        declared: Option<FunctionId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let function = format!("@{}", self.fresh(name));
+        let return_type = match return_ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.resolve_type(("Unit", UNIT_TYPE_ID), span)?,
+        };
+        let param_types = params
+            .iter()
+            .map(|param| self.resolve_type(param.ty.resolved(), span))
+            .collect::<Result<Vec<_>, _>>()?;
+        // declared before the body is emitted so that functions can recurse
+        if let Some(declared) = declared {
+            self.declared_functions
+                .insert(declared, (function.clone(), return_type.clone()));
+        }
+
+        let outer = std::mem::replace(&mut self.function, Function::new());
+        let mut signature = vec![];
+        for (param, ty) in params.iter().zip(param_types) {
+            let value = self.fresh_local(&param.name);
+            signature.push(format!("{} {value}", ty.name));
+            self.bind(param.var, Slot::Value(value), ty);
+        }
+        let value = self.emit_expression(body);
+        if let Ok(Some(value)) = &value {
+            self.instr(&format!("ret {} {value}", return_type.name));
+        }
+        let emitted = std::mem::replace(&mut self.function, outer);
+        value?;
+
+        let rendered = emitted.render(&format!(
+            "define private {} {function}({})",
+            return_type.name,
+            signature.join(", ")
+        ));
+        writeln!(self.functions, "{rendered}").unwrap();
+        Ok(())
+    }
+
+    /// Emits checked `Int` arithmetic through LLVM's overflow intrinsics
+    fn checked(&mut self, intrinsic: &str, lh: &str, rh: &str, span: &Span) -> String {
+        let result = self.assign(
+            "t",
+            &format!("call {{ i64, i1 }} @llvm.{intrinsic}.with.overflow.i64(i64 {lh}, i64 {rh})"),
+        );
+        let overflowed = self.assign("t", &format!("extractvalue {{ i64, i1 }} {result}, 1"));
+        let overflow_label = self.fresh("overflow");
+        let ok_label = self.fresh("ok");
+        self.instr(&format!(
+            "br i1 {overflowed}, label %{overflow_label}, label %{ok_label}"
+        ));
+        self.label(&overflow_label);
+        let location = self.location(span);
+        self.instr(&format!(
+            "call void @alloy_panic(ptr @alloy.overflow, ptr {location})"
+        ));
+        self.instr("unreachable");
+        self.label(&ok_label);
+        self.assign("t", &format!("extractvalue {{ i64, i1 }} {result}, 0"))
+    }
+
+    /// Converts an `Int` operand of `Float` arithmetic
+    fn promote(&mut self, value: String, ty: &LlvmType) -> String {
+        if matches!(ty.layout, Layout::Int) {
+            self.assign("t", &format!("sitofp i64 {value} to float"))
+        } else {
+            value
+        }
+    }
+
+    fn instr(&mut self, text: &str) {
+        self.function.lines.push(format!("  {text}"));
+    }
+
+    /// Emits an instruction into a new SSA value
+    fn assign(&mut self, base: &str, instruction: &str) -> String {
+        let name = self.fresh_local(base);
+        self.instr(&format!("{name} = {instruction}"));
+        name
+    }
+
+    fn label(&mut self, label: &str) {
+        self.function.lines.push(format!("{label}:"));
+        self.function.block = label.to_string();
+    }
+
+    fn fresh(&mut self, base: &str) -> String {
+        self.next_id += 1;
+        format!("{base}.{}", self.next_id)
+    }
+
+    fn fresh_local(&mut self, base: &str) -> String {
+        format!("%{}", self.fresh(base))
+    }
+
+    /// A global holding a NUL terminated string, shared by every use of the same text
+    fn string(&mut self, text: &str) -> String {
+        if let Some((_, name)) = self.strings.iter().find(|(t, _)| t == text) {
+            return name.clone();
+        }
+        let name = format!("@str.{}", self.strings.len());
+        self.strings.push((text.to_string(), name.clone()));
+        name
+    }
+
+    fn location(&mut self, span: &Span) -> String {
+        let module = self.tree.module_at(span.0);
+        let (line, col) = module.location(span.0);
+        let path = module.file_path.display().to_string();
+        self.string(&format!("{path}:{line}:{col}"))
+    }
+
+    fn bind(&mut self, var: Option<VarId>, slot: Slot, ty: LlvmType) {
+        if let Some(var) = var {
+            self.function.vars.insert(var, (slot, ty));
+        }
+    }
+
+    /// Where a variable lives, which the checker only lets the function declaring it use
+    fn resolve_var(&self, var: Option<VarId>) -> Option<(Slot, LlvmType)> {
+        var.and_then(|var| self.function.vars.get(&var).cloned())
+    }
+
+    fn resolve_type(&self, (name, id): (&str, TypeId), span: &Span) -> Result<LlvmType, EmitError> {
+        self.declared_types
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| EmitError::unresolved_type(name, span))
+    }
+
+    fn type_of(&self, expr: &Expression) -> Result<LlvmType, EmitError> {
+        self.resolve_type(expr.ty(), &expr.span)
+    }
+
+    fn variant_tag(ty: &LlvmType, variant: &str, span: &Span) -> Result<usize, EmitError> {
+        match &ty.layout {
+            Layout::Enum { variants, .. } => variants
+                .iter()
+                .position(|(name, _)| name == variant)
+                .ok_or_else(|| EmitError::unresolved_type(&format!("{}.{variant}", ty.name), span)),
+            _ => Err(EmitError::unresolved_type(&ty.name, span)),
+        }
+    }
+}
+
+/// Floats are written as the hex of their `double` widening, which LLVM accepts for `float`
+/// constants that are exactly representable
+fn float_constant(value: f32) -> String {
+    format!("0x{:016X}", f64::from(value).to_bits())
+}
+
+fn string_constant(name: &str, text: &str) -> String {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.push(0);
+    let encoded = bytes
+        .iter()
+        .map(|&byte| {
+            if byte == b' ' || (byte.is_ascii_graphic() && byte != b'"' && byte != b'\\') {
+                (byte as char).to_string()
+            } else {
+                format!("\\{byte:02X}")
+            }
+        })
+        .collect::<String>();
+    format!(
+        "{name} = private unnamed_addr constant [{} x i8] c\"{encoded}\"\n",
+        bytes.len()
+    )
+}
@@ -0,0 +1,4 @@
+pub mod c;
+pub mod errors;
+pub mod llvm;
+pub mod wat;
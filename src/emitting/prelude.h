@@ -0,0 +1,136 @@
+#include <math.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef int64_t alloy_int;
+typedef float alloy_float;
+typedef bool alloy_bool;
+typedef uint8_t alloy_unit;
+
+static const char *const ALLOY_OVERFLOW = "attempt to compute a value that overflows `Int`";
+
+static void alloy_panic(const char *message, const char *location) {
+    fprintf(stderr, "error: %s\n --> %s\n", message, location);
+    exit(1);
+}
+
+static alloy_int alloy_add(alloy_int a, alloy_int b, const char *location) {
+    if ((b > 0 && a > INT64_MAX - b) || (b < 0 && a < INT64_MIN - b)) {
+        alloy_panic(ALLOY_OVERFLOW, location);
+    }
+    return a + b;
+}
+
+static alloy_int alloy_sub(alloy_int a, alloy_int b, const char *location) {
+    if ((b < 0 && a > INT64_MAX + b) || (b > 0 && a < INT64_MIN + b)) {
+        alloy_panic(ALLOY_OVERFLOW, location);
+    }
+    return a - b;
+}
+
+static alloy_int alloy_mul(alloy_int a, alloy_int b, const char *location) {
+    if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a)
+              : (b > 0 ? a < INT64_MIN / b : (a != 0 && b < INT64_MAX / a))) {
+        alloy_panic(ALLOY_OVERFLOW, location);
+    }
+    return a * b;
+}
+
+static alloy_int alloy_neg(alloy_int a, const char *location) {
+    if (a == INT64_MIN) {
+        alloy_panic(ALLOY_OVERFLOW, location);
+    }
+    return -a;
+}
+
+/* exponentiation by squaring, overflowing exactly when the evaluator does */
+static alloy_int alloy_pow(alloy_int base, alloy_int exponent, const char *location) {
+    alloy_int result = 1;
+    if (exponent < 0) {
+        alloy_panic("attempt to raise an `Int` to a negative power", location);
+    }
+    if (exponent > UINT32_MAX) {
+        alloy_panic(ALLOY_OVERFLOW, location);
+    }
+    if (exponent == 0) {
+        return 1;
+    }
+    for (;;) {
+        if (exponent & 1) {
+            result = alloy_mul(result, base, location);
+            if (exponent == 1) {
+                return result;
+            }
+        }
+        exponent /= 2;
+        base = alloy_mul(base, base, location);
+    }
+}
+
+static void alloy_print_unit(alloy_unit value) {
+    (void)value;
+    fputs("()", stdout);
+}
+
+static void alloy_print_int(alloy_int value) {
+    printf("%lld", (long long)value);
+}
+
+static void alloy_print_bool(alloy_bool value) {
+    fputs(value ? "true" : "false", stdout);
+}
+
+/* what Rust's `{:?}` prints: the shortest digits that read back as the same value, written out
+ * with a `.0` on whole numbers, or in exponent form below 1e-4 and from 1e16 */
+static void alloy_print_float(alloy_float value) {
+    static const char zeros[] = "0000000000000000";
+    if (isnan(value)) {
+        fputs("NaN", stdout);
+        return;
+    }
+    if (isinf(value)) {
+        fputs(value < 0 ? "-inf" : "inf", stdout);
+        return;
+    }
+    /* nine significant digits always read back, so `precision` stops at eight */
+    char buffer[32];
+    int precision = 0;
+    for (;;) {
+        /* printf rounds an exact tie to even where Rust rounds it away from zero */
+        double wide = value;
+        snprintf(buffer, sizeof buffer, "%.*e", precision + 1, wide);
+        if (strchr(buffer, 'e')[-1] == '5' && strtod(buffer, NULL) == wide) {
+            wide = nextafter(wide, value < 0 ? -INFINITY : INFINITY);
+        }
+        snprintf(buffer, sizeof buffer, "%.*e", precision, wide);
+        if (precision == 8 || strtof(buffer, NULL) == value) {
+            break;
+        }
+        precision++;
+    }
+    char *e = strchr(buffer, 'e');
+    int exponent = atoi(e + 1);
+    *e = '\0';
+    alloy_float magnitude = fabsf(value);
+    if (magnitude != 0 && (magnitude < 1e-4f || magnitude >= 1e16f)) {
+        printf("%se%d", buffer, exponent);
+        return;
+    }
+    char *digits = buffer;
+    if (*digits == '-') {
+        putchar('-');
+        digits++;
+    }
+    /* the digits after the first, past the decimal point */
+    const char *rest = precision > 0 ? digits + 2 : "";
+    if (exponent < 0) {
+        printf("0.%.*s%c%s", -exponent - 1, zeros, digits[0], rest);
+    } else if (exponent < precision) {
+        printf("%c%.*s.%s", digits[0], exponent, rest, rest + exponent);
+    } else {
+        printf("%c%s%.*s.0", digits[0], rest, exponent - precision, zeros);
+    }
+}
@@ -0,0 +1,213 @@
+; runtime support shared by every module, the generated code follows it
+
+declare i32 @printf(ptr, ...)
+declare i32 @dprintf(i32, ptr, ...)
+declare i32 @snprintf(ptr, i64, ptr, ...)
+declare float @strtof(ptr, ptr)
+declare double @strtod(ptr, ptr)
+declare ptr @strchr(ptr, i32)
+declare i32 @atoi(ptr)
+declare double @nextafter(double, double)
+declare void @exit(i32) noreturn
+declare float @llvm.pow.f32(float, float)
+declare float @llvm.fabs.f32(float)
+declare { i64, i1 } @llvm.sadd.with.overflow.i64(i64, i64)
+declare { i64, i1 } @llvm.ssub.with.overflow.i64(i64, i64)
+declare { i64, i1 } @llvm.smul.with.overflow.i64(i64, i64)
+
+@alloy.overflow = private unnamed_addr constant [48 x i8] c"attempt to compute a value that overflows `Int`\00"
+@alloy.negative_exponent = private unnamed_addr constant [46 x i8] c"attempt to raise an `Int` to a negative power\00"
+@alloy.panic_format = private unnamed_addr constant [19 x i8] c"error: %s\0A --> %s\0A\00"
+@alloy.string_format = private unnamed_addr constant [3 x i8] c"%s\00"
+@alloy.int_format = private unnamed_addr constant [5 x i8] c"%lld\00"
+@alloy.float_format = private unnamed_addr constant [5 x i8] c"%.*e\00"
+@alloy.exponent_format = private unnamed_addr constant [6 x i8] c"%se%d\00"
+@alloy.fraction_format = private unnamed_addr constant [11 x i8] c"0.%.*s%c%s\00"
+@alloy.point_format = private unnamed_addr constant [10 x i8] c"%c%.*s.%s\00"
+@alloy.whole_format = private unnamed_addr constant [11 x i8] c"%c%s%.*s.0\00"
+@alloy.zeros = private unnamed_addr constant [17 x i8] c"0000000000000000\00"
+@alloy.empty = private unnamed_addr constant [1 x i8] c"\00"
+@alloy.minus = private unnamed_addr constant [2 x i8] c"-\00"
+@alloy.nan = private unnamed_addr constant [4 x i8] c"NaN\00"
+@alloy.infinity = private unnamed_addr constant [4 x i8] c"inf\00"
+@alloy.negative_infinity = private unnamed_addr constant [5 x i8] c"-inf\00"
+@alloy.unit = private unnamed_addr constant [3 x i8] c"()\00"
+@alloy.true = private unnamed_addr constant [5 x i8] c"true\00"
+@alloy.false = private unnamed_addr constant [6 x i8] c"false\00"
+@alloy.newline = private unnamed_addr constant [2 x i8] c"\0A\00"
+
+define private void @alloy_panic(ptr %message, ptr %location) noreturn {
+entry:
+  call i32 (i32, ptr, ...) @dprintf(i32 2, ptr @alloy.panic_format, ptr %message, ptr %location)
+  call void @exit(i32 1)
+  unreachable
+}
+
+; exponentiation by squaring, overflowing exactly when the evaluator does
+define private i64 @alloy_pow(i64 %base, i64 %exponent, ptr %location) {
+entry:
+  %negative = icmp slt i64 %exponent, 0
+  br i1 %negative, label %negative_exponent, label %check_size
+negative_exponent:
+  call void @alloy_panic(ptr @alloy.negative_exponent, ptr %location)
+  unreachable
+check_size:
+  %too_big = icmp sgt i64 %exponent, 4294967295
+  br i1 %too_big, label %overflow, label %check_zero
+check_zero:
+  %zero = icmp eq i64 %exponent, 0
+  br i1 %zero, label %one, label %loop
+one:
+  ret i64 1
+loop:
+  %result = phi i64 [ 1, %check_zero ], [ %halved_result, %square ]
+  %power = phi i64 [ %base, %check_zero ], [ %squared, %square ]
+  %remaining = phi i64 [ %exponent, %check_zero ], [ %halved, %square ]
+  %bit = and i64 %remaining, 1
+  %odd = icmp ne i64 %bit, 0
+  br i1 %odd, label %multiply, label %halve
+multiply:
+  %product = call { i64, i1 } @llvm.smul.with.overflow.i64(i64 %result, i64 %power)
+  %product_overflow = extractvalue { i64, i1 } %product, 1
+  br i1 %product_overflow, label %overflow, label %multiplied
+multiplied:
+  %multiplied_result = extractvalue { i64, i1 } %product, 0
+  %last = icmp eq i64 %remaining, 1
+  br i1 %last, label %done, label %halve
+done:
+  ret i64 %multiplied_result
+halve:
+  %halved_result = phi i64 [ %result, %loop ], [ %multiplied_result, %multiplied ]
+  %halved = lshr i64 %remaining, 1
+  %square_result = call { i64, i1 } @llvm.smul.with.overflow.i64(i64 %power, i64 %power)
+  %square_overflow = extractvalue { i64, i1 } %square_result, 1
+  br i1 %square_overflow, label %overflow, label %square
+square:
+  %squared = extractvalue { i64, i1 } %square_result, 0
+  br label %loop
+overflow:
+  call void @alloy_panic(ptr @alloy.overflow, ptr %location)
+  unreachable
+}
+
+define private void @alloy_print_str(ptr %text) {
+entry:
+  call i32 (ptr, ...) @printf(ptr @alloy.string_format, ptr %text)
+  ret void
+}
+
+define private void @alloy_print_unit({} %value) {
+entry:
+  call void @alloy_print_str(ptr @alloy.unit)
+  ret void
+}
+
+define private void @alloy_print_int(i64 %value) {
+entry:
+  call i32 (ptr, ...) @printf(ptr @alloy.int_format, i64 %value)
+  ret void
+}
+
+define private void @alloy_print_bool(i1 %value) {
+entry:
+  %text = select i1 %value, ptr @alloy.true, ptr @alloy.false
+  call void @alloy_print_str(ptr %text)
+  ret void
+}
+
+; what Rust's `{:?}` prints: the shortest digits that read back as the same value, written out
+; with a `.0` on whole numbers, or in exponent form below 1e-4 and from 1e16
+define private void @alloy_print_float(float %value) {
+entry:
+  %buffer = alloca [32 x i8]
+  %nan = fcmp uno float %value, %value
+  br i1 %nan, label %print_nan, label %check_infinite
+print_nan:
+  call void @alloy_print_str(ptr @alloy.nan)
+  ret void
+check_infinite:
+  %magnitude = call float @llvm.fabs.f32(float %value)
+  %infinite = fcmp oeq float %magnitude, 0x7FF0000000000000
+  %negative = fcmp olt float %value, 0.0
+  br i1 %infinite, label %print_infinite, label %finite
+print_infinite:
+  %infinity = select i1 %negative, ptr @alloy.negative_infinity, ptr @alloy.infinity
+  call void @alloy_print_str(ptr %infinity)
+  ret void
+finite:
+  %wide = fpext float %value to double
+  %away = select i1 %negative, double 0xFFF0000000000000, double 0x7FF0000000000000
+  %nudged = call double @nextafter(double %wide, double %away)
+  br label %format
+; nine significant digits always read back, so the precision stops at eight
+format:
+  %precision = phi i32 [ 0, %finite ], [ %next, %retry ]
+  ; printf rounds an exact tie to even where Rust rounds it away from zero
+  %tie_precision = add i32 %precision, 1
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %buffer, i64 32, ptr @alloy.float_format, i32 %tie_precision, double %wide)
+  %tie_exponent = call ptr @strchr(ptr %buffer, i32 101)
+  %tie_digit_at = getelementptr i8, ptr %tie_exponent, i64 -1
+  %tie_digit = load i8, ptr %tie_digit_at
+  %five = icmp eq i8 %tie_digit, 53
+  %tie_value = call double @strtod(ptr %buffer, ptr null)
+  %exact = fcmp oeq double %tie_value, %wide
+  %tie = and i1 %five, %exact
+  %rounded = select i1 %tie, double %nudged, double %wide
+  call i32 (ptr, i64, ptr, ...) @snprintf(ptr %buffer, i64 32, ptr @alloy.float_format, i32 %precision, double %rounded)
+  %parsed = call float @strtof(ptr %buffer, ptr null)
+  %same = fcmp oeq float %parsed, %value
+  %exhausted = icmp eq i32 %precision, 8
+  %found = or i1 %same, %exhausted
+  br i1 %found, label %split, label %retry
+retry:
+  %next = add i32 %precision, 1
+  br label %format
+split:
+  %exponent_at = call ptr @strchr(ptr %buffer, i32 101)
+  %exponent_text = getelementptr i8, ptr %exponent_at, i64 1
+  %exponent = call i32 @atoi(ptr %exponent_text)
+  store i8 0, ptr %exponent_at
+  %nonzero = fcmp one float %magnitude, 0.0
+  %small = fcmp olt float %magnitude, 0x3F1A36E2E0000000
+  %large = fcmp oge float %magnitude, 0x4341C37940000000
+  %extreme = or i1 %small, %large
+  %scientific = and i1 %nonzero, %extreme
+  br i1 %scientific, label %print_scientific, label %sign
+print_scientific:
+  call i32 (ptr, ...) @printf(ptr @alloy.exponent_format, ptr %buffer, i32 %exponent)
+  ret void
+sign:
+  %first = load i8, ptr %buffer
+  %minus = icmp eq i8 %first, 45
+  br i1 %minus, label %print_minus, label %digits
+print_minus:
+  call void @alloy_print_str(ptr @alloy.minus)
+  br label %digits
+digits:
+  %skipped = zext i1 %minus to i64
+  %digits_at = getelementptr i8, ptr %buffer, i64 %skipped
+  %leading_digit = load i8, ptr %digits_at
+  %leading = zext i8 %leading_digit to i32
+  ; the digits after the first, past the decimal point
+  %after_point = getelementptr i8, ptr %digits_at, i64 2
+  %has_rest = icmp sgt i32 %precision, 0
+  %rest = select i1 %has_rest, ptr %after_point, ptr @alloy.empty
+  %below_one = icmp slt i32 %exponent, 0
+  br i1 %below_one, label %print_fraction, label %check_point
+print_fraction:
+  %leading_zeros = sub i32 -1, %exponent
+  call i32 (ptr, ...) @printf(ptr @alloy.fraction_format, i32 %leading_zeros, ptr @alloy.zeros, i32 %leading, ptr %rest)
+  ret void
+check_point:
+  %point_inside = icmp slt i32 %exponent, %precision
+  br i1 %point_inside, label %print_point, label %print_whole
+print_point:
+  %integer_digits = sext i32 %exponent to i64
+  %fraction = getelementptr i8, ptr %rest, i64 %integer_digits
+  call i32 (ptr, ...) @printf(ptr @alloy.point_format, i32 %leading, i32 %exponent, ptr %rest, ptr %fraction)
+  ret void
+print_whole:
+  %trailing_zeros = sub i32 %exponent, %precision
+  call i32 (ptr, ...) @printf(ptr @alloy.whole_format, i32 %leading, ptr %rest, i32 %trailing_zeros, ptr @alloy.zeros)
+  ret void
+}
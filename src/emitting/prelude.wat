@@ -0,0 +1,129 @@
+  ;; runtime support shared by every module, overflows trap like the evaluator's errors
+
+  (func $alloy_add (param $a i64) (param $b i64) (result i64)
+    (local $result i64)
+    local.get $a
+    local.get $b
+    i64.add
+    local.set $result
+    ;; overflowed when both operands have a different sign to the result
+    local.get $a
+    local.get $result
+    i64.xor
+    local.get $b
+    local.get $result
+    i64.xor
+    i64.and
+    i64.const 0
+    i64.lt_s
+    if
+      unreachable
+    end
+    local.get $result)
+
+  (func $alloy_sub (param $a i64) (param $b i64) (result i64)
+    (local $result i64)
+    local.get $a
+    local.get $b
+    i64.sub
+    local.set $result
+    ;; overflowed when the operands' signs differ and the result's sign isn't the left one's
+    local.get $a
+    local.get $b
+    i64.xor
+    local.get $a
+    local.get $result
+    i64.xor
+    i64.and
+    i64.const 0
+    i64.lt_s
+    if
+      unreachable
+    end
+    local.get $result)
+
+  (func $alloy_mul (param $a i64) (param $b i64) (result i64)
+    (local $result i64)
+    local.get $a
+    local.get $b
+    i64.mul
+    local.set $result
+    ;; dividing back recovers the right operand unless it overflowed, `i64.div_s` traps for
+    ;; the one case it can't tell apart
+    local.get $a
+    i64.const 0
+    i64.ne
+    if
+      local.get $result
+      local.get $a
+      i64.div_s
+      local.get $b
+      i64.ne
+      if
+        unreachable
+      end
+    end
+    local.get $result)
+
+  (func $alloy_neg (param $a i64) (result i64)
+    local.get $a
+    i64.const 0x8000000000000000
+    i64.eq
+    if
+      unreachable
+    end
+    i64.const 0
+    local.get $a
+    i64.sub)
+
+  ;; exponentiation by squaring, overflowing exactly when the evaluator does
+  (func $alloy_pow (param $base i64) (param $exponent i64) (result i64)
+    (local $result i64)
+    local.get $exponent
+    i64.const 0
+    i64.lt_s
+    local.get $exponent
+    i64.const 0xFFFFFFFF
+    i64.gt_s
+    i32.or
+    if
+      unreachable
+    end
+    i64.const 1
+    local.set $result
+    local.get $exponent
+    i64.eqz
+    if
+      i64.const 1
+      return
+    end
+    loop $square
+      local.get $exponent
+      i64.const 1
+      i64.and
+      i64.const 0
+      i64.ne
+      if
+        local.get $result
+        local.get $base
+        call $alloy_mul
+        local.set $result
+        local.get $exponent
+        i64.const 1
+        i64.eq
+        if
+          local.get $result
+          return
+        end
+      end
+      local.get $exponent
+      i64.const 1
+      i64.shr_u
+      local.set $exponent
+      local.get $base
+      local.get $base
+      call $alloy_mul
+      local.set $base
+      br $square
+    end
+    unreachable)
@@ -0,0 +1,1009 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{
+    checking::scope::{BOOL_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, NEVER_TYPE_ID, UNIT_TYPE_ID},
+    common::{
+        ast::Ast,
+        expression::{
+            Expression, ExpressionKind, FieldInit, FunctionId, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeId, TypeIdent, TypeValue, VarId,
+        },
+        span::Span,
+    },
+    emitting::errors::EmitError,
+};
+
+pub const PRELUDE: &str = include_str!("prelude.wat");
+
+#[derive(Clone)]
+enum Layout {
+    Unit,
+    Int,
+    Float,
+    Bool,
+    Struct {
+        owner: String,
+        fields: Vec<(String, WasmType)>,
+    },
+    // the tag comes first, followed by the payloads of every variant one after the other
+    Enum {
+        owner: String,
+        variants: Vec<(String, Vec<WasmType>)>,
+    },
+}
+
+/// An alloy type as the wasm values it is made of, compound types are flattened so that they
+/// travel through locals, parameters and results as several values
+#[derive(Clone)]
+struct WasmType {
+    values: Vec<&'static str>,
+    layout: Layout,
+}
+
+impl WasmType {
+    fn builtin(values: &[&'static str], layout: Layout) -> Self {
+        Self {
+            values: values.to_vec(),
+            layout,
+        }
+    }
+
+    fn flatten<'t>(types: impl IntoIterator<Item = &'t Self>) -> Vec<&'static str> {
+        types
+            .into_iter()
+            .flat_map(|ty| ty.values.iter().copied())
+            .collect()
+    }
+
+    /// The values of a field, or of a variant's payload, within the flattened values
+    fn slice<'l>(locals: &'l [String], types: &[Self], index: usize) -> &'l [String] {
+        let start = types[..index]
+            .iter()
+            .map(|ty| ty.values.len())
+            .sum::<usize>();
+        &locals[start..start + types[index].values.len()]
+    }
+
+    fn variant_slice<'l>(
+        locals: &'l [String],
+        variants: &[(String, Vec<Self>)],
+        tag: usize,
+    ) -> &'l [String] {
+        let start = 1 + variants[..tag]
+            .iter()
+            .map(|(_, payload)| Self::flatten(payload).len())
+            .sum::<usize>();
+        &locals[start..start + Self::flatten(&variants[tag].1).len()]
+    }
+
+    fn layout_name(&self) -> String {
+        match &self.layout {
+            Layout::Unit => "Unit".to_string(),
+            Layout::Int => "Int".to_string(),
+            Layout::Float => "Float".to_string(),
+            Layout::Bool => "Bool".to_string(),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => owner.clone(),
+        }
+    }
+
+    /// The instruction comparing two values of this type on the stack, `None` when they're
+    /// always equal
+    fn compare(&self) -> Option<String> {
+        match &self.layout {
+            Layout::Unit => None,
+            Layout::Int => Some("i64.eq".to_string()),
+            Layout::Float => Some("f32.eq".to_string()),
+            Layout::Bool => Some("i32.eq".to_string()),
+            Layout::Struct { owner, .. } | Layout::Enum { owner, .. } => {
+                Some(format!("call $alloy_eq_{owner}"))
+            }
+        }
+    }
+
+    /// Instructions leaving whether two values held in locals are equal on the stack
+    fn equality(&self, lh: &[String], rh: &[String]) -> Vec<String> {
+        let Some(compare) = self.compare() else {
+            return vec!["i32.const 1".to_string()];
+        };
+        let mut code = lh
+            .iter()
+            .chain(rh)
+            .map(|local| format!("local.get {local}"))
+            .collect::<Vec<_>>();
+        code.push(compare);
+        code
+    }
+}
+
+fn results(values: &[&str]) -> String {
+    if values.is_empty() {
+        String::new()
+    } else {
+        format!(" (result {})", values.join(" "))
+    }
+}
+
+fn zero(value: &str) -> String {
+    format!("{value}.const 0")
+}
+
+/// The function being emitted
+struct Function {
+    locals: Vec<(String, &'static str)>,
+    lines: Vec<String>,
+    indent: usize,
+    // the labels `continue` and `break` jump to for each enclosing loop
+    loops: Vec<(String, String)>,
+    // the locals holding each variable declared in the function
+    vars: HashMap<VarId, Vec<String>>,
+}
+
+impl Function {
+    fn new() -> Self {
+        Self {
+            locals: vec![],
+            lines: vec![],
+            indent: 2,
+            loops: vec![],
+            vars: HashMap::new(),
+        }
+    }
+
+    fn render(&self, signature: &str) -> String {
+        let mut output = format!("  ({signature}");
+        for (name, value) in &self.locals {
+            write!(output, "\n    (local {name} {value})").unwrap();
+        }
+        for line in &self.lines {
+            write!(output, "\n{line}").unwrap();
+        }
+        output.push_str(")\n");
+        output
+    }
+}
+
+/// Translates a checked `Ast` into a WebAssembly text module that exports the top level
+/// expressions as a `main` function returning the value of the last one
+///
+/// Runtime errors trap, and `Float` powers import `pow` from the `alloy` module of the host
+pub struct WatEmitter {
+    helpers: Vec<String>,
+    functions: Vec<String>,
+    uses_float_pow: bool,
+    declared_functions: HashMap<FunctionId, String>,
+    declared_types: HashMap<TypeId, WasmType>,
+    function: Function,
+    next_id: usize,
+}
+
+impl WatEmitter {
+    pub fn new() -> Self {
+        let declared_types = HashMap::from([
+            (UNIT_TYPE_ID, WasmType::builtin(&[], Layout::Unit)),
+            (INT_TYPE_ID, WasmType::builtin(&["i64"], Layout::Int)),
+            (FLOAT_TYPE_ID, WasmType::builtin(&["f32"], Layout::Float)),
+            (BOOL_TYPE_ID, WasmType::builtin(&["i32"], Layout::Bool)),
+            (NEVER_TYPE_ID, WasmType::builtin(&[], Layout::Unit)),
+        ]);
+        Self {
+            helpers: vec![],
+            functions: vec![],
+            uses_float_pow: false,
+            declared_functions: HashMap::new(),
+            declared_types,
+            function: Function::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn emit(mut self, ast: &Ast) -> Result<String, EmitError> {
+        self.emit_sequence(&ast.0)?;
+        let result = match ast.0.last() {
+            Some(last) => self.type_of(last)?.values,
+            None => vec![],
+        };
+        let main = self
+            .function
+            .render(&format!("func $main (export \"main\"){}", results(&result)));
+
+        let mut items = vec![];
+        if self.uses_float_pow {
+            items.push(
+                "  (import \"alloy\" \"pow\" (func $alloy_powf (param f32 f32) (result f32)))\n"
+                    .to_string(),
+            );
+        }
+        items.push(PRELUDE.to_string());
+        items.extend(self.helpers);
+        items.extend(self.functions);
+        items.push(main);
+        let output = format!("(module\n{})\n", items.join("\n"));
+        Ok(output)
+    }
+
+    /// Emits the instructions leaving an expression's values on the stack
+    fn emit_expression(&mut self, expr: &Expression) -> Result<(), EmitError> {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => self.instr(&format!("i64.const {value}")),
+            ExpressionKind::LiteralFloat(value) => self.instr(&format!("f32.const {value:?}")),
+            ExpressionKind::LiteralBool(value) => {
+                self.instr(&format!("i32.const {}", i32::from(*value)));
+            }
+            ExpressionKind::Ident { name, var } => {
+                let locals = self
+                    .resolve_var(*var)
+                    .ok_or_else(|| EmitError::unresolved_variable(name, span))?;
+                self.get(&locals);
+            }
+            ExpressionKind::Prefix { op, rh } => self.emit_prefix(*op, rh)?,
+            ExpressionKind::Infix { op, lh, rh } => match op {
+                InfixOp::And | InfixOp::Or => self.emit_logical(*op, lh, rh)?,
+                _ => self.emit_infix(*op, lh, rh)?,
+            },
+            ExpressionKind::Block(exprs) => {
+                let label = self.fresh("block");
+                let values = self.type_of(expr)?.values;
+                self.open(&format!("block {label}{}", results(&values)));
+                self.emit_sequence(exprs)?;
+                self.close();
+            }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => self.emit_if(expr, condition, then, otherwise.as_deref())?,
+            ExpressionKind::While { condition, body } => self.emit_while(condition, body)?,
+            ExpressionKind::Loop(body) => self.emit_loop(expr, body)?,
+            ExpressionKind::Break(value) => self.emit_break(value.as_deref(), span)?,
+            ExpressionKind::Continue => {
+                let (next, _) =
+                    self.function.loops.last().cloned().ok_or_else(|| {
+                        EmitError::unsupported("`continue` outside of a loop", span)
+                    })?;
+                self.instr(&format!("br {next}"));
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                ty,
+                var,
+                ..
+            } => self.emit_variable_decl(name, value, ty.as_ref(), *var, span)?,
+            ExpressionKind::VariableAssignment { name, value, var } => {
+                self.emit_expression(value)?;
+                let locals = self
+                    .resolve_var(*var)
+                    .ok_or_else(|| EmitError::unresolved_variable(name, span))?;
+                self.set(&locals);
+            }
+            ExpressionKind::TypeDecl {
+                name, value, ty, ..
+            } => self.emit_type_decl(name, value, *ty, span)?,
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                return_ty,
+                body,
+                function,
+                ..
+            } => self.emit_function(name, params, return_ty.as_ref(), body, *function, span)?,
+            ExpressionKind::FunctionCall {
+                name,
+                args,
+                function,
+            } => {
+                self.emit_call(name, *function, &args.iter().collect::<Vec<_>>(), span)?;
+            }
+            ExpressionKind::MethodCall {
+                caller,
+                name,
+                args,
+                function,
+            } => {
+                let args = std::iter::once(caller.as_ref())
+                    .chain(args.iter())
+                    .collect::<Vec<_>>();
+                self.emit_call(name, *function, &args, span)?;
+            }
+            ExpressionKind::StructLiteral { fields, .. } => {
+                self.emit_struct_literal(expr, fields, span)?;
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                self.emit_field_access(target, field, span)?;
+            }
+            ExpressionKind::EnumVariant { variant, args, .. } => {
+                self.emit_enum_variant(expr, variant, args, span)?;
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => self.emit_match(expr, scrutinee, arms)?,
+        }
+        Ok(())
+    }
+
+    /// Emits an expression whose values aren't used
+    fn emit_prefix(&mut self, op: PrefixOp, rh: &Expression) -> Result<(), EmitError> {
+        self.emit_expression(rh)?;
+        match (op, self.type_of(rh)?.layout) {
+            (PrefixOp::Not, Layout::Int) => self.instr("i64.eqz"),
+            (PrefixOp::Not, Layout::Float) => {
+                self.instr("f32.const 0");
+                self.instr("f32.eq");
+            }
+            (PrefixOp::Not, _) => self.instr("i32.eqz"),
+            (PrefixOp::Negate, Layout::Int) => self.instr("call $alloy_neg"),
+            (PrefixOp::Negate, _) => self.instr("f32.neg"),
+            (PrefixOp::BitNot, _) => {
+                self.instr("i64.const -1");
+                self.instr("i64.xor");
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_logical(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(lh)?;
+        self.open("if (result i32)");
+        if matches!(op, InfixOp::And) {
+            self.emit_expression(rh)?;
+            self.reopen("else");
+            self.instr("i32.const 0");
+        } else {
+            self.instr("i32.const 1");
+            self.reopen("else");
+            self.emit_expression(rh)?;
+        }
+        self.close();
+        Ok(())
+    }
+
+    fn emit_if(
+        &mut self,
+        expr: &Expression,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: Option<&Expression>,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(condition)?;
+        let values = self.type_of(expr)?.values;
+        self.open(&format!("if{}", results(&values)));
+        match otherwise {
+            Some(otherwise) => {
+                self.emit_expression(then)?;
+                self.reopen("else");
+                self.emit_expression(otherwise)?;
+            }
+            None => self.emit_statement(then)?,
+        }
+        self.close();
+        Ok(())
+    }
+
+    fn emit_while(&mut self, condition: &Expression, body: &Expression) -> Result<(), EmitError> {
+        let exit = self.fresh("exit");
+        let next = self.fresh("next");
+        self.open(&format!("block {exit}"));
+        self.open(&format!("loop {next}"));
+        self.emit_expression(condition)?;
+        self.instr("i32.eqz");
+        self.instr(&format!("br_if {exit}"));
+        self.function.loops.push((next.clone(), exit));
+        let result = self.emit_statement(body);
+        self.function.loops.pop();
+        result?;
+        self.instr(&format!("br {next}"));
+        self.close();
+        self.close();
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, expr: &Expression, body: &Expression) -> Result<(), EmitError> {
+        let exit = self.fresh("exit");
+        let next = self.fresh("next");
+        let values = self.type_of(expr)?.values;
+        self.open(&format!("block {exit}{}", results(&values)));
+        self.open(&format!("loop {next}"));
+        self.function.loops.push((next.clone(), exit));
+        let result = self.emit_statement(body);
+        self.function.loops.pop();
+        result?;
+        self.instr(&format!("br {next}"));
+        self.close();
+        // only a `break` leaves the loop
+        self.instr("unreachable");
+        self.close();
+        Ok(())
+    }
+
+    fn emit_break(&mut self, value: Option<&Expression>, span: &Span) -> Result<(), EmitError> {
+        if let Some(value) = value {
+            self.emit_expression(value)?;
+        }
+        let (_, exit) = self
+            .function
+            .loops
+            .last()
+            .cloned()
+            .ok_or_else(|| EmitError::unsupported("`break` outside of a loop", span))?;
+        self.instr(&format!("br {exit}"));
+        Ok(())
+    }
+
+    fn emit_variable_decl(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        ty: Option<&TypeIdent>,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        self.emit_expression(value)?;
+        let ty = match ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.type_of(value)?,
+        };
+        let locals = self.declare_locals(name, &ty.values);
+        self.set(&locals);
+        self.bind(var, locals);
+        Ok(())
+    }
+
+    fn emit_struct_literal(
+        &mut self,
+        expr: &Expression,
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let ty = self.type_of(expr)?;
+        let Layout::Struct {
+            fields: declared, ..
+        } = &ty.layout
+        else {
+            return Err(EmitError::unresolved_type(expr.ty().0, span));
+        };
+        let in_order = fields.len() == declared.len()
+            && fields
+                .iter()
+                .zip(declared)
+                .all(|(field, (name, _))| &field.name == name);
+        if in_order {
+            for field in fields {
+                self.emit_expression(&field.value)?;
+            }
+        } else {
+            // fields are evaluated as written but laid out as declared
+            let mut values = vec![];
+            for field in fields {
+                self.emit_expression(&field.value)?;
+                let field_type = self.type_of(&field.value)?;
+                let temps = self.declare_locals("t", &field_type.values);
+                self.set(&temps);
+                values.push((field.name.as_str(), temps));
+            }
+            for (field, _) in declared {
+                let (_, temps) =
+                    values
+                        .iter()
+                        .find(|(name, _)| name == field)
+                        .ok_or_else(|| {
+                            EmitError::unsupported(&format!("missing field `{field}`"), span)
+                        })?;
+                self.get(temps);
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_field_access(
+        &mut self,
+        target: &Expression,
+        field: &str,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let ty = self.type_of(target)?;
+        let Layout::Struct { fields, .. } = &ty.layout else {
+            return Err(EmitError::unresolved_type(&ty.layout_name(), span));
+        };
+        let index = fields
+            .iter()
+            .position(|(name, _)| name == field)
+            .ok_or_else(|| EmitError::unresolved_variable(field, span))?;
+        let field_types = fields.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+        // a variable's own locals can be read directly
+        let locals = match &target.kind {
+            ExpressionKind::Ident { var, .. } => self.resolve_var(*var),
+            _ => None,
+        };
+        let locals = match locals {
+            Some(locals) => locals,
+            None => self.spill(target, &ty)?,
+        };
+        self.get(WasmType::slice(&locals, &field_types, index));
+        Ok(())
+    }
+
+    fn emit_enum_variant(
+        &mut self,
+        expr: &Expression,
+        variant: &str,
+        args: &[Expression],
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let ty = self.type_of(expr)?;
+        let Layout::Enum { variants, .. } = &ty.layout else {
+            return Err(EmitError::unresolved_type(variant, span));
+        };
+        let tag = variants
+            .iter()
+            .position(|(name, _)| name == variant)
+            .ok_or_else(|| EmitError::unresolved_type(variant, span))?;
+        self.instr(&format!("i32.const {tag}"));
+        for (index, (_, payload)) in variants.iter().enumerate() {
+            if index == tag {
+                for arg in args {
+                    self.emit_expression(arg)?;
+                }
+            } else {
+                for value in WasmType::flatten(payload) {
+                    self.instr(&zero(value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Each arm is a block its pattern breaks out of when it doesn't match
+    fn emit_match(
+        &mut self,
+        expr: &Expression,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+    ) -> Result<(), EmitError> {
+        let scrutinee_type = self.type_of(scrutinee)?;
+        let locals = self.spill(scrutinee, &scrutinee_type)?;
+        let end = self.fresh("match");
+        let values = self.type_of(expr)?.values;
+        self.open(&format!("block {end}{}", results(&values)));
+        for arm in arms {
+            let next = self.fresh("arm");
+            self.open(&format!("block {next}"));
+            self.emit_pattern(&arm.pattern, &locals, &scrutinee_type, &next)?;
+            self.emit_expression(&arm.body)?;
+            self.instr(&format!("br {end}"));
+            self.close();
+        }
+        // no arm applies, which the checker rules out
+        self.instr("unreachable");
+        self.close();
+        Ok(())
+    }
+
+    fn emit_statement(&mut self, expr: &Expression) -> Result<(), EmitError> {
+        self.emit_expression(expr)?;
+        for _ in 0..self.type_of(expr)?.values.len() {
+            self.instr("drop");
+        }
+        Ok(())
+    }
+
+    fn emit_sequence(&mut self, exprs: &[Expression]) -> Result<(), EmitError> {
+        if let Some((last, rest)) = exprs.split_last() {
+            for expr in rest {
+                self.emit_statement(expr)?;
+            }
+            self.emit_expression(last)?;
+        }
+        Ok(())
+    }
+
+    fn emit_infix(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+    ) -> Result<(), EmitError> {
+        let lh_type = self.type_of(lh)?;
+        let rh_type = self.type_of(rh)?;
+
+        if matches!(op, InfixOp::Equals | InfixOp::NotEquals) {
+            self.emit_expression(lh)?;
+            self.emit_expression(rh)?;
+            match lh_type.compare() {
+                Some(compare) => self.instr(&compare),
+                None => self.instr("i32.const 1"),
+            }
+            if matches!(op, InfixOp::NotEquals) {
+                self.instr("i32.eqz");
+            }
+            return Ok(());
+        }
+
+        let ints = matches!(
+            (&lh_type.layout, &rh_type.layout),
+            (Layout::Int, Layout::Int)
+        );
+        if ints && !matches!(op, InfixOp::Divide) {
+            self.emit_expression(lh)?;
+            self.emit_expression(rh)?;
+            self.instr(match op {
+                InfixOp::Add => "call $alloy_add",
+                InfixOp::Subtract => "call $alloy_sub",
+                InfixOp::Multiply => "call $alloy_mul",
+                InfixOp::Power => "call $alloy_pow",
+                InfixOp::LessThan => "i64.lt_s",
+                InfixOp::LessThanOrEqual => "i64.le_s",
+                InfixOp::GreaterThan => "i64.gt_s",
+                InfixOp::GreaterThanOrEqual => "i64.ge_s",
+                _ => unreachable!("handled above"),
+            });
+            return Ok(());
+        }
+
+        // mixing an `Int` with a `Float`, or dividing, computes in `Float`
+        self.emit_expression(lh)?;
+        self.promote(&lh_type);
+        self.emit_expression(rh)?;
+        self.promote(&rh_type);
+        let instruction = match op {
+            InfixOp::Add => "f32.add",
+            InfixOp::Subtract => "f32.sub",
+            InfixOp::Multiply => "f32.mul",
+            InfixOp::Divide => "f32.div",
+            InfixOp::Power => {
+                self.uses_float_pow = true;
+                "call $alloy_powf"
+            }
+            InfixOp::LessThan => "f32.lt",
+            InfixOp::LessThanOrEqual => "f32.le",
+            InfixOp::GreaterThan => "f32.gt",
+            InfixOp::GreaterThanOrEqual => "f32.ge",
+            _ => unreachable!("handled above"),
+        };
+        self.instr(instruction);
+        Ok(())
+    }
+
+    fn emit_call(
+        &mut self,
+        name: &str,
+        function: Option<FunctionId>,
+        args: &[&Expression],
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let function =
+            function.and_then(|function| self.declared_functions.get(&function).cloned());
+        for arg in args {
+            self.emit_expression(arg)?;
+        }
+        match function {
+            Some(function) => self.instr(&format!("call {function}")),
+            // `to_unit` is a builtin that discards its arguments
+            None if name == "to_unit" => {
+                for arg in args {
+                    for _ in 0..self.type_of(arg)?.values.len() {
+                        self.instr("drop");
+                    }
+                }
+            }
+            None => return Err(EmitError::unresolved_function(name, span)),
+        }
+        Ok(())
+    }
+
+    /// Emits the tests of a pattern against values held in locals, leaving the block `fail`
+    /// as soon as one of them doesn't hold
+    fn emit_pattern(
+        &mut self,
+        pattern: &Pattern,
+        locals: &[String],
+        ty: &WasmType,
+        fail: &str,
+    ) -> Result<(), EmitError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding { var, .. } => {
+                // the locals are never assigned again, so the binding can share them
+                self.bind(*var, locals.to_vec());
+            }
+            PatternKind::LiteralInt(value) => {
+                self.get(locals);
+                self.instr(&format!("i64.const {value}"));
+                self.instr("i64.ne");
+                self.instr(&format!("br_if {fail}"));
+            }
+            PatternKind::LiteralBool(value) => {
+                self.get(locals);
+                if *value {
+                    self.instr("i32.eqz");
+                }
+                self.instr(&format!("br_if {fail}"));
+            }
+            PatternKind::Variant { name, args, .. } => {
+                let Layout::Enum { variants, .. } = &ty.layout else {
+                    return Err(EmitError::unresolved_type(name, &pattern.span));
+                };
+                let tag = variants
+                    .iter()
+                    .position(|(variant, _)| variant == name)
+                    .ok_or_else(|| EmitError::unresolved_type(name, &pattern.span))?;
+                self.instr(&format!("local.get {}", locals[0]));
+                self.instr(&format!("i32.const {tag}"));
+                self.instr("i32.ne");
+                self.instr(&format!("br_if {fail}"));
+                let payload = WasmType::variant_slice(locals, variants, tag);
+                let payload_types = &variants[tag].1;
+                for (index, (arg, arg_type)) in args.iter().zip(payload_types).enumerate() {
+                    let arg_locals = WasmType::slice(payload, payload_types, index);
+                    self.emit_pattern(arg, arg_locals, arg_type, fail)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_type_decl(
+        &mut self,
+        name: &str,
+        value: &TypeValue,
+        declared: Option<TypeId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let owner = self.fresh(name).trim_start_matches('$').to_string();
+        let ty = match value {
+            TypeValue::Alias(parent) => self.resolve_type(parent.resolved(), span)?,
+            TypeValue::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        Ok((
+                            field.name.clone(),
+                            self.resolve_type(field.ty.resolved(), span)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let ty = WasmType {
+                    values: WasmType::flatten(fields.iter().map(|(_, ty)| ty)),
+                    layout: Layout::Struct { owner, fields },
+                };
+                self.emit_eq_helper(&ty);
+                ty
+            }
+            TypeValue::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let payload = variant
+                            .payload
+                            .iter()
+                            .map(|ty| self.resolve_type(ty.resolved(), span))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok((variant.name.clone(), payload))
+                    })
+                    .collect::<Result<Vec<_>, EmitError>>()?;
+                let mut values = vec!["i32"];
+                values.extend(WasmType::flatten(
+                    variants.iter().flat_map(|(_, payload)| payload),
+                ));
+                let ty = WasmType {
+                    values,
+                    layout: Layout::Enum { owner, variants },
+                };
+                self.emit_eq_helper(&ty);
+                ty
+            }
+        };
+        if let Some(declared) = declared {
+            self.declared_types.insert(declared, ty);
+        }
+        Ok(())
+    }
+
+    fn emit_eq_helper(&mut self, ty: &WasmType) {
+        let lh = (0..ty.values.len())
+            .map(|index| format!("$a.{index}"))
+            .collect::<Vec<_>>();
+        let rh = (0..ty.values.len())
+            .map(|index| format!("$b.{index}"))
+            .collect::<Vec<_>>();
+        let mut body = Function::new();
+        match &ty.layout {
+            Layout::Struct { fields, .. } => {
+                let field_types = fields.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+                body.lines.push("    i32.const 1".to_string());
+                for (index, field_type) in field_types.iter().enumerate() {
+                    let equality = field_type.equality(
+                        WasmType::slice(&lh, &field_types, index),
+                        WasmType::slice(&rh, &field_types, index),
+                    );
+                    body.lines
+                        .extend(equality.iter().map(|line| format!("    {line}")));
+                    body.lines.push("    i32.and".to_string());
+                }
+            }
+            Layout::Enum { variants, .. } => {
+                body.lines.extend(
+                    [
+                        "local.get $a.0",
+                        "local.get $b.0",
+                        "i32.ne",
+                        "if",
+                        "  i32.const 0",
+                        "  return",
+                        "end",
+                    ]
+                    .map(|line| format!("    {line}")),
+                );
+                for (tag, (_, payload)) in variants.iter().enumerate() {
+                    if payload.is_empty() {
+                        continue;
+                    }
+                    body.lines.extend([
+                        "    local.get $a.0".to_string(),
+                        format!("    i32.const {tag}"),
+                        "    i32.eq".to_string(),
+                        "    if".to_string(),
+                        "      i32.const 1".to_string(),
+                    ]);
+                    let lh_payload = WasmType::variant_slice(&lh, variants, tag);
+                    let rh_payload = WasmType::variant_slice(&rh, variants, tag);
+                    for (index, arg_type) in payload.iter().enumerate() {
+                        let equality = arg_type.equality(
+                            WasmType::slice(lh_payload, payload, index),
+                            WasmType::slice(rh_payload, payload, index),
+                        );
+                        body.lines
+                            .extend(equality.iter().map(|line| format!("      {line}")));
+                        body.lines.push("      i32.and".to_string());
+                    }
+                    body.lines
+                        .extend(["      return".to_string(), "    end".to_string()]);
+                }
+                body.lines.push("    i32.const 1".to_string());
+            }
+            _ => return,
+        }
+        let mut params = String::new();
+        for (name, value) in lh.iter().chain(&rh).zip(ty.values.iter().chain(&ty.values)) {
+            write!(params, " (param {name} {value})").unwrap();
+        }
+        let signature = format!("func $alloy_eq_{}{params} (result i32)", ty.layout_name());
+        self.helpers.push(body.render(&signature));
+    }
+
+    fn emit_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        return_ty: Option<&TypeIdent>,
+        body: &Expression,
+        declared: Option<FunctionId>,
+        span: &Span,
+    ) -> Result<(), EmitError> {
+        let function = self.fresh(name);
+        let return_type = match return_ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.resolve_type(("Unit", UNIT_TYPE_ID), span)?,
+        };
+        let param_types = params
+            .iter()
+            .map(|param| self.resolve_type(param.ty.resolved(), span))
+            .collect::<Result<Vec<_>, _>>()?;
+        // declared before the body is emitted so that functions can recurse
+        if let Some(declared) = declared {
+            self.declared_functions.insert(declared, function.clone());
+        }
+
+        let outer = std::mem::replace(&mut self.function, Function::new());
+        let mut signature = format!("func {function}");
+        for (param, ty) in params.iter().zip(param_types) {
+            let mut locals = vec![];
+            for value in &ty.values {
+                let local = self.fresh(&param.name);
+                write!(signature, " (param {local} {value})").unwrap();
+                locals.push(local);
+            }
+            self.bind(param.var, locals);
+        }
+        signature.push_str(&results(&return_type.values));
+        let result = self.emit_expression(body);
+        let emitted = std::mem::replace(&mut self.function, outer);
+        result?;
+
+        self.functions.push(emitted.render(&signature));
+        Ok(())
+    }
+
+    /// Evaluates an expression into new locals
+    fn spill(&mut self, expr: &Expression, ty: &WasmType) -> Result<Vec<String>, EmitError> {
+        self.emit_expression(expr)?;
+        let temps = self.declare_locals("t", &ty.values);
+        self.set(&temps);
+        Ok(temps)
+    }
+
+    /// Converts an `Int` operand of `Float` arithmetic
+    fn promote(&mut self, ty: &WasmType) {
+        if matches!(ty.layout, Layout::Int) {
+            self.instr("f32.convert_i64_s");
+        }
+    }
+
+    fn declare_locals(&mut self, name: &str, values: &[&'static str]) -> Vec<String> {
+        values
+            .iter()
+            .map(|value| {
+                let local = self.fresh(name);
+                self.function.locals.push((local.clone(), value));
+                local
+            })
+            .collect()
+    }
+
+    fn get(&mut self, locals: &[String]) {
+        for local in locals {
+            self.instr(&format!("local.get {local}"));
+        }
+    }
+
+    /// Pops values into locals, the last value is on top of the stack
+    fn set(&mut self, locals: &[String]) {
+        for local in locals.iter().rev() {
+            self.instr(&format!("local.set {local}"));
+        }
+    }
+
+    fn instr(&mut self, text: &str) {
+        let indent = "  ".repeat(self.function.indent);
+        self.function.lines.push(format!("{indent}{text}"));
+    }
+
+    fn open(&mut self, text: &str) {
+        self.instr(text);
+        self.function.indent += 1;
+    }
+
+    fn reopen(&mut self, text: &str) {
+        self.function.indent -= 1;
+        self.open(text);
+    }
+
+    fn close(&mut self) {
+        self.function.indent -= 1;
+        self.instr("end");
+    }
+
+    fn fresh(&mut self, base: &str) -> String {
+        self.next_id += 1;
+        format!("${base}.{}", self.next_id)
+    }
+
+    fn bind(&mut self, var: Option<VarId>, locals: Vec<String>) {
+        if let Some(var) = var {
+            self.function.vars.insert(var, locals);
+        }
+    }
+
+    /// The locals of a variable, which the checker only lets the function declaring it use
+    fn resolve_var(&self, var: Option<VarId>) -> Option<Vec<String>> {
+        var.and_then(|var| self.function.vars.get(&var).cloned())
+    }
+
+    fn resolve_type(&self, (name, id): (&str, TypeId), span: &Span) -> Result<WasmType, EmitError> {
+        self.declared_types
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| EmitError::unresolved_type(name, span))
+    }
+
+    fn type_of(&self, expr: &Expression) -> Result<WasmType, EmitError> {
+        self.resolve_type(expr.ty(), &expr.span)
+    }
+}
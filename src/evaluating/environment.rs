@@ -0,0 +1,152 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{checking::scope::ScopeKind, common::expression::Expression, evaluating::value::Value};
+
+pub type FrameRef = Rc<RefCell<Frame>>;
+
+/// A declared function, its body resolves functions and types from the frame it was declared in
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Expression,
+    pub frame: FrameRef,
+}
+
+#[derive(Clone)]
+pub enum RuntimeType {
+    Alias(String),
+    Struct(Vec<String>),
+    Enum,
+}
+
+/// The runtime counterpart of a checker scope
+pub struct Frame {
+    parent: Option<FrameRef>,
+    kind: ScopeKind,
+    vars: Vec<(String, Value)>,
+    functions: Vec<(String, Rc<Function>)>,
+    types: Vec<(String, RuntimeType)>,
+}
+
+impl Frame {
+    pub fn root() -> FrameRef {
+        Self::new(None, ScopeKind::Block)
+    }
+
+    pub fn child(parent: &FrameRef, kind: ScopeKind) -> FrameRef {
+        Self::new(Some(parent.clone()), kind)
+    }
+
+    fn new(parent: Option<FrameRef>, kind: ScopeKind) -> FrameRef {
+        Rc::new(RefCell::new(Self {
+            parent,
+            kind,
+            vars: vec![],
+            functions: vec![],
+            types: vec![],
+        }))
+    }
+
+    pub fn define_var(&mut self, name: &str, value: Value) {
+        self.vars.push((name.to_string(), value));
+    }
+
+    pub fn vars(&self) -> &[(String, Value)] {
+        &self.vars
+    }
+}
+
+pub struct Environment {
+    pub cur: FrameRef,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self { cur: Frame::root() }
+    }
+
+    /// Walks the frame chain from the current frame, newest declarations first
+    fn find<T>(
+        &self,
+        crosses_functions: bool,
+        mut f: impl FnMut(&mut Frame) -> Option<T>,
+    ) -> Option<T> {
+        let mut frame = self.cur.clone();
+        loop {
+            let parent = {
+                let mut borrowed = frame.borrow_mut();
+                if let Some(found) = f(&mut borrowed) {
+                    return Some(found);
+                }
+                if borrowed.kind == ScopeKind::Function && !crosses_functions {
+                    return None;
+                }
+                borrowed.parent.clone()?
+            };
+            frame = parent;
+        }
+    }
+
+    pub fn define_var(&self, name: &str, value: Value) {
+        self.cur.borrow_mut().define_var(name, value);
+    }
+
+    pub fn lookup_var(&self, name: &str) -> Option<Value> {
+        self.find(false, |frame| {
+            frame
+                .vars
+                .iter()
+                .rev()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.clone())
+        })
+    }
+
+    /// Returns false when no variable with the name is visible
+    pub fn assign_var(&self, name: &str, value: Value) -> bool {
+        let mut value = Some(value);
+        self.find(false, |frame| {
+            let (_, slot) = frame.vars.iter_mut().rev().find(|(n, _)| n == name)?;
+            *slot = value.take()?;
+            Some(())
+        })
+        .is_some()
+    }
+
+    pub fn define_function(&self, name: &str, function: Function) {
+        self.cur
+            .borrow_mut()
+            .functions
+            .push((name.to_string(), Rc::new(function)));
+    }
+
+    pub fn lookup_function(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(true, |frame| {
+            frame
+                .functions
+                .iter()
+                .rev()
+                .find(|(n, _)| n == name)
+                .map(|(_, function)| function.clone())
+        })
+    }
+
+    pub fn define_type(&self, name: &str, ty: RuntimeType) {
+        self.cur.borrow_mut().types.push((name.to_string(), ty));
+    }
+
+    /// Follows aliases to the struct or enum a type name refers to, returning the owner's name
+    pub fn resolve_type(&self, name: &str) -> Option<(String, RuntimeType)> {
+        let ty = self.find(true, |frame| {
+            frame
+                .types
+                .iter()
+                .rev()
+                .find(|(n, _)| n == name)
+                .map(|(_, ty)| ty.clone())
+        })?;
+        match ty {
+            RuntimeType::Alias(parent) => self.resolve_type(&parent),
+            _ => Some((name.to_string(), ty)),
+        }
+    }
+}
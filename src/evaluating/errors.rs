@@ -0,0 +1,43 @@
+use crate::common::span::Span;
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub span: Span,
+    pub kind: RuntimeErrorKind,
+}
+
+#[derive(Debug)]
+pub enum RuntimeErrorKind {
+    IntegerOverflow,
+    NegativeExponent,
+    StackOverflow(usize),
+    // the checker should have rejected the program, seeing one of these is a bug
+    Internal(String),
+}
+
+impl RuntimeError {
+    pub fn integer_overflow(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: RuntimeErrorKind::IntegerOverflow,
+        }
+    }
+    pub fn negative_exponent(span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: RuntimeErrorKind::NegativeExponent,
+        }
+    }
+    pub fn stack_overflow(depth: usize, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: RuntimeErrorKind::StackOverflow(depth),
+        }
+    }
+    pub fn internal(message: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: RuntimeErrorKind::Internal(message.to_string()),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{panic, thread};
+
+use crate::{
+    checking::scope::ScopeKind,
+    common::{
+        ast::Ast,
+        expression::{
+            Expression, ExpressionKind, FieldInit, InfixOp, MatchArm, Pattern, PatternKind,
+            TypeValue,
+        },
+        span::Span,
+    },
+    evaluating::{
+        environment::{Environment, Frame, FrameRef, Function, RuntimeType},
+        errors::RuntimeError,
+        value::Value,
+    },
+};
+
+pub mod environment;
+pub mod errors;
+pub mod operators;
+pub mod value;
+
+/// Calls nested deeper than this are reported instead of overflowing the interpreter's own stack
+pub const MAX_CALL_DEPTH: usize = 2048;
+
+/// Evaluation recurses on the native stack, this leaves room for `MAX_CALL_DEPTH` nested calls
+/// even in debug builds
+pub const STACK_SIZE: usize = 128 * 1024 * 1024;
+
+/// Runs `f` on a thread with a `STACK_SIZE` stack, evaluators should be created and used inside it
+pub fn with_evaluation_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("Failed to spawn the evaluation thread");
+        handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+/// How evaluation leaves an expression other than by producing a value
+enum Unwind {
+    Break(Value),
+    Continue,
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Self::Error(err)
+    }
+}
+
+type Evaluation = Result<Value, Unwind>;
+
+/// Runs checked ASTs, it relies on the checker for type safety and only reports errors that
+/// depend on runtime values
+pub struct Evaluator {
+    env: Environment,
+    depth: usize,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self {
+            env: Environment::new(),
+            depth: 0,
+        }
+    }
+
+    /// Evaluates every top level expression, returning the value of the last one
+    pub fn evaluate(&mut self, ast: &Ast) -> Result<Value, RuntimeError> {
+        let mut last = Value::Unit;
+        for expr in &ast.0 {
+            last = self
+                .evaluate_expression(expr)
+                .map_err(|unwind| match unwind {
+                    Unwind::Error(err) => err,
+                    Unwind::Break(_) | Unwind::Continue => RuntimeError::internal(
+                        "`break` or `continue` outside of a loop",
+                        &expr.span,
+                    ),
+                })?;
+        }
+        Ok(last)
+    }
+
+    /// The variables declared at the top level, in declaration order
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.env.cur.borrow().vars().to_vec()
+    }
+
+    fn evaluate_expression(&mut self, expr: &Expression) -> Evaluation {
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => Ok(Value::Int(*value)),
+            ExpressionKind::LiteralFloat(value) => Ok(Value::Float(*value)),
+            ExpressionKind::LiteralBool(value) => Ok(Value::Bool(*value)),
+            ExpressionKind::Ident { name, .. } => self.env.lookup_var(name).ok_or_else(|| {
+                RuntimeError::internal(&format!("variable `{name}` is not defined"), &expr.span)
+                    .into()
+            }),
+            ExpressionKind::Prefix { op, rh } => {
+                let rh = self.evaluate_expression(rh)?;
+                Ok(operators::prefix(*op, rh, &expr.span)?)
+            }
+            ExpressionKind::Infix { op, lh, rh } => self.evaluate_infix(*op, lh, rh, &expr.span),
+            ExpressionKind::Block(exprs) => {
+                let frame = Frame::child(&self.env.cur, ScopeKind::Block);
+                self.with_frame(frame, |this| {
+                    let mut last = Value::Unit;
+                    for expr in exprs {
+                        last = this.evaluate_expression(expr)?;
+                    }
+                    Ok(last)
+                })
+            }
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if self.evaluate_bool(condition)? {
+                    self.evaluate_expression(then)
+                } else if let Some(otherwise) = otherwise {
+                    self.evaluate_expression(otherwise)
+                } else {
+                    Ok(Value::Unit)
+                }
+            }
+            ExpressionKind::While { condition, body } => self.evaluate_while(condition, body),
+            ExpressionKind::Loop(body) => self.evaluate_loop(body),
+            ExpressionKind::Break(value) => {
+                let value = match value {
+                    Some(value) => self.evaluate_expression(value)?,
+                    None => Value::Unit,
+                };
+                Err(Unwind::Break(value))
+            }
+            ExpressionKind::Continue => Err(Unwind::Continue),
+            ExpressionKind::VariableDecl { name, value, .. } => {
+                let value = self.evaluate_expression(value)?;
+                self.env.define_var(name, value);
+                Ok(Value::Unit)
+            }
+            ExpressionKind::VariableAssignment { name, value, .. } => {
+                self.evaluate_assignment(name, value, &expr.span)
+            }
+            ExpressionKind::TypeDecl { name, value, .. } => {
+                self.declare_type(name, value);
+                Ok(Value::Unit)
+            }
+            ExpressionKind::FunctionDecl {
+                name, params, body, ..
+            } => {
+                self.env.define_function(
+                    name,
+                    Function {
+                        params: params.iter().map(|param| param.name.clone()).collect(),
+                        body: body.as_ref().clone(),
+                        frame: self.env.cur.clone(),
+                    },
+                );
+                Ok(Value::Unit)
+            }
+            ExpressionKind::FunctionCall { name, args, .. } => {
+                let args = self.evaluate_all(args)?;
+                self.call(name, args, &expr.span)
+            }
+            ExpressionKind::MethodCall {
+                caller, name, args, ..
+            } => {
+                let mut all_args = vec![self.evaluate_expression(caller)?];
+                all_args.extend(self.evaluate_all(args)?);
+                self.call(name, all_args, &expr.span)
+            }
+            ExpressionKind::StructLiteral { name, fields } => {
+                self.evaluate_struct_literal(name, fields, &expr.span)
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                self.evaluate_field_access(target, field, &expr.span)
+            }
+            ExpressionKind::EnumVariant { ty, variant, args } => {
+                self.evaluate_enum_variant(ty, variant, args)
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => {
+                let value = self.evaluate_expression(scrutinee)?;
+                self.evaluate_match(&value, arms, &expr.span)
+            }
+        }
+    }
+
+    fn evaluate_infix(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Evaluation {
+        match op {
+            InfixOp::And | InfixOp::Or => {
+                let lh = self.evaluate_bool(lh)?;
+                // `false && _` and `true || _` are decided without the right hand side
+                if lh == matches!(op, InfixOp::Or) {
+                    Ok(Value::Bool(lh))
+                } else {
+                    Ok(Value::Bool(self.evaluate_bool(rh)?))
+                }
+            }
+            _ => {
+                let lh = self.evaluate_expression(lh)?;
+                let rh = self.evaluate_expression(rh)?;
+                Ok(operators::infix(op, &lh, &rh, span)?)
+            }
+        }
+    }
+
+    fn evaluate_while(&mut self, condition: &Expression, body: &Expression) -> Evaluation {
+        while self.evaluate_bool(condition)? {
+            match self.evaluate_expression(body) {
+                Ok(_) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break(_)) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    fn evaluate_loop(&mut self, body: &Expression) -> Evaluation {
+        loop {
+            match self.evaluate_expression(body) {
+                Ok(_) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break(value)) => return Ok(value),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn evaluate_assignment(&mut self, name: &str, value: &Expression, span: &Span) -> Evaluation {
+        let value = self.evaluate_expression(value)?;
+        if !self.env.assign_var(name, value) {
+            return Err(
+                RuntimeError::internal(&format!("variable `{name}` is not defined"), span).into(),
+            );
+        }
+        Ok(Value::Unit)
+    }
+
+    fn declare_type(&self, name: &str, value: &TypeValue) {
+        let ty = match value {
+            TypeValue::Alias(parent) => RuntimeType::Alias(parent.name.clone()),
+            TypeValue::Struct(fields) => {
+                RuntimeType::Struct(fields.iter().map(|f| f.name.clone()).collect())
+            }
+            TypeValue::Enum(_) => RuntimeType::Enum,
+        };
+        self.env.define_type(name, ty);
+    }
+
+    fn evaluate_struct_literal(
+        &mut self,
+        name: &str,
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Evaluation {
+        // fields are evaluated in the order they are written, but stored in declaration order
+        let mut values = vec![];
+        for field in fields {
+            values.push((field.name.clone(), self.evaluate_expression(&field.value)?));
+        }
+        let Some((ty, RuntimeType::Struct(layout))) = self.env.resolve_type(name) else {
+            return Err(
+                RuntimeError::internal(&format!("`{name}` is not a struct type"), span).into(),
+            );
+        };
+        let fields = layout
+            .iter()
+            .filter_map(|field| values.iter().find(|(n, _)| n == field).cloned())
+            .collect();
+        Ok(Value::Struct { ty, fields })
+    }
+
+    fn evaluate_field_access(
+        &mut self,
+        target: &Expression,
+        field: &str,
+        span: &Span,
+    ) -> Evaluation {
+        match self.evaluate_expression(target)? {
+            Value::Struct { fields, .. } => fields
+                .into_iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+        .ok_or_else(|| RuntimeError::internal(&format!("no field `{field}` to read"), span).into())
+    }
+
+    fn evaluate_enum_variant(
+        &mut self,
+        ty: &str,
+        variant: &str,
+        args: &[Expression],
+    ) -> Evaluation {
+        let args = self.evaluate_all(args)?;
+        // values built through an alias compare equal to ones built through the enum
+        let ty = self
+            .env
+            .resolve_type(ty)
+            .map_or_else(|| ty.to_string(), |(owner, _)| owner);
+        Ok(Value::Variant {
+            ty,
+            name: variant.to_string(),
+            args,
+        })
+    }
+
+    fn evaluate_bool(&mut self, expr: &Expression) -> Result<bool, Unwind> {
+        match self.evaluate_expression(expr)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(RuntimeError::internal(
+                &format!("expected a `Bool`, found `{value}`"),
+                &expr.span,
+            )
+            .into()),
+        }
+    }
+
+    fn evaluate_all(&mut self, exprs: &[Expression]) -> Result<Vec<Value>, Unwind> {
+        exprs
+            .iter()
+            .map(|expr| self.evaluate_expression(expr))
+            .collect()
+    }
+
+    fn evaluate_match(&mut self, value: &Value, arms: &[MatchArm], span: &Span) -> Evaluation {
+        for arm in arms {
+            let mut bindings = vec![];
+            if Self::matches(&arm.pattern, value, &mut bindings) {
+                let frame = Frame::child(&self.env.cur, ScopeKind::Block);
+                for (name, value) in bindings {
+                    frame.borrow_mut().define_var(&name, value);
+                }
+                return self.with_frame(frame, |this| this.evaluate_expression(&arm.body));
+            }
+        }
+        Err(RuntimeError::internal(&format!("no arm matches `{value}`"), span).into())
+    }
+
+    fn matches(pattern: &Pattern, value: &Value, bindings: &mut Vec<(String, Value)>) -> bool {
+        match (&pattern.kind, value) {
+            (PatternKind::Wildcard, _) => true,
+            (PatternKind::Binding { name, .. }, _) => {
+                bindings.push((name.clone(), value.clone()));
+                true
+            }
+            (PatternKind::LiteralInt(expected), Value::Int(value)) => expected == value,
+            (PatternKind::LiteralBool(expected), Value::Bool(value)) => expected == value,
+            (
+                PatternKind::Variant { name, args, .. },
+                Value::Variant {
+                    name: value_name,
+                    args: values,
+                    ..
+                },
+            ) => {
+                name == value_name
+                    && args
+                        .iter()
+                        .zip(values)
+                        .all(|(arg, value)| Self::matches(arg, value, bindings))
+            }
+            _ => false,
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>, span: &Span) -> Evaluation {
+        let Some(function) = self.env.lookup_function(name) else {
+            if name == "to_unit" {
+                return Ok(Value::Unit);
+            }
+            return Err(
+                RuntimeError::internal(&format!("function `{name}` is not defined"), span).into(),
+            );
+        };
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError::stack_overflow(MAX_CALL_DEPTH, span).into());
+        }
+
+        // the body sees its parameters and the declarations around the function, never the caller's
+        let frame = Frame::child(&function.frame, ScopeKind::Function);
+        for (param, arg) in function.params.iter().zip(args) {
+            frame.borrow_mut().define_var(param, arg);
+        }
+        self.depth += 1;
+        let result = self.with_frame(frame, |this| this.evaluate_expression(&function.body));
+        self.depth -= 1;
+        result
+    }
+
+    /// Runs `f` with `frame` as the current frame, restoring the previous one even on errors
+    fn with_frame<T>(&mut self, frame: FrameRef, f: impl FnOnce(&mut Self) -> T) -> T {
+        let original = std::mem::replace(&mut self.env.cur, frame);
+        let result = f(self);
+        self.env.cur = original;
+        result
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    common::{
+        expression::{InfixOp, PrefixOp},
+        span::Span,
+    },
+    evaluating::{errors::RuntimeError, value::Value},
+};
+
+pub fn prefix(op: PrefixOp, rh: Value, span: &Span) -> Result<Value, RuntimeError> {
+    match (op, rh) {
+        (PrefixOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        // the checker lets `!` apply to numbers, which are falsy when zero
+        (PrefixOp::Not, Value::Int(value)) => Ok(Value::Bool(value == 0)),
+        (PrefixOp::Not, Value::Float(value)) => Ok(Value::Bool(value == 0.0)),
+        (PrefixOp::Negate, Value::Int(value)) => value
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| RuntimeError::integer_overflow(span)),
+        (PrefixOp::Negate, Value::Float(value)) => Ok(Value::Float(-value)),
+        (PrefixOp::BitNot, Value::Int(value)) => Ok(Value::Int(!value)),
+        (op, value) => Err(RuntimeError::internal(
+            &format!("cannot apply {op:?} to `{value}`"),
+            span,
+        )),
+    }
+}
+
+pub fn infix(op: InfixOp, lh: &Value, rh: &Value, span: &Span) -> Result<Value, RuntimeError> {
+    match op {
+        InfixOp::Equals => return Ok(Value::Bool(lh == rh)),
+        InfixOp::NotEquals => return Ok(Value::Bool(lh != rh)),
+        _ => {}
+    }
+
+    if let (Value::Int(lh), Value::Int(rh)) = (lh, rh) {
+        return int_infix(op, *lh, *rh, span);
+    }
+    let (Some(lh), Some(rh)) = (lh.as_float(), rh.as_float()) else {
+        return Err(RuntimeError::internal(
+            &format!("cannot apply {op:?} to `{lh}` and `{rh}`"),
+            span,
+        ));
+    };
+    Ok(match op {
+        InfixOp::Add => Value::Float(lh + rh),
+        InfixOp::Subtract => Value::Float(lh - rh),
+        InfixOp::Multiply => Value::Float(lh * rh),
+        InfixOp::Divide => Value::Float(lh / rh),
+        InfixOp::Power => Value::Float(lh.powf(rh)),
+        InfixOp::LessThan => Value::Bool(lh < rh),
+        InfixOp::LessThanOrEqual => Value::Bool(lh <= rh),
+        InfixOp::GreaterThan => Value::Bool(lh > rh),
+        InfixOp::GreaterThanOrEqual => Value::Bool(lh >= rh),
+        InfixOp::Equals | InfixOp::NotEquals | InfixOp::And | InfixOp::Or => {
+            return Err(RuntimeError::internal(
+                &format!("{op:?} is not an arithmetic operator"),
+                span,
+            ));
+        }
+    })
+}
+
+fn int_infix(op: InfixOp, lh: isize, rh: isize, span: &Span) -> Result<Value, RuntimeError> {
+    let result = match op {
+        InfixOp::Add => lh.checked_add(rh),
+        InfixOp::Subtract => lh.checked_sub(rh),
+        InfixOp::Multiply => lh.checked_mul(rh),
+        // the checker types every division as a `Float`
+        #[allow(clippy::cast_precision_loss)]
+        InfixOp::Divide => return Ok(Value::Float(lh as f32 / rh as f32)),
+        InfixOp::Power => {
+            let Ok(exponent) = u32::try_from(rh) else {
+                return Err(if rh < 0 {
+                    RuntimeError::negative_exponent(span)
+                } else {
+                    RuntimeError::integer_overflow(span)
+                });
+            };
+            lh.checked_pow(exponent)
+        }
+        InfixOp::LessThan => return Ok(Value::Bool(lh < rh)),
+        InfixOp::LessThanOrEqual => return Ok(Value::Bool(lh <= rh)),
+        InfixOp::GreaterThan => return Ok(Value::Bool(lh > rh)),
+        InfixOp::GreaterThanOrEqual => return Ok(Value::Bool(lh >= rh)),
+        InfixOp::Equals | InfixOp::NotEquals | InfixOp::And | InfixOp::Or => {
+            return Err(RuntimeError::internal(
+                &format!("{op:?} is not an arithmetic operator"),
+                span,
+            ));
+        }
+    };
+    result
+        .map(Value::Int)
+        .ok_or_else(|| RuntimeError::integer_overflow(span))
+}
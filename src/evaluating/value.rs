@@ -0,0 +1,66 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unit,
+    Int(isize),
+    Float(f32),
+    Bool(bool),
+    Struct {
+        ty: String,
+        fields: Vec<(String, Self)>,
+    },
+    Variant {
+        ty: String,
+        name: String,
+        args: Vec<Self>,
+    },
+}
+
+impl Value {
+    /// The value as a `Float`, the checker lets `Int` and `Float` operands mix in arithmetic
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn as_float(&self) -> Option<f32> {
+        match self {
+            Self::Int(value) => Some(*value as f32),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unit => write!(f, "()"),
+            Self::Int(value) => write!(f, "{value}"),
+            // debug formatting keeps the `.0` on whole floats so they don't read as ints
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Struct { ty, fields } => {
+                write!(f, "{ty} {{ ")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                write!(f, " }}")
+            }
+            Self::Variant { ty, name, args } => {
+                write!(f, "{ty}.{name}")?;
+                if !args.is_empty() {
+                    write!(f, "(")?;
+                    for (index, arg) in args.iter().enumerate() {
+                        if index > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{arg}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{common::span::Span, ir::BlockId};
+
+#[derive(Debug)]
+pub struct LowerError {
+    pub span: Span,
+    pub kind: LowerErrorKind,
+}
+
+// what kind of name went unresolved, the checker resolves every one so these point at bugs
+#[derive(Debug)]
+pub enum LowerErrorKind {
+    Variable(String),
+    Function(String),
+    Type(String),
+    Field(String),
+}
+
+impl LowerError {
+    pub fn unresolved_variable(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: LowerErrorKind::Variable(name.to_string()),
+        }
+    }
+    pub fn unresolved_function(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: LowerErrorKind::Function(name.to_string()),
+        }
+    }
+    pub fn unresolved_type(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: LowerErrorKind::Type(name.to_string()),
+        }
+    }
+    pub fn unresolved_field(name: &str, span: &Span) -> Self {
+        Self {
+            span: span.clone(),
+            kind: LowerErrorKind::Field(name.to_string()),
+        }
+    }
+}
+
+/// A broken invariant of the IR, found by the verifier
+#[derive(Debug)]
+pub struct VerifyError {
+    pub function: String,
+    pub block: Option<BlockId>,
+    pub message: String,
+}
+
+impl VerifyError {
+    pub fn new(function: &str, block: Option<BlockId>, message: String) -> Self {
+        Self {
+            function: function.to_string(),
+            block,
+            message,
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    common::span::Span,
+    evaluating::{MAX_CALL_DEPTH, errors::RuntimeError, operators, value::Value},
+    ir::{
+        Function, FunctionId, Inst, MAIN_FUNCTION, Module, Terminator, Type, TypeDefKind, TypeId,
+        ValueId,
+    },
+};
+
+/// Runs a lowered module, it shares its operators with the evaluator so both report the same
+/// results and runtime errors
+pub struct Interpreter<'a> {
+    module: &'a Module,
+    depth: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    pub const fn new(module: &'a Module) -> Self {
+        Self { module, depth: 0 }
+    }
+
+    /// Runs `main`, returning the value of the last top level expression
+    pub fn run(&mut self) -> Result<Value, RuntimeError> {
+        self.call(MAIN_FUNCTION, &[])
+    }
+
+    fn call(&mut self, id: FunctionId, args: &[Value]) -> Result<Value, RuntimeError> {
+        let function = &self.module.functions[id.0];
+        let mut values: Vec<Option<Value>> = vec![None; function.values.len()];
+        let mut previous = None;
+        let mut block = Function::ENTRY;
+        loop {
+            let insts = &function.block(block).insts;
+            // phis read the values from the end of the predecessor, so they are all read first
+            let phis = insts
+                .iter()
+                .map_while(|id| match &function.value(*id).inst {
+                    Inst::Phi(incoming) => Some((id, incoming)),
+                    _ => None,
+                })
+                .map(|(id, incoming)| {
+                    let value = incoming
+                        .iter()
+                        .find(|(block, _)| Some(*block) == previous)
+                        .map(|(_, value)| get(&values, *value))
+                        .ok_or_else(|| {
+                            RuntimeError::internal(
+                                "phi has no value for its predecessor",
+                                &function.value(*id).span,
+                            )
+                        })?;
+                    Ok((*id, value))
+                })
+                .collect::<Result<Vec<_>, RuntimeError>>()?;
+            let phi_count = phis.len();
+            for (id, value) in phis {
+                values[id.0] = Some(value);
+            }
+            for id in &insts[phi_count..] {
+                values[id.0] = Some(self.execute(function, *id, &values, args)?);
+            }
+
+            previous = Some(block);
+            block = match &function.block(block).terminator {
+                Some(Terminator::Jump(target)) => *target,
+                Some(Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                }) => match get(&values, *condition) {
+                    Value::Bool(true) => *then,
+                    Value::Bool(false) => *otherwise,
+                    value => {
+                        return Err(RuntimeError::internal(
+                            &format!("cannot branch on `{value}`"),
+                            &function.value(*condition).span,
+                        ));
+                    }
+                },
+                Some(Terminator::Return(value)) => return Ok(get(&values, *value)),
+                Some(Terminator::Unreachable) | None => {
+                    return Err(RuntimeError::internal(
+                        &format!("reached the end of bb{}", block.0),
+                        &Span::new(0, 0),
+                    ));
+                }
+            };
+        }
+    }
+
+    fn execute(
+        &mut self,
+        function: &Function,
+        id: ValueId,
+        values: &[Option<Value>],
+        args: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        let value = function.value(id);
+        let span = &value.span;
+        Ok(match &value.inst {
+            Inst::Param(index) => args[*index].clone(),
+            Inst::Const(constant) => constant.value(),
+            Inst::Unary(op, operand) => {
+                operators::prefix(op.prefix_op(), get(values, *operand), span)?
+            }
+            Inst::Binary(op, lh, rh) => {
+                operators::infix(op.infix_op(), &get(values, *lh), &get(values, *rh), span)?
+            }
+            Inst::IntToFloat(operand) => {
+                let operand = get(values, *operand);
+                operand.as_float().map(Value::Float).ok_or_else(|| {
+                    RuntimeError::internal(&format!("cannot convert `{operand}`"), span)
+                })?
+            }
+            Inst::Call(callee, call_args) => {
+                if self.depth == MAX_CALL_DEPTH {
+                    return Err(RuntimeError::stack_overflow(MAX_CALL_DEPTH, span));
+                }
+                let call_args = call_args
+                    .iter()
+                    .map(|arg| get(values, *arg))
+                    .collect::<Vec<_>>();
+                self.depth += 1;
+                let result = self.call(*callee, &call_args);
+                self.depth -= 1;
+                result?
+            }
+            Inst::Struct(ty, fields) => self.build_struct(*ty, fields, values, span)?,
+            Inst::Field(target, index) => match get(values, *target) {
+                Value::Struct { mut fields, .. } if *index < fields.len() => {
+                    fields.swap_remove(*index).1
+                }
+                target => {
+                    return Err(RuntimeError::internal(
+                        &format!("no field {index} in `{target}`"),
+                        span,
+                    ));
+                }
+            },
+            Inst::Variant(ty, tag, payload) => {
+                self.build_variant(*ty, *tag, payload, values, span)?
+            }
+            Inst::Tag(target) => {
+                let tag = match (function.value(*target).ty, get(values, *target)) {
+                    (Type::Enum(ty), Value::Variant { name, .. }) => {
+                        match &self.module.types[ty.0].kind {
+                            TypeDefKind::Enum(variants) => {
+                                variants.iter().position(|(variant, _)| *variant == name)
+                            }
+                            TypeDefKind::Struct(_) => None,
+                        }
+                    }
+                    _ => None,
+                };
+                let tag = tag.ok_or_else(|| RuntimeError::internal("no variant to read", span))?;
+                Value::Int(tag.cast_signed())
+            }
+            Inst::Payload(target, _, index) => match get(values, *target) {
+                Value::Variant { mut args, .. } if *index < args.len() => args.swap_remove(*index),
+                target => {
+                    return Err(RuntimeError::internal(
+                        &format!("no payload {index} in `{target}`"),
+                        span,
+                    ));
+                }
+            },
+            Inst::Phi(_) => {
+                return Err(RuntimeError::internal(
+                    "phi after the start of a block",
+                    span,
+                ));
+            }
+        })
+    }
+
+    fn build_struct(
+        &self,
+        ty: TypeId,
+        fields: &[ValueId],
+        values: &[Option<Value>],
+        span: &Span,
+    ) -> Result<Value, RuntimeError> {
+        let def = &self.module.types[ty.0];
+        let TypeDefKind::Struct(layout) = &def.kind else {
+            return Err(RuntimeError::internal(
+                &format!("`{}` is not a struct type", def.name),
+                span,
+            ));
+        };
+        Ok(Value::Struct {
+            ty: def.name.clone(),
+            fields: layout
+                .iter()
+                .zip(fields)
+                .map(|((name, _), field)| (name.clone(), get(values, *field)))
+                .collect(),
+        })
+    }
+
+    fn build_variant(
+        &self,
+        ty: TypeId,
+        tag: usize,
+        payload: &[ValueId],
+        values: &[Option<Value>],
+        span: &Span,
+    ) -> Result<Value, RuntimeError> {
+        let def = &self.module.types[ty.0];
+        let TypeDefKind::Enum(variants) = &def.kind else {
+            return Err(RuntimeError::internal(
+                &format!("`{}` is not an enum type", def.name),
+                span,
+            ));
+        };
+        Ok(Value::Variant {
+            ty: def.name.clone(),
+            name: variants[tag].0.clone(),
+            args: payload.iter().map(|value| get(values, *value)).collect(),
+        })
+    }
+}
+
+// the verifier checks that definitions dominate their uses, so every operand has a value
+fn get(values: &[Option<Value>], id: ValueId) -> Value {
+    values[id.0]
+        .clone()
+        .expect("Values are defined before they are used")
+}
@@ -0,0 +1,1101 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    checking::scope::{BOOL_TYPE_ID, FLOAT_TYPE_ID, INT_TYPE_ID, NEVER_TYPE_ID, UNIT_TYPE_ID},
+    common::{
+        ast::Ast,
+        expression::{
+            self, Expression, ExpressionKind, FieldInit, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeIdent, TypeValue, VarId,
+        },
+        span::Span,
+    },
+    ir::{
+        BinaryOp, Block, BlockId, Constant, Function, FunctionId, Inst, MAIN_FUNCTION, Module,
+        Terminator, Type, TypeDef, TypeDefKind, TypeId, UnaryOp, Value, ValueId,
+        errors::LowerError,
+    },
+};
+
+#[derive(Clone, Copy)]
+enum Binding {
+    // immutable variables are the value they were declared with
+    Value(ValueId),
+    // mutable variables are given a new value by every assignment, tracked per block
+    Var(VarId),
+}
+
+struct LoopTarget {
+    next: BlockId,
+    exit: BlockId,
+    // the value of every `break` along with the block it jumps from
+    breaks: Vec<(BlockId, ValueId)>,
+}
+
+/// The function being lowered, building SSA form directly as described in "Simple and
+/// Efficient Construction of Static Single Assignment Form" by Braun et al.
+///
+/// A block is sealed once all of its predecessors are known, reading a variable in a block
+/// that isn't sealed yet leaves a phi to be completed when it is
+struct Builder {
+    function: Function,
+    block: BlockId,
+    predecessors: Vec<Vec<BlockId>>,
+    sealed: HashSet<BlockId>,
+    definitions: HashMap<(usize, BlockId), ValueId>,
+    incomplete: HashMap<BlockId, Vec<(usize, ValueId)>>,
+    var_types: HashMap<usize, Type>,
+    // what each variable declared in the function is bound to
+    bindings: HashMap<VarId, Binding>,
+    loops: Vec<LoopTarget>,
+    unit: Option<ValueId>,
+}
+
+impl Builder {
+    fn new(name: &str, params: Vec<Type>, return_ty: Type) -> Self {
+        let mut builder = Self {
+            function: Function {
+                name: name.to_string(),
+                params,
+                return_ty,
+                blocks: vec![],
+                values: vec![],
+            },
+            block: Function::ENTRY,
+            predecessors: vec![],
+            sealed: HashSet::new(),
+            definitions: HashMap::new(),
+            incomplete: HashMap::new(),
+            var_types: HashMap::new(),
+            bindings: HashMap::new(),
+            loops: vec![],
+            unit: None,
+        };
+        let entry = builder.new_block();
+        builder.seal(entry);
+        builder
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.function.blocks.push(Block::default());
+        self.predecessors.push(vec![]);
+        BlockId(self.function.blocks.len() - 1)
+    }
+
+    const fn switch_to(&mut self, block: BlockId) {
+        self.block = block;
+    }
+
+    fn define(&mut self, inst: Inst, ty: Type, block: BlockId, span: &Span) -> ValueId {
+        self.function.values.push(Value {
+            ty,
+            inst,
+            block,
+            span: span.clone(),
+        });
+        ValueId(self.function.values.len() - 1)
+    }
+
+    /// Appends an instruction to the current block
+    fn push(&mut self, inst: Inst, ty: Type, span: &Span) -> ValueId {
+        let value = self.define(inst, ty, self.block, span);
+        self.function.blocks[self.block.0].insts.push(value);
+        value
+    }
+
+    fn constant(&mut self, constant: Constant, span: &Span) -> ValueId {
+        let ty = match constant {
+            Constant::Unit => Type::Unit,
+            Constant::Int(_) => Type::Int,
+            Constant::Float(_) => Type::Float,
+            Constant::Bool(_) => Type::Bool,
+        };
+        self.push(Inst::Const(constant), ty, span)
+    }
+
+    /// The unit value, defined once at the start of the function
+    fn unit(&mut self, span: &Span) -> ValueId {
+        if let Some(unit) = self.unit {
+            return unit;
+        }
+        let unit = self.define(
+            Inst::Const(Constant::Unit),
+            Type::Unit,
+            Function::ENTRY,
+            span,
+        );
+        self.function.blocks[Function::ENTRY.0]
+            .insts
+            .insert(0, unit);
+        self.unit = Some(unit);
+        unit
+    }
+
+    /// Adds a phi after the phis already at the start of a block
+    fn phi(
+        &mut self,
+        block: BlockId,
+        ty: Type,
+        incoming: Vec<(BlockId, ValueId)>,
+        span: &Span,
+    ) -> ValueId {
+        let phi = self.define(Inst::Phi(incoming), ty, block, span);
+        let insts = &self.function.blocks[block.0].insts;
+        let position = insts
+            .iter()
+            .take_while(|value| matches!(self.function.values[value.0].inst, Inst::Phi(_)))
+            .count();
+        self.function.blocks[block.0].insts.insert(position, phi);
+        phi
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        for successor in terminator.successors() {
+            self.predecessors[successor.0].push(self.block);
+        }
+        self.function.blocks[self.block.0].terminator = Some(terminator);
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        self.terminate(Terminator::Jump(target));
+    }
+
+    fn seal(&mut self, block: BlockId) {
+        for (var, phi) in self.incomplete.remove(&block).unwrap_or_default() {
+            self.add_phi_operands(var, phi);
+        }
+        self.sealed.insert(block);
+    }
+
+    fn write_var(&mut self, var: usize, ty: Type, value: ValueId) {
+        self.var_types.insert(var, ty);
+        self.definitions.insert((var, self.block), value);
+    }
+
+    fn read_var(&mut self, var: usize, block: BlockId) -> ValueId {
+        if let Some(value) = self.definitions.get(&(var, block)) {
+            return *value;
+        }
+        let ty = self.var_types[&var];
+        let span = Span::new(0, 0);
+        let value = if !self.sealed.contains(&block) {
+            let phi = self.phi(block, ty, vec![], &span);
+            self.incomplete.entry(block).or_default().push((var, phi));
+            phi
+        } else if let [predecessor] = self.predecessors[block.0][..] {
+            self.read_var(var, predecessor)
+        } else {
+            // defined before its operands are read, so that loops find it
+            let phi = self.phi(block, ty, vec![], &span);
+            self.definitions.insert((var, block), phi);
+            self.add_phi_operands(var, phi);
+            phi
+        };
+        self.definitions.insert((var, block), value);
+        value
+    }
+
+    fn add_phi_operands(&mut self, var: usize, phi: ValueId) {
+        let block = self.function.values[phi.0].block;
+        for predecessor in self.predecessors[block.0].clone() {
+            let value = self.read_var(var, predecessor);
+            if let Inst::Phi(incoming) = &mut self.function.values[phi.0].inst {
+                incoming.push((predecessor, value));
+            }
+        }
+    }
+
+    /// Removes phis that only ever see one value, then blocks that can't be reached
+    fn finish(mut self) -> Function {
+        self.remove_trivial_phis();
+        self.function.remove_unreachable_blocks();
+        self.function
+    }
+
+    fn remove_trivial_phis(&mut self) {
+        loop {
+            let trivial = self.function.blocks.iter().find_map(|block| {
+                block.insts.iter().find_map(|value| {
+                    let operand = self.function.phi_value(*value)?;
+                    Some((*value, operand))
+                })
+            });
+            let Some((phi, operand)) = trivial else {
+                return;
+            };
+            self.function.replace_uses(phi, operand);
+            self.function.remove_value(phi);
+        }
+    }
+}
+
+type Lowering = Result<Option<ValueId>, LowerError>;
+
+/// Lowers a checked `Ast` to SSA form, every expression lowers to the value it produces or to
+/// `None` when control never gets past it, like after a `break`
+pub struct Lowerer {
+    types: Vec<TypeDef>,
+    functions: Vec<Option<Function>>,
+    returns: Vec<Type>,
+    // the function and type each declaration the checker numbered lowered to
+    declared_functions: HashMap<expression::FunctionId, FunctionId>,
+    declared_types: HashMap<expression::TypeId, Type>,
+    builders: Vec<Builder>,
+}
+
+impl Lowerer {
+    pub fn new() -> Self {
+        let declared_types = HashMap::from([
+            (UNIT_TYPE_ID, Type::Unit),
+            (INT_TYPE_ID, Type::Int),
+            (FLOAT_TYPE_ID, Type::Float),
+            (BOOL_TYPE_ID, Type::Bool),
+            // nothing of type `Never` is ever produced
+            (NEVER_TYPE_ID, Type::Unit),
+        ]);
+        Self {
+            types: vec![],
+            functions: vec![],
+            returns: vec![],
+            declared_functions: HashMap::new(),
+            declared_types,
+            builders: vec![],
+        }
+    }
+
+    pub fn lower(mut self, ast: &Ast) -> Result<Module, LowerError> {
+        let main = self.reserve_function(Type::Unit);
+        debug_assert_eq!(main, MAIN_FUNCTION);
+        self.builders.push(Builder::new("main", vec![], Type::Unit));
+
+        let value = self.lower_sequence(&ast.0, &Span::new(0, 0))?;
+        let return_ty = match ast.0.last() {
+            Some(last) => self.type_of(last)?,
+            None => Type::Unit,
+        };
+        if let Some(value) = value {
+            self.builder().terminate(Terminator::Return(value));
+        }
+        let mut builder = self.builders.pop().expect("Main is being lowered");
+        builder.function.return_ty = return_ty;
+        self.functions[main.0] = Some(builder.finish());
+
+        Ok(Module {
+            types: self.types,
+            functions: self
+                .functions
+                .into_iter()
+                .map(|function| function.expect("Every reserved function is lowered"))
+                .collect(),
+        })
+    }
+
+    fn lower_expression(&mut self, expr: &Expression) -> Lowering {
+        let span = &expr.span;
+        match &expr.kind {
+            ExpressionKind::LiteralInt(value) => {
+                Ok(Some(self.builder().constant(Constant::Int(*value), span)))
+            }
+            ExpressionKind::LiteralFloat(value) => {
+                Ok(Some(self.builder().constant(Constant::Float(*value), span)))
+            }
+            ExpressionKind::LiteralBool(value) => {
+                Ok(Some(self.builder().constant(Constant::Bool(*value), span)))
+            }
+            ExpressionKind::Ident { name, var } => {
+                let binding = self
+                    .resolve_var(*var)
+                    .ok_or_else(|| LowerError::unresolved_variable(name, span))?;
+                Ok(Some(match binding {
+                    Binding::Value(value) => value,
+                    Binding::Var(var) => {
+                        let builder = self.builder();
+                        builder.read_var(var, builder.block)
+                    }
+                }))
+            }
+            ExpressionKind::Prefix { op, rh } => self.lower_prefix(*op, rh, span),
+            ExpressionKind::Infix { op, lh, rh } => match op {
+                InfixOp::And | InfixOp::Or => self.lower_logical(*op, lh, rh, span),
+                _ => self.lower_infix(*op, lh, rh, span),
+            },
+            ExpressionKind::Block(exprs) => self.lower_sequence(exprs, span),
+            ExpressionKind::If {
+                condition,
+                then,
+                otherwise,
+            } => self.lower_if(expr, condition, then, otherwise.as_deref()),
+            ExpressionKind::While { condition, body } => self.lower_while(condition, body, span),
+            ExpressionKind::Loop(body) => self.lower_loop(expr, body),
+            ExpressionKind::Break(value) => self.lower_break(value.as_deref(), span),
+            ExpressionKind::Continue => {
+                let builder = self.builder();
+                let next = builder
+                    .loops
+                    .last()
+                    .expect("The checker only allows `continue` in loops")
+                    .next;
+                builder.jump(next);
+                Ok(None)
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                var,
+                ..
+            } => self.lower_variable_decl(name, value, *mutable, ty.as_ref(), *var, span),
+            ExpressionKind::VariableAssignment { name, value, var } => {
+                self.lower_assignment(name, value, *var, span)
+            }
+            ExpressionKind::TypeDecl {
+                name, value, ty, ..
+            } => self.lower_type_decl(name, value, *ty, span),
+            ExpressionKind::FunctionDecl {
+                name,
+                params,
+                return_ty,
+                body,
+                function,
+                ..
+            } => self.lower_function(name, params, return_ty.as_ref(), body, *function, span),
+            ExpressionKind::FunctionCall {
+                name,
+                args,
+                function,
+            } => self.lower_call(name, *function, &args.iter().collect::<Vec<_>>(), span),
+            ExpressionKind::MethodCall {
+                caller,
+                name,
+                args,
+                function,
+            } => {
+                let args = std::iter::once(caller.as_ref())
+                    .chain(args.iter())
+                    .collect::<Vec<_>>();
+                self.lower_call(name, *function, &args, span)
+            }
+            ExpressionKind::StructLiteral { fields, .. } => {
+                self.lower_struct_literal(expr, fields, span)
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                self.lower_field_access(target, field, span)
+            }
+            ExpressionKind::EnumVariant { variant, args, .. } => {
+                self.lower_enum_variant(expr, variant, args, span)
+            }
+            ExpressionKind::Import(_) => unreachable!("imports are resolved by the checker"),
+            ExpressionKind::Error => {
+                unreachable!("programs with parse errors never get past the checker")
+            }
+            ExpressionKind::Match { scrutinee, arms } => self.lower_match(expr, scrutinee, arms),
+        }
+    }
+
+    fn lower_prefix(&mut self, op: PrefixOp, rh: &Expression, span: &Span) -> Lowering {
+        let Some(value) = self.lower_expression(rh)? else {
+            return Ok(None);
+        };
+        let ty = self.type_of(rh)?;
+        let builder = self.builder();
+        Ok(Some(match (op, ty) {
+            // numbers are falsy when zero
+            (PrefixOp::Not, Type::Int | Type::Float) => {
+                let zero = match ty {
+                    Type::Int => Constant::Int(0),
+                    _ => Constant::Float(0.0),
+                };
+                let zero = builder.constant(zero, span);
+                builder.push(Inst::Binary(BinaryOp::Eq, value, zero), Type::Bool, span)
+            }
+            (PrefixOp::Not, _) => builder.push(Inst::Unary(UnaryOp::Not, value), ty, span),
+            (PrefixOp::Negate, _) => builder.push(Inst::Unary(UnaryOp::Neg, value), ty, span),
+            (PrefixOp::BitNot, _) => builder.push(Inst::Unary(UnaryOp::BitNot, value), ty, span),
+        }))
+    }
+
+    fn lower_if(
+        &mut self,
+        expr: &Expression,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: Option<&Expression>,
+    ) -> Lowering {
+        let span = &expr.span;
+        let Some(condition) = self.lower_expression(condition)? else {
+            return Ok(None);
+        };
+        let builder = self.builder();
+        let condition_block = builder.block;
+        let then_block = builder.new_block();
+        let end = builder.new_block();
+        let else_block = match otherwise {
+            Some(_) => builder.new_block(),
+            None => end,
+        };
+        builder.terminate(Terminator::Branch {
+            condition,
+            then: then_block,
+            otherwise: else_block,
+        });
+        builder.seal(then_block);
+
+        let mut incoming = vec![];
+        builder.switch_to(then_block);
+        self.lower_branch(then, end, &mut incoming)?;
+        if let Some(otherwise) = otherwise {
+            self.builder().seal(else_block);
+            self.builder().switch_to(else_block);
+            self.lower_branch(otherwise, end, &mut incoming)?;
+        } else {
+            // the condition jumps straight past `then`
+            incoming.push((condition_block, self.builder().unit(span)));
+        }
+        self.merge(expr, end, incoming)
+    }
+
+    fn lower_while(&mut self, condition: &Expression, body: &Expression, span: &Span) -> Lowering {
+        let builder = self.builder();
+        let header = builder.new_block();
+        builder.jump(header);
+        builder.switch_to(header);
+        let Some(condition) = self.lower_expression(condition)? else {
+            return Ok(None);
+        };
+        let builder = self.builder();
+        let body_block = builder.new_block();
+        let exit = builder.new_block();
+        builder.terminate(Terminator::Branch {
+            condition,
+            then: body_block,
+            otherwise: exit,
+        });
+        builder.seal(body_block);
+        builder.switch_to(body_block);
+        builder.loops.push(LoopTarget {
+            next: header,
+            exit,
+            breaks: vec![],
+        });
+        let value = self.lower_expression(body);
+        let builder = self.builder();
+        builder.loops.pop();
+        if value?.is_some() {
+            builder.jump(header);
+        }
+        builder.seal(header);
+        builder.seal(exit);
+        builder.switch_to(exit);
+        Ok(Some(builder.unit(span)))
+    }
+
+    fn lower_loop(&mut self, expr: &Expression, body: &Expression) -> Lowering {
+        let builder = self.builder();
+        let body_block = builder.new_block();
+        let exit = builder.new_block();
+        builder.jump(body_block);
+        builder.switch_to(body_block);
+        builder.loops.push(LoopTarget {
+            next: body_block,
+            exit,
+            breaks: vec![],
+        });
+        let value = self.lower_expression(body);
+        let builder = self.builder();
+        let target = builder.loops.pop().expect("Loop target was pushed");
+        if value?.is_some() {
+            builder.jump(body_block);
+        }
+        builder.seal(body_block);
+        self.merge(expr, exit, target.breaks)
+    }
+
+    fn lower_break(&mut self, value: Option<&Expression>, span: &Span) -> Lowering {
+        let value = match value {
+            Some(value) => match self.lower_expression(value)? {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            None => self.builder().unit(span),
+        };
+        let builder = self.builder();
+        let block = builder.block;
+        let target = builder
+            .loops
+            .last_mut()
+            .expect("The checker only allows `break` in loops");
+        target.breaks.push((block, value));
+        let exit = target.exit;
+        builder.jump(exit);
+        Ok(None)
+    }
+
+    fn lower_variable_decl(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        mutable: bool,
+        ty: Option<&TypeIdent>,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Lowering {
+        let Some(var) = var else {
+            return Err(LowerError::unresolved_variable(name, span));
+        };
+        let Some(value_id) = self.lower_expression(value)? else {
+            return Ok(None);
+        };
+        let ty = match ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => self.type_of(value)?,
+        };
+        let binding = if mutable {
+            self.builder().write_var(var, ty, value_id);
+            Binding::Var(var)
+        } else {
+            Binding::Value(value_id)
+        };
+        self.builder().bindings.insert(var, binding);
+        Ok(Some(self.builder().unit(span)))
+    }
+
+    fn lower_assignment(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        var: Option<VarId>,
+        span: &Span,
+    ) -> Lowering {
+        let Some(value_id) = self.lower_expression(value)? else {
+            return Ok(None);
+        };
+        let Some(Binding::Var(var)) = self.resolve_var(var) else {
+            return Err(LowerError::unresolved_variable(name, span));
+        };
+        let ty = self.type_of(value)?;
+        self.builder().write_var(var, ty, value_id);
+        Ok(Some(self.builder().unit(span)))
+    }
+
+    fn lower_type_decl(
+        &mut self,
+        name: &str,
+        value: &TypeValue,
+        declared: Option<expression::TypeId>,
+        span: &Span,
+    ) -> Lowering {
+        let ty = match value {
+            TypeValue::Alias(parent) => self.resolve_type(parent.resolved(), span)?,
+            TypeValue::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        Ok((
+                            field.name.clone(),
+                            self.resolve_type(field.ty.resolved(), span)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, LowerError>>()?;
+                Type::Struct(self.define_type(name, TypeDefKind::Struct(fields)))
+            }
+            TypeValue::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let payload = variant
+                            .payload
+                            .iter()
+                            .map(|ty| self.resolve_type(ty.resolved(), span))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok((variant.name.clone(), payload))
+                    })
+                    .collect::<Result<Vec<_>, LowerError>>()?;
+                Type::Enum(self.define_type(name, TypeDefKind::Enum(variants)))
+            }
+        };
+        if let Some(declared) = declared {
+            self.declared_types.insert(declared, ty);
+        }
+        Ok(Some(self.builder().unit(span)))
+    }
+
+    fn lower_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        return_ty: Option<&TypeIdent>,
+        body: &Expression,
+        declared: Option<expression::FunctionId>,
+        span: &Span,
+    ) -> Lowering {
+        let return_ty = match return_ty {
+            Some(ty) => self.resolve_type(ty.resolved(), span)?,
+            None => Type::Unit,
+        };
+        let param_types = params
+            .iter()
+            .map(|param| self.resolve_type(param.ty.resolved(), span))
+            .collect::<Result<Vec<_>, _>>()?;
+        // declared before the body is lowered so that functions can recurse
+        let function = self.reserve_function(return_ty);
+        if let Some(declared) = declared {
+            self.declared_functions.insert(declared, function);
+        }
+
+        let mut builder = Builder::new(name, param_types.clone(), return_ty);
+        for (index, (param, ty)) in params.iter().zip(param_types).enumerate() {
+            let value = builder.push(Inst::Param(index), ty, span);
+            if let Some(var) = param.var {
+                builder.bindings.insert(var, Binding::Value(value));
+            }
+        }
+        self.builders.push(builder);
+        let value = self.lower_expression(body);
+        if let Ok(Some(value)) = value {
+            self.builder().terminate(Terminator::Return(value));
+        }
+        let builder = self.builders.pop().expect("The function is being lowered");
+        value?;
+        self.functions[function.0] = Some(builder.finish());
+        Ok(Some(self.builder().unit(span)))
+    }
+
+    fn lower_struct_literal(
+        &mut self,
+        expr: &Expression,
+        fields: &[FieldInit],
+        span: &Span,
+    ) -> Lowering {
+        let ty = self.type_of(expr)?;
+        let name = expr.ty().0;
+        let Type::Struct(id) = ty else {
+            return Err(LowerError::unresolved_type(name, span));
+        };
+        let mut values = vec![];
+        for field in fields {
+            let Some(value) = self.lower_expression(&field.value)? else {
+                return Ok(None);
+            };
+            values.push((field.name.as_str(), value));
+        }
+        let TypeDefKind::Struct(declared) = &self.types[id.0].kind else {
+            return Err(LowerError::unresolved_type(name, span));
+        };
+        // fields are evaluated as written but laid out as declared
+        let ordered = declared
+            .iter()
+            .map(|(field, _)| {
+                values
+                    .iter()
+                    .find(|(name, _)| name == field)
+                    .map(|(_, value)| *value)
+                    .ok_or_else(|| LowerError::unresolved_field(field, span))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(self.builder().push(
+            Inst::Struct(id, ordered),
+            ty,
+            span,
+        )))
+    }
+
+    fn lower_field_access(&mut self, target: &Expression, field: &str, span: &Span) -> Lowering {
+        let Some(value) = self.lower_expression(target)? else {
+            return Ok(None);
+        };
+        let Type::Struct(id) = self.type_of(target)? else {
+            return Err(LowerError::unresolved_field(field, span));
+        };
+        let TypeDefKind::Struct(fields) = &self.types[id.0].kind else {
+            return Err(LowerError::unresolved_field(field, span));
+        };
+        let (index, ty) = fields
+            .iter()
+            .enumerate()
+            .find(|(_, (name, _))| name == field)
+            .map(|(index, (_, ty))| (index, *ty))
+            .ok_or_else(|| LowerError::unresolved_field(field, span))?;
+        Ok(Some(self.builder().push(
+            Inst::Field(value, index),
+            ty,
+            span,
+        )))
+    }
+
+    fn lower_enum_variant(
+        &mut self,
+        expr: &Expression,
+        variant: &str,
+        args: &[Expression],
+        span: &Span,
+    ) -> Lowering {
+        let enum_type = self.type_of(expr)?;
+        let ty = expr.ty().0;
+        let tag = self
+            .variants(enum_type)
+            .and_then(|variants| variants.iter().position(|(name, _)| name == variant))
+            .ok_or_else(|| LowerError::unresolved_type(&format!("{ty}.{variant}"), span))?;
+        let mut values = vec![];
+        for arg in args {
+            let Some(value) = self.lower_expression(arg)? else {
+                return Ok(None);
+            };
+            values.push(value);
+        }
+        let Type::Enum(id) = enum_type else {
+            return Err(LowerError::unresolved_type(ty, span));
+        };
+        Ok(Some(self.builder().push(
+            Inst::Variant(id, tag, values),
+            enum_type,
+            span,
+        )))
+    }
+
+    fn lower_match(
+        &mut self,
+        expr: &Expression,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+    ) -> Lowering {
+        let Some(value) = self.lower_expression(scrutinee)? else {
+            return Ok(None);
+        };
+        let ty = self.type_of(scrutinee)?;
+        let end = self.builder().new_block();
+        let mut incoming = vec![];
+        let mut fail = None;
+        for arm in arms {
+            let next = self.builder().new_block();
+            self.lower_pattern(&arm.pattern, value, ty, next)?;
+            self.builder().seal(next);
+            self.lower_branch(&arm.body, end, &mut incoming)?;
+
+            if arm.pattern.is_irrefutable() {
+                fail = None;
+                break;
+            }
+            self.builder().switch_to(next);
+            fail = Some(next);
+        }
+        if fail.is_some() {
+            // no arm applies, which the checker rules out
+            self.builder().terminate(Terminator::Unreachable);
+        }
+        self.merge(expr, end, incoming)
+    }
+
+    fn lower_sequence(&mut self, exprs: &[Expression], span: &Span) -> Lowering {
+        if exprs.is_empty() {
+            return Ok(Some(self.builder().unit(span)));
+        }
+        let mut value = None;
+        for expr in exprs {
+            value = self.lower_expression(expr)?;
+            // nothing after a `break` or `continue` can run
+            if value.is_none() {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Lowers one arm of a conditional, jumping to `end` with its value if it has one
+    fn lower_branch(
+        &mut self,
+        expr: &Expression,
+        end: BlockId,
+        incoming: &mut Vec<(BlockId, ValueId)>,
+    ) -> Result<(), LowerError> {
+        if let Some(value) = self.lower_expression(expr)? {
+            let builder = self.builder();
+            incoming.push((builder.block, value));
+            builder.jump(end);
+        }
+        Ok(())
+    }
+
+    /// Continues in `end` with the value of a conditional or loop, a phi over the values
+    /// coming in from its predecessors
+    fn merge(
+        &mut self,
+        expr: &Expression,
+        end: BlockId,
+        incoming: Vec<(BlockId, ValueId)>,
+    ) -> Lowering {
+        let ty = self.type_of(expr)?;
+        let builder = self.builder();
+        builder.seal(end);
+        builder.switch_to(end);
+        if incoming.is_empty() {
+            return Ok(None);
+        }
+        if ty == Type::Unit {
+            return Ok(Some(builder.unit(&expr.span)));
+        }
+        Ok(Some(builder.phi(end, ty, incoming, &expr.span)))
+    }
+
+    fn lower_infix(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Lowering {
+        let Some(mut lh_value) = self.lower_expression(lh)? else {
+            return Ok(None);
+        };
+        let Some(mut rh_value) = self.lower_expression(rh)? else {
+            return Ok(None);
+        };
+        let lh_type = self.type_of(lh)?;
+        let rh_type = self.type_of(rh)?;
+        let builder = self.builder();
+
+        let op = match op {
+            InfixOp::Add => BinaryOp::Add,
+            InfixOp::Subtract => BinaryOp::Sub,
+            InfixOp::Multiply => BinaryOp::Mul,
+            InfixOp::Divide => BinaryOp::Div,
+            InfixOp::Power => BinaryOp::Pow,
+            InfixOp::LessThan => BinaryOp::Lt,
+            InfixOp::LessThanOrEqual => BinaryOp::Le,
+            InfixOp::GreaterThan => BinaryOp::Gt,
+            InfixOp::GreaterThanOrEqual => BinaryOp::Ge,
+            InfixOp::Equals | InfixOp::NotEquals => {
+                let op = if matches!(op, InfixOp::Equals) {
+                    BinaryOp::Eq
+                } else {
+                    BinaryOp::Ne
+                };
+                return Ok(Some(builder.push(
+                    Inst::Binary(op, lh_value, rh_value),
+                    Type::Bool,
+                    span,
+                )));
+            }
+            InfixOp::And | InfixOp::Or => unreachable!("lowered with branches"),
+        };
+
+        // mixing an `Int` with a `Float`, or dividing, computes in `Float`
+        let ty = if lh_type == Type::Int && rh_type == Type::Int && op != BinaryOp::Div {
+            Type::Int
+        } else {
+            if lh_type == Type::Int {
+                lh_value = builder.push(Inst::IntToFloat(lh_value), Type::Float, span);
+            }
+            if rh_type == Type::Int {
+                rh_value = builder.push(Inst::IntToFloat(rh_value), Type::Float, span);
+            }
+            Type::Float
+        };
+        let result_ty = match op {
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => Type::Bool,
+            _ => ty,
+        };
+        Ok(Some(builder.push(
+            Inst::Binary(op, lh_value, rh_value),
+            result_ty,
+            span,
+        )))
+    }
+
+    fn lower_logical(
+        &mut self,
+        op: InfixOp,
+        lh: &Expression,
+        rh: &Expression,
+        span: &Span,
+    ) -> Lowering {
+        let Some(lh) = self.lower_expression(lh)? else {
+            return Ok(None);
+        };
+        let short_circuit = matches!(op, InfixOp::Or);
+        let builder = self.builder();
+        let short_value = builder.constant(Constant::Bool(short_circuit), span);
+        let rh_block = builder.new_block();
+        let end = builder.new_block();
+        let (then, otherwise) = if short_circuit {
+            (end, rh_block)
+        } else {
+            (rh_block, end)
+        };
+        let mut incoming = vec![(builder.block, short_value)];
+        builder.terminate(Terminator::Branch {
+            condition: lh,
+            then,
+            otherwise,
+        });
+        builder.seal(rh_block);
+        builder.switch_to(rh_block);
+        if let Some(rh) = self.lower_expression(rh)? {
+            let builder = self.builder();
+            incoming.push((builder.block, rh));
+            builder.jump(end);
+        }
+        let builder = self.builder();
+        builder.seal(end);
+        builder.switch_to(end);
+        Ok(Some(builder.phi(end, Type::Bool, incoming, span)))
+    }
+
+    fn lower_call(
+        &mut self,
+        name: &str,
+        function: Option<expression::FunctionId>,
+        args: &[&Expression],
+        span: &Span,
+    ) -> Lowering {
+        let function =
+            function.and_then(|function| self.declared_functions.get(&function).copied());
+        let mut values = vec![];
+        for arg in args {
+            let Some(value) = self.lower_expression(arg)? else {
+                return Ok(None);
+            };
+            values.push(value);
+        }
+        match function {
+            Some(function) => {
+                let ty = self.returns[function.0];
+                Ok(Some(self.builder().push(
+                    Inst::Call(function, values),
+                    ty,
+                    span,
+                )))
+            }
+            // `to_unit` is a builtin that discards its arguments
+            None if name == "to_unit" => Ok(Some(self.builder().unit(span))),
+            None => Err(LowerError::unresolved_function(name, span)),
+        }
+    }
+
+    /// Lowers the tests of a pattern, branching to `fail` as soon as one of them doesn't hold
+    fn lower_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: ValueId,
+        ty: Type,
+        fail: BlockId,
+    ) -> Result<(), LowerError> {
+        let span = &pattern.span;
+        let holds = match &pattern.kind {
+            PatternKind::Wildcard => return Ok(()),
+            PatternKind::Binding { var, .. } => {
+                if let Some(var) = var {
+                    self.builder().bindings.insert(*var, Binding::Value(value));
+                }
+                return Ok(());
+            }
+            PatternKind::LiteralInt(literal) => {
+                let builder = self.builder();
+                let literal = builder.constant(Constant::Int(*literal), span);
+                builder.push(Inst::Binary(BinaryOp::Eq, value, literal), Type::Bool, span)
+            }
+            PatternKind::LiteralBool(true) => value,
+            PatternKind::LiteralBool(false) => {
+                self.builder()
+                    .push(Inst::Unary(UnaryOp::Not, value), Type::Bool, span)
+            }
+            PatternKind::Variant { name, args, .. } => {
+                let variants = self
+                    .variants(ty)
+                    .ok_or_else(|| LowerError::unresolved_type(name, span))?;
+                let tag = variants
+                    .iter()
+                    .position(|(variant, _)| variant == name)
+                    .ok_or_else(|| LowerError::unresolved_type(name, span))?;
+                let payload = variants[tag].1.clone();
+
+                let builder = self.builder();
+                let actual = builder.push(Inst::Tag(value), Type::Int, span);
+                let expected = builder.constant(Constant::Int(tag.cast_signed()), span);
+                let holds = builder.push(
+                    Inst::Binary(BinaryOp::Eq, actual, expected),
+                    Type::Bool,
+                    span,
+                );
+                self.test(holds, fail);
+                for (index, (arg, arg_type)) in args.iter().zip(payload).enumerate() {
+                    // wildcards don't need the value
+                    if matches!(arg.kind, PatternKind::Wildcard) {
+                        continue;
+                    }
+                    let arg_value =
+                        self.builder()
+                            .push(Inst::Payload(value, tag, index), arg_type, &arg.span);
+                    self.lower_pattern(arg, arg_value, arg_type, fail)?;
+                }
+                return Ok(());
+            }
+        };
+        self.test(holds, fail);
+        Ok(())
+    }
+
+    /// Continues in a new block when a condition holds, branching to `fail` otherwise
+    fn test(&mut self, condition: ValueId, fail: BlockId) {
+        let builder = self.builder();
+        let next = builder.new_block();
+        builder.terminate(Terminator::Branch {
+            condition,
+            then: next,
+            otherwise: fail,
+        });
+        builder.seal(next);
+        builder.switch_to(next);
+    }
+
+    fn reserve_function(&mut self, return_ty: Type) -> FunctionId {
+        self.functions.push(None);
+        self.returns.push(return_ty);
+        FunctionId(self.functions.len() - 1)
+    }
+
+    fn define_type(&mut self, name: &str, kind: TypeDefKind) -> TypeId {
+        self.types.push(TypeDef {
+            name: name.to_string(),
+            kind,
+        });
+        TypeId(self.types.len() - 1)
+    }
+
+    fn variants(&self, ty: Type) -> Option<&Vec<(String, Vec<Type>)>> {
+        match ty {
+            Type::Enum(id) => match &self.types[id.0].kind {
+                TypeDefKind::Enum(variants) => Some(variants),
+                TypeDefKind::Struct(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn builder(&mut self) -> &mut Builder {
+        self.builders
+            .last_mut()
+            .expect("Lowering outside of a function")
+    }
+
+    /// What a variable is bound to, which the checker only lets the function declaring it use
+    fn resolve_var(&self, var: Option<VarId>) -> Option<Binding> {
+        let builder = self.builders.last()?;
+        var.and_then(|var| builder.bindings.get(&var).copied())
+    }
+
+    fn resolve_type(
+        &self,
+        (name, id): (&str, expression::TypeId),
+        span: &Span,
+    ) -> Result<Type, LowerError> {
+        self.declared_types
+            .get(&id)
+            .copied()
+            .ok_or_else(|| LowerError::unresolved_type(name, span))
+    }
+
+    fn type_of(&self, expr: &Expression) -> Result<Type, LowerError> {
+        self.resolve_type(expr.ty(), &expr.span)
+    }
+}
@@ -0,0 +1,405 @@
+use crate::{
+    common::{
+        expression::{InfixOp, PrefixOp},
+        span::Span,
+    },
+    evaluating::value::Value as RuntimeValue,
+};
+
+pub mod errors;
+pub mod interpreter;
+pub mod lowering;
+pub mod passes;
+pub mod printer;
+pub mod verifier;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValueId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FunctionId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeId(pub usize);
+
+/// Aliases are resolved away, so two values have the same type exactly when these are equal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Type {
+    Unit,
+    Int,
+    Float,
+    Bool,
+    Struct(TypeId),
+    Enum(TypeId),
+}
+
+pub enum TypeDefKind {
+    Struct(Vec<(String, Type)>),
+    Enum(Vec<(String, Vec<Type>)>),
+}
+
+pub struct TypeDef {
+    pub name: String,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Constant {
+    Unit,
+    Int(isize),
+    Float(f32),
+    Bool(bool),
+}
+
+impl Constant {
+    pub const fn value(self) -> RuntimeValue {
+        match self {
+            Self::Unit => RuntimeValue::Unit,
+            Self::Int(value) => RuntimeValue::Int(value),
+            Self::Float(value) => RuntimeValue::Float(value),
+            Self::Bool(value) => RuntimeValue::Bool(value),
+        }
+    }
+
+    /// The constant for a runtime value, structs and variants are built by instructions instead
+    pub const fn from_value(value: &RuntimeValue) -> Option<Self> {
+        match value {
+            RuntimeValue::Unit => Some(Self::Unit),
+            RuntimeValue::Int(value) => Some(Self::Int(*value)),
+            RuntimeValue::Float(value) => Some(Self::Float(*value)),
+            RuntimeValue::Bool(value) => Some(Self::Bool(*value)),
+            RuntimeValue::Struct { .. } | RuntimeValue::Variant { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Logical not of a `Bool`
+    Not,
+    /// Negation of an `Int`, which overflows for the smallest one, or of a `Float`
+    Neg,
+    BitNot,
+}
+
+impl UnaryOp {
+    /// The operator that behaves the same in the evaluator
+    pub const fn prefix_op(self) -> PrefixOp {
+        match self {
+            Self::Not => PrefixOp::Not,
+            Self::Neg => PrefixOp::Negate,
+            Self::BitNot => PrefixOp::BitNot,
+        }
+    }
+}
+
+/// Operands have the same type, `Int` arithmetic is checked and `Div` only applies to `Float`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Structural equality, defined for every type
+    Eq,
+    Ne,
+}
+
+impl BinaryOp {
+    /// The operator that behaves the same in the evaluator
+    pub const fn infix_op(self) -> InfixOp {
+        match self {
+            Self::Add => InfixOp::Add,
+            Self::Sub => InfixOp::Subtract,
+            Self::Mul => InfixOp::Multiply,
+            Self::Div => InfixOp::Divide,
+            Self::Pow => InfixOp::Power,
+            Self::Lt => InfixOp::LessThan,
+            Self::Le => InfixOp::LessThanOrEqual,
+            Self::Gt => InfixOp::GreaterThan,
+            Self::Ge => InfixOp::GreaterThanOrEqual,
+            Self::Eq => InfixOp::Equals,
+            Self::Ne => InfixOp::NotEquals,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inst {
+    /// The parameter at the given position, only found in the entry block
+    Param(usize),
+    Const(Constant),
+    Unary(UnaryOp, ValueId),
+    Binary(BinaryOp, ValueId, ValueId),
+    /// Converts an `Int` to a `Float`
+    IntToFloat(ValueId),
+    Call(FunctionId, Vec<ValueId>),
+    /// Builds a struct from its fields in declaration order
+    Struct(TypeId, Vec<ValueId>),
+    Field(ValueId, usize),
+    Variant(TypeId, usize, Vec<ValueId>),
+    /// The index of an enum value's variant, as an `Int`
+    Tag(ValueId),
+    /// A payload value of an enum value that is known to be the given variant
+    Payload(ValueId, usize, usize),
+    /// The value coming in from each predecessor, only found at the start of a block
+    Phi(Vec<(BlockId, ValueId)>),
+}
+
+pub struct Value {
+    pub ty: Type,
+    pub inst: Inst,
+    pub block: BlockId,
+    // where the value comes from, for runtime errors
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: ValueId,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(ValueId),
+    /// Reached when no match arm applies, which the checker rules out
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Self::Jump(target) => vec![*target],
+            Self::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Self::Return(_) | Self::Unreachable => vec![],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Block {
+    /// Instructions in order, phis come first
+    pub insts: Vec<ValueId>,
+    pub terminator: Option<Terminator>,
+}
+
+/// A function in SSA form, every value is defined by exactly one instruction and values are
+/// stored in one table so instructions can refer to them by id
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Type>,
+    pub return_ty: Type,
+    pub blocks: Vec<Block>,
+    pub values: Vec<Value>,
+}
+
+impl Function {
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn value(&self, id: ValueId) -> &Value {
+        &self.values[id.0]
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0]
+    }
+
+    /// The predecessors of every block, in the order their terminators appear
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![vec![]; self.blocks.len()];
+        for (index, block) in self.blocks.iter().enumerate() {
+            if let Some(terminator) = &block.terminator {
+                for successor in terminator.successors() {
+                    if !predecessors[successor.0].contains(&BlockId(index)) {
+                        predecessors[successor.0].push(BlockId(index));
+                    }
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// The one value a phi takes apart from itself, if it only ever takes one
+    pub fn phi_value(&self, phi: ValueId) -> Option<ValueId> {
+        let Inst::Phi(incoming) = &self.value(phi).inst else {
+            return None;
+        };
+        let mut operands = incoming
+            .iter()
+            .map(|(_, operand)| *operand)
+            .filter(|operand| *operand != phi)
+            .collect::<Vec<_>>();
+        operands.sort();
+        operands.dedup();
+        match operands[..] {
+            [operand] => Some(operand),
+            _ => None,
+        }
+    }
+
+    /// Makes everything that reads `from` read `to` instead, returning whether anything did
+    pub fn replace_uses(&mut self, from: ValueId, to: ValueId) -> bool {
+        let mut replaced = false;
+        let mut replace = |value: ValueId| {
+            if value == from {
+                replaced = true;
+                to
+            } else {
+                value
+            }
+        };
+        for value in &mut self.values {
+            value.inst.map_operands(&mut replace);
+        }
+        for block in &mut self.blocks {
+            match &mut block.terminator {
+                Some(Terminator::Branch { condition, .. }) => *condition = replace(*condition),
+                Some(Terminator::Return(value)) => *value = replace(*value),
+                _ => {}
+            }
+        }
+        replaced
+    }
+
+    /// Takes a value out of its block, nothing may read it afterwards
+    pub fn remove_value(&mut self, id: ValueId) {
+        let block = self.values[id.0].block;
+        self.blocks[block.0].insts.retain(|value| *value != id);
+    }
+
+    /// The blocks reachable from the entry, each one after all of its successors except for
+    /// loop back edges
+    pub fn postorder(&self) -> Vec<BlockId> {
+        let mut postorder = vec![];
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack = vec![(Self::ENTRY, 0)];
+        visited[Self::ENTRY.0] = true;
+        while let Some((block, next)) = stack.pop() {
+            let successors = self
+                .block(block)
+                .terminator
+                .iter()
+                .flat_map(Terminator::successors)
+                // visited last to first so that the first successor comes first in reverse
+                .rev()
+                .collect::<Vec<_>>();
+            if let Some(successor) = successors.get(next) {
+                stack.push((block, next + 1));
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((*successor, 0));
+                }
+            } else {
+                postorder.push(block);
+            }
+        }
+        postorder
+    }
+
+    /// Drops the blocks that can't be reached and lays out the rest in reverse postorder, so
+    /// blocks read in the order control flows through them
+    pub fn remove_unreachable_blocks(&mut self) {
+        let order = self.postorder().into_iter().rev().collect::<Vec<_>>();
+        let mut renumbered = vec![None; self.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            renumbered[block.0] = Some(BlockId(index));
+        }
+        let renumber = |block: BlockId| {
+            renumbered[block.0].expect("Successors of reachable blocks are reachable")
+        };
+
+        let mut blocks = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        for id in order {
+            let mut block = blocks[id.0].take().expect("Blocks are visited once");
+            block.terminator = block.terminator.map(|terminator| match terminator {
+                Terminator::Jump(target) => Terminator::Jump(renumber(target)),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => Terminator::Branch {
+                    condition,
+                    then: renumber(then),
+                    otherwise: renumber(otherwise),
+                },
+                terminator => terminator,
+            });
+            for value in &block.insts {
+                let value = &mut self.values[value.0];
+                value.block = renumber(value.block);
+                if let Inst::Phi(incoming) = &mut value.inst {
+                    incoming.retain(|(block, _)| renumbered[block.0].is_some());
+                    for (block, _) in incoming {
+                        *block = renumber(*block);
+                    }
+                }
+            }
+            self.blocks.push(block);
+        }
+    }
+}
+
+/// A lowered module, the function at `MAIN_FUNCTION` runs the top level expressions
+pub struct Module {
+    pub types: Vec<TypeDef>,
+    pub functions: Vec<Function>,
+}
+
+pub const MAIN_FUNCTION: FunctionId = FunctionId(0);
+
+impl Inst {
+    /// The values an instruction reads
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Self::Param(_) | Self::Const(_) => vec![],
+            Self::Unary(_, value)
+            | Self::IntToFloat(value)
+            | Self::Field(value, _)
+            | Self::Tag(value)
+            | Self::Payload(value, _, _) => vec![*value],
+            Self::Binary(_, lh, rh) => vec![*lh, *rh],
+            Self::Call(_, args) | Self::Struct(_, args) | Self::Variant(_, _, args) => args.clone(),
+            Self::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    /// Rewrites the values an instruction reads
+    pub fn map_operands(&mut self, mut f: impl FnMut(ValueId) -> ValueId) {
+        match self {
+            Self::Param(_) | Self::Const(_) => {}
+            Self::Unary(_, value)
+            | Self::IntToFloat(value)
+            | Self::Field(value, _)
+            | Self::Tag(value)
+            | Self::Payload(value, _, _) => *value = f(*value),
+            Self::Binary(_, lh, rh) => {
+                *lh = f(*lh);
+                *rh = f(*rh);
+            }
+            Self::Call(_, args) | Self::Struct(_, args) | Self::Variant(_, _, args) => {
+                for arg in args {
+                    *arg = f(*arg);
+                }
+            }
+            Self::Phi(incoming) => {
+                for (_, value) in incoming {
+                    *value = f(*value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::ir::{BinaryOp, Function, Inst, Terminator, Type, UnaryOp, ValueId, passes::Pass};
+
+/// Removes values that nothing reads, like stores to variables that are never read again.
+/// Instructions that can fail at runtime, and calls, are kept for their effect.
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dead-code-elimination"
+    }
+
+    fn run(&mut self, function: &mut Function) -> bool {
+        let mut live = vec![false; function.values.len()];
+        let mut worklist = vec![];
+        for block in &function.blocks {
+            if let Some(
+                Terminator::Branch {
+                    condition: value, ..
+                }
+                | Terminator::Return(value),
+            ) = &block.terminator
+            {
+                worklist.push(*value);
+            }
+            worklist.extend(
+                block
+                    .insts
+                    .iter()
+                    .filter(|value| has_effect(function, **value)),
+            );
+        }
+        while let Some(value) = worklist.pop() {
+            if !live[value.0] {
+                live[value.0] = true;
+                worklist.extend(function.value(value).inst.operands());
+            }
+        }
+
+        let mut changed = false;
+        for block in &mut function.blocks {
+            let count = block.insts.len();
+            block.insts.retain(|value| live[value.0]);
+            changed |= block.insts.len() != count;
+        }
+        changed
+    }
+}
+
+/// Whether running the instruction can do more than produce its value
+fn has_effect(function: &Function, id: ValueId) -> bool {
+    let value = function.value(id);
+    match &value.inst {
+        // the callee might fail or never return
+        Inst::Call(..) => true,
+        // `Int` arithmetic fails when it overflows
+        Inst::Unary(UnaryOp::Neg, _) => value.ty == Type::Int,
+        Inst::Binary(BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Pow, _, _) => {
+            value.ty == Type::Int
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    evaluating::{operators, value::Value},
+    ir::{Constant, Function, Inst, ValueId, passes::Pass},
+};
+
+/// Computes instructions whose operands are constants, reusing the evaluator's operators so
+/// the results are the same as at runtime. Instructions that would fail, like an overflowing
+/// addition, are left to fail at runtime.
+///
+/// SSA has already replaced variables with the values they hold, so folding also propagates
+/// constants through `::` variables, and through `:=` ones between their assignments
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "constant-folding"
+    }
+
+    fn run(&mut self, function: &mut Function) -> bool {
+        let mut changed = false;
+        // definitions dominate their uses, so visiting in block order folds operands first
+        let values = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.insts.clone())
+            .collect::<Vec<_>>();
+        for id in values {
+            match fold(function, id) {
+                Some(Folded::Constant(constant)) => {
+                    function.values[id.0].inst = Inst::Const(constant);
+                    changed = true;
+                }
+                Some(Folded::Value(value)) => {
+                    function.replace_uses(id, value);
+                    function.remove_value(id);
+                    changed = true;
+                }
+                None => {}
+            }
+        }
+        changed
+    }
+}
+
+enum Folded {
+    Constant(Constant),
+    /// The instruction always produces an existing value
+    Value(ValueId),
+}
+
+fn fold(function: &Function, id: ValueId) -> Option<Folded> {
+    let span = &function.value(id).span;
+    let constant = |value: &ValueId| match function.value(*value).inst {
+        Inst::Const(constant) => Some(constant.value()),
+        _ => None,
+    };
+    let folded = match &function.value(id).inst {
+        Inst::Unary(op, operand) => {
+            operators::prefix(op.prefix_op(), constant(operand)?, span).ok()?
+        }
+        Inst::Binary(op, lh, rh) => {
+            operators::infix(op.infix_op(), &constant(lh)?, &constant(rh)?, span).ok()?
+        }
+        Inst::IntToFloat(operand) => Value::Float(constant(operand)?.as_float()?),
+        // reading back what a struct or variant was built from
+        Inst::Field(target, index) => match &function.value(*target).inst {
+            Inst::Struct(_, fields) => return Some(Folded::Value(fields[*index])),
+            _ => return None,
+        },
+        Inst::Tag(target) => match &function.value(*target).inst {
+            Inst::Variant(_, tag, _) => Value::Int(tag.cast_signed()),
+            _ => return None,
+        },
+        // code that reads the payload of another variant never runs, so it is left alone
+        Inst::Payload(target, tag, index) => match &function.value(*target).inst {
+            Inst::Variant(_, variant, payload) if variant == tag => {
+                return Some(Folded::Value(payload[*index]));
+            }
+            _ => return None,
+        },
+        Inst::Phi(_) => return function.phi_value(id).map(Folded::Value),
+        _ => return None,
+    };
+    Constant::from_value(&folded).map(Folded::Constant)
+}
@@ -0,0 +1,78 @@
+use crate::ir::{Function, Module, verifier};
+
+pub mod dead_code;
+pub mod folding;
+pub mod simplify_cfg;
+
+use dead_code::DeadCodeElimination;
+use folding::ConstantFolding;
+use simplify_cfg::SimplifyCfg;
+
+/// The highest level `-O` accepts
+pub const MAX_OPT_LEVEL: u8 = 2;
+
+// passes enable each other, like folding a condition letting the CFG drop a branch, so the
+// pipeline repeats until it settles but gives up on programs that keep it busy for longer
+const MAX_ROUNDS: usize = 16;
+
+/// A transformation of one function that keeps its behaviour, including its runtime errors
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the function changed
+    fn run(&mut self, function: &mut Function) -> bool;
+}
+
+/// Runs a pipeline of passes over every function of a module
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self { passes: vec![] }
+    }
+
+    /// The pipeline for an `-O` level: nothing at 0, folding and dead code elimination at 1, and
+    /// also control flow simplification at 2 and above
+    pub fn for_level(level: u8) -> Self {
+        let mut manager = Self::new();
+        if level >= 1 {
+            manager.add(ConstantFolding);
+            manager.add(DeadCodeElimination);
+        }
+        if level >= 2 {
+            manager.add(SimplifyCfg);
+        }
+        manager
+    }
+
+    pub fn add(&mut self, pass: impl Pass + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    pub fn run(&mut self, module: &mut Module) {
+        for function in &mut module.functions {
+            for _ in 0..MAX_ROUNDS {
+                let mut changed = false;
+                for pass in &mut self.passes {
+                    changed |= pass.run(function);
+                }
+                if !changed {
+                    break;
+                }
+            }
+        }
+        // a pass that breaks the IR is much easier to find here than in whatever reads it next
+        if cfg!(debug_assertions)
+            && let Err(errors) = verifier::verify(module)
+        {
+            let names = self
+                .passes
+                .iter()
+                .map(|pass| pass.name())
+                .collect::<Vec<_>>();
+            panic!("Passes {names:?} produced invalid IR: {errors:#?}");
+        }
+    }
+}
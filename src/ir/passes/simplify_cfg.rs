@@ -0,0 +1,166 @@
+use crate::ir::{BlockId, Constant, Function, Inst, Terminator, passes::Pass};
+
+/// Simplifies the control flow graph: branches on constants become jumps, blocks that only
+/// jump somewhere else are skipped, straight line blocks are merged and blocks that can no
+/// longer be reached are removed
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run(&mut self, function: &mut Function) -> bool {
+        let count = function.blocks.len();
+        let mut changed = simplify_branches(function);
+        function.remove_unreachable_blocks();
+        while let Some(block) = find_forwarding_block(function) {
+            skip_block(function, block);
+            function.remove_unreachable_blocks();
+            changed = true;
+        }
+        while let Some(block) = find_mergeable_block(function) {
+            merge_into_predecessor(function, block);
+            function.remove_unreachable_blocks();
+            changed = true;
+        }
+        changed || function.blocks.len() != count
+    }
+}
+
+/// Turns branches whose condition is a constant, or whose targets are the same, into jumps
+fn simplify_branches(function: &mut Function) -> bool {
+    let mut changed = false;
+    for index in 0..function.blocks.len() {
+        let Some(Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        }) = function.blocks[index].terminator
+        else {
+            continue;
+        };
+        let (target, dropped) = match function.value(condition).inst {
+            _ if then == otherwise => (then, None),
+            Inst::Const(Constant::Bool(true)) => (then, Some(otherwise)),
+            Inst::Const(Constant::Bool(false)) => (otherwise, Some(then)),
+            _ => continue,
+        };
+        function.blocks[index].terminator = Some(Terminator::Jump(target));
+        if let Some(dropped) = dropped {
+            remove_incoming(function, dropped, BlockId(index));
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// A block that does nothing but jump to another one, which its predecessors can jump to
+/// directly. Predecessors that already jump to that block would need two values in its phis,
+/// so those blocks stay.
+fn find_forwarding_block(function: &Function) -> Option<BlockId> {
+    let predecessors = function.predecessors();
+    (1..function.blocks.len()).map(BlockId).find(|block| {
+        let data = function.block(*block);
+        let Some(Terminator::Jump(target)) = data.terminator else {
+            return false;
+        };
+        let target_has_phis = function
+            .block(target)
+            .insts
+            .first()
+            .is_some_and(|value| matches!(function.value(*value).inst, Inst::Phi(_)));
+        data.insts.is_empty()
+            && target != *block
+            && !predecessors[block.0].is_empty()
+            && !(target_has_phis
+                && predecessors[block.0]
+                    .iter()
+                    .any(|predecessor| predecessors[target.0].contains(predecessor)))
+    })
+}
+
+fn skip_block(function: &mut Function, block: BlockId) {
+    let Some(Terminator::Jump(target)) = function.block(block).terminator else {
+        return;
+    };
+    let predecessors = function.predecessors()[block.0].clone();
+    for predecessor in &predecessors {
+        let retarget = |successor: &mut BlockId| {
+            if *successor == block {
+                *successor = target;
+            }
+        };
+        match &mut function.blocks[predecessor.0].terminator {
+            Some(Terminator::Jump(successor)) => retarget(successor),
+            Some(Terminator::Branch {
+                then, otherwise, ..
+            }) => {
+                retarget(then);
+                retarget(otherwise);
+            }
+            _ => {}
+        }
+    }
+    // the values that came from the skipped block now come from each of its predecessors
+    for value in function.blocks[target.0].insts.clone() {
+        if let Inst::Phi(incoming) = &mut function.values[value.0].inst
+            && let Some(position) = incoming.iter().position(|(from, _)| *from == block)
+        {
+            let (_, value) = incoming.remove(position);
+            incoming.extend(predecessors.iter().map(|predecessor| (*predecessor, value)));
+        }
+    }
+}
+
+/// A block whose only predecessor jumps to it unconditionally, so the two always run together
+fn find_mergeable_block(function: &Function) -> Option<BlockId> {
+    let predecessors = function.predecessors();
+    (1..function.blocks.len()).map(BlockId).find(|block| {
+        matches!(predecessors[block.0][..], [predecessor]
+            if predecessor != *block
+                && function.block(predecessor).terminator == Some(Terminator::Jump(*block)))
+    })
+}
+
+fn merge_into_predecessor(function: &mut Function, block: BlockId) {
+    let predecessor = function.predecessors()[block.0][0];
+    // with a single predecessor every phi has one value
+    for value in function.block(block).insts.clone() {
+        if let Inst::Phi(incoming) = &function.value(value).inst {
+            let (_, operand) = incoming[0];
+            function.replace_uses(value, operand);
+            function.remove_value(value);
+        }
+    }
+
+    let insts = std::mem::take(&mut function.blocks[block.0].insts);
+    for value in &insts {
+        function.values[value.0].block = predecessor;
+    }
+    function.blocks[predecessor.0].insts.extend(insts);
+    let terminator = function.blocks[block.0]
+        .terminator
+        .replace(Terminator::Unreachable);
+    for successor in terminator.iter().flat_map(Terminator::successors) {
+        for value in &function.blocks[successor.0].insts {
+            if let Inst::Phi(incoming) = &mut function.values[value.0].inst {
+                for (from, _) in incoming {
+                    if *from == block {
+                        *from = predecessor;
+                    }
+                }
+            }
+        }
+    }
+    function.blocks[predecessor.0].terminator = terminator;
+}
+
+/// Forgets the values a block's phis take when coming from a block that no longer jumps to it
+fn remove_incoming(function: &mut Function, block: BlockId, from: BlockId) {
+    for value in &function.blocks[block.0].insts {
+        if let Inst::Phi(incoming) = &mut function.values[value.0].inst {
+            incoming.retain(|(predecessor, _)| *predecessor != from);
+        }
+    }
+}
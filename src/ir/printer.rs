@@ -0,0 +1,291 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::ir::{
+    BinaryOp, BlockId, Constant, Function, Inst, Module, Terminator, Type, TypeDefKind, TypeId,
+    UnaryOp, ValueId,
+};
+
+/// Renders a module as text, values are numbered in the order they appear in each function
+pub fn print(module: &Module) -> String {
+    let printer = Printer {
+        module,
+        type_names: unique(module.types.iter().map(|def| def.name.as_str())),
+        function_names: unique(
+            module
+                .functions
+                .iter()
+                .map(|function| function.name.as_str()),
+        ),
+    };
+    let mut items = vec![];
+    for (index, def) in module.types.iter().enumerate() {
+        let body = match &def.kind {
+            TypeDefKind::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, ty)| format!("{name}: {}", printer.ty(*ty)))
+                    .collect::<Vec<_>>();
+                format!("struct {{ {} }}", fields.join(", "))
+            }
+            TypeDefKind::Enum(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|(name, payload)| {
+                        if payload.is_empty() {
+                            name.clone()
+                        } else {
+                            let payload =
+                                payload.iter().map(|ty| printer.ty(*ty)).collect::<Vec<_>>();
+                            format!("{name}({})", payload.join(", "))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("enum {{ {} }}", variants.join(", "))
+            }
+        };
+        items.push(format!("type {} = {body}\n", printer.type_names[index]));
+    }
+    for (index, function) in module.functions.iter().enumerate() {
+        items.push(printer.function(index, function));
+    }
+    items.join("\n")
+}
+
+/// Names that are shadowed in the source get a numeric suffix to tell them apart
+fn unique<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    names
+        .map(|name| {
+            let count = seen.entry(name).or_default();
+            *count += 1;
+            if *count == 1 {
+                name.to_string()
+            } else {
+                format!("{name}.{}", *count - 1)
+            }
+        })
+        .collect()
+}
+
+struct Printer<'a> {
+    module: &'a Module,
+    type_names: Vec<String>,
+    function_names: Vec<String>,
+}
+
+impl Printer<'_> {
+    fn function(&self, index: usize, function: &Function) -> String {
+        // parameters are numbered first since the signature shows them
+        let mut numbers = HashMap::new();
+        let values = function.blocks.iter().flat_map(|block| &block.insts);
+        for value in values.clone() {
+            if matches!(function.value(*value).inst, Inst::Param(_)) {
+                numbers.insert(*value, numbers.len());
+            }
+        }
+        for value in values {
+            if !numbers.contains_key(value) {
+                numbers.insert(*value, numbers.len());
+            }
+        }
+        let name = |value: &ValueId| {
+            numbers
+                .get(value)
+                .map_or_else(|| format!("%?{}", value.0), |number| format!("%{number}"))
+        };
+
+        let mut params = vec![None; function.params.len()];
+        for value in &function.block(Function::ENTRY).insts {
+            if let Inst::Param(position) = function.value(*value).inst {
+                params[position] = Some(*value);
+            }
+        }
+        let params = params
+            .iter()
+            .zip(&function.params)
+            .map(|(value, ty)| {
+                let value = value.map_or_else(|| "_".to_string(), |value| name(&value));
+                format!("{value}: {}", self.ty(*ty))
+            })
+            .collect::<Vec<_>>();
+
+        let mut output = format!(
+            "fn {}({}) -> {} {{\n",
+            self.function_names[index],
+            params.join(", "),
+            self.ty(function.return_ty)
+        );
+        for (index, block) in function.blocks.iter().enumerate() {
+            writeln!(output, "{}:", block_name(BlockId(index))).unwrap();
+            for value in &block.insts {
+                let value_data = function.value(*value);
+                let Some(inst) = self.inst(function, &value_data.inst, &name) else {
+                    continue;
+                };
+                writeln!(
+                    output,
+                    "    {}: {} = {inst}",
+                    name(value),
+                    self.ty(value_data.ty)
+                )
+                .unwrap();
+            }
+            let terminator = terminator_text(block.terminator.as_ref(), &name);
+            writeln!(output, "    {terminator}").unwrap();
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    fn inst(
+        &self,
+        function: &Function,
+        inst: &Inst,
+        name: &impl Fn(&ValueId) -> String,
+    ) -> Option<String> {
+        Some(match inst {
+            // parameters are shown in the signature
+            Inst::Param(_) => return None,
+            Inst::Const(constant) => format!("const {}", constant_text(*constant)),
+            Inst::Unary(op, operand) => {
+                let op = match op {
+                    UnaryOp::Not => "not",
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::BitNot => "bitnot",
+                };
+                format!("{op} {}", name(operand))
+            }
+            Inst::Binary(op, lh, rh) => {
+                format!("{} {}, {}", binary_name(*op), name(lh), name(rh))
+            }
+            Inst::IntToFloat(operand) => format!("itof {}", name(operand)),
+            Inst::Call(callee, args) => {
+                let args = args.iter().map(name).collect::<Vec<_>>();
+                format!(
+                    "call {}({})",
+                    self.function_names[callee.0],
+                    args.join(", ")
+                )
+            }
+            Inst::Struct(ty, fields) => {
+                let fields = fields.iter().map(name).collect::<Vec<_>>();
+                format!(
+                    "struct {} {{ {} }}",
+                    self.type_names[ty.0],
+                    fields.join(", ")
+                )
+            }
+            Inst::Field(target, index) => {
+                let field = match function.value(*target).ty {
+                    Type::Struct(ty) => match &self.module.types[ty.0].kind {
+                        TypeDefKind::Struct(fields) => {
+                            fields.get(*index).map(|(name, _)| name.clone())
+                        }
+                        TypeDefKind::Enum(_) => None,
+                    },
+                    _ => None,
+                };
+                format!(
+                    "field {}, {}",
+                    name(target),
+                    field.unwrap_or_else(|| index.to_string())
+                )
+            }
+            Inst::Variant(ty, tag, payload) => {
+                let variant = self.variant(*ty, *tag);
+                if payload.is_empty() {
+                    format!("variant {variant}")
+                } else {
+                    let payload = payload.iter().map(name).collect::<Vec<_>>();
+                    format!("variant {variant}({})", payload.join(", "))
+                }
+            }
+            Inst::Tag(target) => format!("tag {}", name(target)),
+            Inst::Payload(target, tag, index) => {
+                let variant = match function.value(*target).ty {
+                    Type::Enum(ty) => self.variant(ty, *tag),
+                    _ => tag.to_string(),
+                };
+                format!("payload {}, {variant}.{index}", name(target))
+            }
+            Inst::Phi(incoming) => {
+                let incoming = incoming
+                    .iter()
+                    .map(|(block, value)| format!("[{}: {}]", block_name(*block), name(value)))
+                    .collect::<Vec<_>>();
+                format!("phi {}", incoming.join(", "))
+            }
+        })
+    }
+
+    fn ty(&self, ty: Type) -> String {
+        match ty {
+            Type::Unit => "Unit".to_string(),
+            Type::Int => "Int".to_string(),
+            Type::Float => "Float".to_string(),
+            Type::Bool => "Bool".to_string(),
+            Type::Struct(id) | Type::Enum(id) => self.type_names[id.0].clone(),
+        }
+    }
+
+    fn variant(&self, ty: TypeId, tag: usize) -> String {
+        let name = match &self.module.types[ty.0].kind {
+            TypeDefKind::Enum(variants) => variants.get(tag).map(|(name, _)| name.clone()),
+            TypeDefKind::Struct(_) => None,
+        };
+        format!(
+            "{}.{}",
+            self.type_names[ty.0],
+            name.unwrap_or_else(|| tag.to_string())
+        )
+    }
+}
+
+fn block_name(block: BlockId) -> String {
+    format!("bb{}", block.0)
+}
+
+fn terminator_text(terminator: Option<&Terminator>, name: &impl Fn(&ValueId) -> String) -> String {
+    match terminator {
+        Some(Terminator::Jump(target)) => format!("jump {}", block_name(*target)),
+        Some(Terminator::Branch {
+            condition,
+            then,
+            otherwise,
+        }) => format!(
+            "br {}, {}, {}",
+            name(condition),
+            block_name(*then),
+            block_name(*otherwise)
+        ),
+        Some(Terminator::Return(value)) => format!("return {}", name(value)),
+        Some(Terminator::Unreachable) => "unreachable".to_string(),
+        None => "<no terminator>".to_string(),
+    }
+}
+
+fn constant_text(constant: Constant) -> String {
+    match constant {
+        Constant::Unit => "()".to_string(),
+        Constant::Int(value) => value.to_string(),
+        // debug formatting keeps the `.0` on whole floats so they don't read as ints
+        Constant::Float(value) => format!("{value:?}"),
+        Constant::Bool(value) => value.to_string(),
+    }
+}
+
+const fn binary_name(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Sub => "sub",
+        BinaryOp::Mul => "mul",
+        BinaryOp::Div => "div",
+        BinaryOp::Pow => "pow",
+        BinaryOp::Lt => "lt",
+        BinaryOp::Le => "le",
+        BinaryOp::Gt => "gt",
+        BinaryOp::Ge => "ge",
+        BinaryOp::Eq => "eq",
+        BinaryOp::Ne => "ne",
+    }
+}
@@ -0,0 +1,396 @@
+use crate::ir::{
+    BinaryOp, BlockId, Constant, Function, Inst, Module, Terminator, Type, TypeDefKind, UnaryOp,
+    ValueId, errors::VerifyError,
+};
+
+/// Checks the invariants the rest of the compiler relies on: every block ends in a terminator,
+/// phis match the predecessors of their block, definitions dominate their uses and operands
+/// have the types their instructions expect
+pub fn verify(module: &Module) -> Result<(), Vec<VerifyError>> {
+    let mut errors = vec![];
+    for function in &module.functions {
+        Verifier {
+            module,
+            function,
+            errors: &mut errors,
+        }
+        .verify();
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Where each value is defined, as its block and position in the block
+type Definitions = Vec<Option<(BlockId, usize)>>;
+
+struct Verifier<'a> {
+    module: &'a Module,
+    function: &'a Function,
+    errors: &'a mut Vec<VerifyError>,
+}
+
+impl Verifier<'_> {
+    fn verify(&mut self) {
+        let Some(definitions) = self.definitions() else {
+            return;
+        };
+        if !self.verify_terminators() {
+            return;
+        }
+        let predecessors = self.function.predecessors();
+        let dominators = dominators(self.function, &predecessors);
+
+        for (index, block) in self.function.blocks.iter().enumerate() {
+            let id = BlockId(index);
+            let mut phis_done = false;
+            for (position, value) in block.insts.iter().enumerate() {
+                let inst = &self.function.value(*value).inst;
+                if let Inst::Phi(incoming) = inst {
+                    if phis_done {
+                        self.error(
+                            Some(id),
+                            format!("{} is a phi after other instructions", name(*value)),
+                        );
+                    }
+                    let mut from = incoming.iter().map(|(block, _)| *block).collect::<Vec<_>>();
+                    from.sort();
+                    let mut expected = predecessors[index].clone();
+                    expected.sort();
+                    if from != expected {
+                        self.error(
+                            Some(id),
+                            format!(
+                                "{} doesn't have one value for every predecessor",
+                                name(*value)
+                            ),
+                        );
+                    }
+                    for (block, operand) in incoming {
+                        // the value has to be available at the end of the predecessor
+                        let available = definitions[operand.0]
+                            .is_some_and(|(defined, _)| dominates(&dominators, defined, *block));
+                        if !available {
+                            self.error(
+                                Some(id),
+                                format!(
+                                    "{} isn't available at the end of bb{} for {}",
+                                    name(*operand),
+                                    block.0,
+                                    name(*value)
+                                ),
+                            );
+                        }
+                    }
+                } else {
+                    phis_done = true;
+                    for operand in inst.operands() {
+                        let available = definitions[operand.0].is_some_and(|(defined, at)| {
+                            if defined == id {
+                                at < position
+                            } else {
+                                dominates(&dominators, defined, id)
+                            }
+                        });
+                        if !available {
+                            self.error(
+                                Some(id),
+                                format!(
+                                    "{} is used by {} before it is defined",
+                                    name(operand),
+                                    name(*value)
+                                ),
+                            );
+                        }
+                    }
+                }
+                self.verify_types(id, *value);
+            }
+
+            let used = match &block.terminator {
+                Some(Terminator::Branch { condition, .. }) => Some(*condition),
+                Some(Terminator::Return(value)) => Some(*value),
+                _ => None,
+            };
+            if let Some(used) = used {
+                let available = definitions[used.0]
+                    .is_some_and(|(defined, _)| dominates(&dominators, defined, id));
+                if !available {
+                    self.error(
+                        Some(id),
+                        format!(
+                            "{} is used by the terminator before it is defined",
+                            name(used)
+                        ),
+                    );
+                }
+            }
+            match &block.terminator {
+                Some(Terminator::Branch { condition, .. }) => {
+                    self.expect_type(id, *condition, Type::Bool, "branch condition");
+                }
+                Some(Terminator::Return(value)) => {
+                    self.expect_type(id, *value, self.function.return_ty, "return value");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Finds where every value is defined, checking that each one is defined exactly once
+    fn definitions(&mut self) -> Option<Definitions> {
+        let mut definitions = vec![None; self.function.values.len()];
+        let mut valid = true;
+        for (index, block) in self.function.blocks.iter().enumerate() {
+            for (position, value) in block.insts.iter().enumerate() {
+                let Some(definition) = definitions.get_mut(value.0) else {
+                    self.error(
+                        Some(BlockId(index)),
+                        format!("{} doesn't exist", name(*value)),
+                    );
+                    valid = false;
+                    continue;
+                };
+                if definition.is_some() {
+                    self.error(
+                        Some(BlockId(index)),
+                        format!("{} is defined twice", name(*value)),
+                    );
+                    valid = false;
+                }
+                *definition = Some((BlockId(index), position));
+                if self.function.value(*value).block != BlockId(index) {
+                    self.error(
+                        Some(BlockId(index)),
+                        format!("{} is recorded in another block", name(*value)),
+                    );
+                }
+                let operands = self.function.value(*value).inst.operands();
+                if operands
+                    .iter()
+                    .any(|operand| operand.0 >= self.function.values.len())
+                {
+                    self.error(
+                        Some(BlockId(index)),
+                        format!("{} reads a value that doesn't exist", name(*value)),
+                    );
+                    valid = false;
+                }
+            }
+        }
+        valid.then_some(definitions)
+    }
+
+    fn verify_terminators(&mut self) -> bool {
+        let mut valid = true;
+        for (index, block) in self.function.blocks.iter().enumerate() {
+            let Some(terminator) = &block.terminator else {
+                self.error(Some(BlockId(index)), "block has no terminator".to_string());
+                valid = false;
+                continue;
+            };
+            if terminator
+                .successors()
+                .iter()
+                .any(|target| target.0 >= self.function.blocks.len())
+            {
+                self.error(
+                    Some(BlockId(index)),
+                    "jump to a block that doesn't exist".to_string(),
+                );
+                valid = false;
+            }
+            if let Terminator::Branch {
+                condition: value, ..
+            }
+            | Terminator::Return(value) = terminator
+                && value.0 >= self.function.values.len()
+            {
+                self.error(
+                    Some(BlockId(index)),
+                    format!("{} doesn't exist", name(*value)),
+                );
+                valid = false;
+            }
+        }
+        valid
+    }
+
+    fn verify_types(&mut self, block: BlockId, id: ValueId) {
+        let value = self.function.value(id);
+        let ty = value.ty;
+        let ty_of = |operand: &ValueId| self.function.value(*operand).ty;
+        let valid = match &value.inst {
+            Inst::Param(index) => self.function.params.get(*index) == Some(&ty),
+            Inst::Const(constant) => {
+                ty == match constant {
+                    Constant::Unit => Type::Unit,
+                    Constant::Int(_) => Type::Int,
+                    Constant::Float(_) => Type::Float,
+                    Constant::Bool(_) => Type::Bool,
+                }
+            }
+            Inst::Unary(op, operand) => {
+                ty == ty_of(operand)
+                    && match op {
+                        UnaryOp::Not => ty == Type::Bool,
+                        UnaryOp::Neg => matches!(ty, Type::Int | Type::Float),
+                        UnaryOp::BitNot => ty == Type::Int,
+                    }
+            }
+            Inst::Binary(op, lh, rh) => {
+                let operands = ty_of(lh);
+                operands == ty_of(rh)
+                    && match op {
+                        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Pow => {
+                            ty == operands && matches!(ty, Type::Int | Type::Float)
+                        }
+                        BinaryOp::Div => ty == Type::Float && operands == Type::Float,
+                        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                            ty == Type::Bool && matches!(operands, Type::Int | Type::Float)
+                        }
+                        BinaryOp::Eq | BinaryOp::Ne => ty == Type::Bool,
+                    }
+            }
+            Inst::IntToFloat(operand) => ty == Type::Float && ty_of(operand) == Type::Int,
+            Inst::Call(callee, args) => self.module.functions.get(callee.0).is_some_and(|callee| {
+                callee.return_ty == ty
+                    && callee.params.len() == args.len()
+                    && callee
+                        .params
+                        .iter()
+                        .zip(args)
+                        .all(|(param, arg)| *param == ty_of(arg))
+            }),
+            Inst::Struct(def, fields) => {
+                ty == Type::Struct(*def)
+                    && matches!(&self.module.types[def.0].kind, TypeDefKind::Struct(layout)
+                        if layout.len() == fields.len()
+                            && layout.iter().zip(fields).all(|((_, field_ty), field)| *field_ty == ty_of(field)))
+            }
+            Inst::Field(target, index) => match ty_of(target) {
+                Type::Struct(def) => {
+                    matches!(&self.module.types[def.0].kind, TypeDefKind::Struct(layout)
+                    if layout.get(*index).is_some_and(|(_, field_ty)| *field_ty == ty))
+                }
+                _ => false,
+            },
+            Inst::Variant(def, tag, payload) => {
+                ty == Type::Enum(*def)
+                    && self.payload(ty, *tag).is_some_and(|types| {
+                        types.len() == payload.len()
+                            && types
+                                .iter()
+                                .zip(payload)
+                                .all(|(arg_ty, arg)| *arg_ty == ty_of(arg))
+                    })
+            }
+            Inst::Tag(target) => ty == Type::Int && matches!(ty_of(target), Type::Enum(_)),
+            Inst::Payload(target, tag, index) => self
+                .payload(ty_of(target), *tag)
+                .is_some_and(|types| types.get(*index) == Some(&ty)),
+            Inst::Phi(incoming) => incoming.iter().all(|(_, operand)| ty_of(operand) == ty),
+        };
+        if !valid {
+            self.error(
+                Some(block),
+                format!("{} has operands of the wrong type", name(id)),
+            );
+        }
+    }
+
+    fn payload(&self, ty: Type, tag: usize) -> Option<&Vec<Type>> {
+        let Type::Enum(def) = ty else {
+            return None;
+        };
+        match &self.module.types[def.0].kind {
+            TypeDefKind::Enum(variants) => variants.get(tag).map(|(_, payload)| payload),
+            TypeDefKind::Struct(_) => None,
+        }
+    }
+
+    fn expect_type(&mut self, block: BlockId, value: ValueId, expected: Type, what: &str) {
+        if self.function.value(value).ty != expected {
+            self.error(
+                Some(block),
+                format!("{what} {} has the wrong type", name(value)),
+            );
+        }
+    }
+
+    fn error(&mut self, block: Option<BlockId>, message: String) {
+        self.errors
+            .push(VerifyError::new(&self.function.name, block, message));
+    }
+}
+
+fn name(value: ValueId) -> String {
+    format!("%{}", value.0)
+}
+
+/// The immediate dominator of every reachable block, following "A Simple, Fast Dominance
+/// Algorithm" by Cooper, Harvey and Kennedy
+fn dominators(function: &Function, predecessors: &[Vec<BlockId>]) -> Vec<Option<BlockId>> {
+    let postorder = function.postorder();
+    let mut order = vec![usize::MAX; function.blocks.len()];
+    for (index, block) in postorder.iter().enumerate() {
+        order[block.0] = index;
+    }
+
+    let mut idoms = vec![None; function.blocks.len()];
+    idoms[Function::ENTRY.0] = Some(Function::ENTRY);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in postorder.iter().rev().skip(1) {
+            let mut idom: Option<BlockId> = None;
+            for predecessor in &predecessors[block.0] {
+                if idoms[predecessor.0].is_none() {
+                    continue;
+                }
+                idom = Some(idom.map_or(*predecessor, |idom| {
+                    intersect(&idoms, &order, idom, *predecessor)
+                }));
+            }
+            if idom.is_some() && idoms[block.0] != idom {
+                idoms[block.0] = idom;
+                changed = true;
+            }
+        }
+    }
+    idoms
+}
+
+/// The closest common dominator of two blocks, walking up the tree from the later one in
+/// postorder until both meet
+fn intersect(
+    idoms: &[Option<BlockId>],
+    order: &[usize],
+    mut a: BlockId,
+    mut b: BlockId,
+) -> BlockId {
+    while a != b {
+        while order[a.0] < order[b.0] {
+            a = idoms[a.0].expect("Processed blocks have a dominator");
+        }
+        while order[b.0] < order[a.0] {
+            b = idoms[b.0].expect("Processed blocks have a dominator");
+        }
+    }
+    a
+}
+
+/// Whether every path from the entry to `block` passes through `dominator`
+fn dominates(idoms: &[Option<BlockId>], dominator: BlockId, mut block: BlockId) -> bool {
+    loop {
+        if block == dominator {
+            return true;
+        }
+        match idoms[block.0] {
+            Some(idom) if idom != block => block = idom,
+            _ => return false,
+        }
+    }
+}
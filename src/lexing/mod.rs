@@ -1,5 +1,6 @@
 use std::{
-    iter::{Enumerate, Peekable},
+    iter::{Peekable, Zip},
+    ops::RangeFrom,
     str::Chars,
 };
 
@@ -11,17 +12,34 @@ pub mod token;
 
 pub struct Lexer<'a> {
     module: &'a Module,
-    char_peeker: Peekable<Enumerate<Chars<'a>>>,
+    // chars paired with their offset in the program
+    char_peeker: Peekable<Zip<RangeFrom<usize>, Chars<'a>>>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(module: &'a Module) -> Self {
         Self {
             module: module,
-            char_peeker: module.iter().enumerate().peekable(),
+            char_peeker: (module.offset..).zip(module.iter()).peekable(),
         }
     }
 
+    /// Lexes `module` from a char offset on, for sources that grow like the REPL's session
+    pub fn starting_at(module: &'a Module, offset: usize) -> Self {
+        let mut chars = (module.offset..).zip(module.iter());
+        if offset > module.offset {
+            chars.nth(offset - module.offset - 1);
+        }
+        Self {
+            module,
+            char_peeker: chars.peekable(),
+        }
+    }
+
+    pub const fn module(&self) -> &'a Module {
+        self.module
+    }
+
     fn consume(&self, kind: TokenKind, cur_idx: usize, size: usize) -> Token {
         Token::new(kind, &self.module.slice(cur_idx, cur_idx + size), cur_idx)
     }
@@ -102,6 +120,8 @@ impl<'a> Iterator for Lexer<'a> {
                 self.char_peeker.next();
                 if self.char_peeker.peek().is_some_and(|(_, c)| c == &'=') {
                     self.consume(DoubleEquals, cur_idx, 2)
+                } else if self.char_peeker.peek().is_some_and(|(_, c)| c == &'>') {
+                    self.consume(FatArrow, cur_idx, 2)
                 } else {
                     call_next = false;
                     self.consume(Equals, cur_idx, 1)
@@ -135,6 +155,26 @@ impl<'a> Iterator for Lexer<'a> {
                 }
             }
 
+            '&' => {
+                self.char_peeker.next();
+                if self.char_peeker.peek().is_some_and(|(_, c)| c == &'&') {
+                    self.consume(DoubleAmpersand, cur_idx, 2)
+                } else {
+                    call_next = false;
+                    self.consume(Illegal, cur_idx, 1)
+                }
+            }
+            '|' => {
+                self.char_peeker.next();
+                if self.char_peeker.peek().is_some_and(|(_, c)| c == &'|') {
+                    self.consume(DoublePipe, cur_idx, 2)
+                } else {
+                    call_next = false;
+                    self.consume(Illegal, cur_idx, 1)
+                }
+            }
+            '~' => self.consume(Tilde, cur_idx, 1),
+
             '{' => self.consume(LBrace, cur_idx, 1),
             '}' => self.consume(RBrace, cur_idx, 1),
             '(' => self.consume(LParen, cur_idx, 1),
@@ -158,6 +198,18 @@ impl<'a> Iterator for Lexer<'a> {
                     "true" => self.consume(Boolean, cur_idx, 4),
                     "false" => self.consume(Boolean, cur_idx, 5),
                     "type" => self.consume(Type, cur_idx, 4),
+                    "struct" => self.consume(Struct, cur_idx, 6),
+                    "enum" => self.consume(Enum, cur_idx, 4),
+                    "match" => self.consume(Match, cur_idx, 5),
+                    "fn" => self.consume(Fn, cur_idx, 2),
+                    "if" => self.consume(If, cur_idx, 2),
+                    "else" => self.consume(Else, cur_idx, 4),
+                    "while" => self.consume(While, cur_idx, 5),
+                    "loop" => self.consume(Loop, cur_idx, 4),
+                    "break" => self.consume(Break, cur_idx, 5),
+                    "continue" => self.consume(Continue, cur_idx, 8),
+                    "import" => self.consume(Import, cur_idx, 6),
+                    "pub" => self.consume(Pub, cur_idx, 3),
                     _ => self.consume(Ident, cur_idx, chars.len()),
                 }
             }
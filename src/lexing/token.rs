@@ -21,12 +21,16 @@ pub enum TokenKind {
     GreaterThanOrEqual,
     DoubleEquals,
     NotEquals,
+    DoubleAmpersand,
+    DoublePipe,
 
     LBrace,
     RBrace,
     Exclamation,
+    Tilde,
     Colon,
     Equals,
+    FatArrow,
     Dot,
     LParen,
     RParen,
@@ -36,6 +40,18 @@ pub enum TokenKind {
     Boolean,
     Ident,
     Type,
+    Struct,
+    Enum,
+    Match,
+    Fn,
+    If,
+    Else,
+    While,
+    Loop,
+    Break,
+    Continue,
+    Import,
+    Pub,
 
     Comment,
     Illegal,
@@ -51,3 +67,53 @@ impl Token {
         }
     }
 }
+
+impl TokenKind {
+    pub const fn describe(&self) -> &'static str {
+        match self {
+            Self::Plus => "`+`",
+            Self::Minus => "`-`",
+            Self::Asterisk => "`*`",
+            Self::Slash => "`/`",
+            Self::Caret => "`^`",
+            Self::LessThan => "`<`",
+            Self::LessThanOrEqual => "`<=`",
+            Self::GreaterThan => "`>`",
+            Self::GreaterThanOrEqual => "`>=`",
+            Self::DoubleEquals => "`==`",
+            Self::NotEquals => "`!=`",
+            Self::DoubleAmpersand => "`&&`",
+            Self::DoublePipe => "`||`",
+            Self::LBrace => "`{`",
+            Self::RBrace => "`}`",
+            Self::Exclamation => "`!`",
+            Self::Tilde => "`~`",
+            Self::Colon => "`:`",
+            Self::Equals => "`=`",
+            Self::FatArrow => "`=>`",
+            Self::Dot => "`.`",
+            Self::LParen => "`(`",
+            Self::RParen => "`)`",
+            Self::Comma => "`,`",
+            Self::Number => "a number",
+            Self::Boolean => "a boolean",
+            Self::Ident => "an identifier",
+            Self::Type => "`type`",
+            Self::Struct => "`struct`",
+            Self::Enum => "`enum`",
+            Self::Match => "`match`",
+            Self::Fn => "`fn`",
+            Self::If => "`if`",
+            Self::Else => "`else`",
+            Self::While => "`while`",
+            Self::Loop => "`loop`",
+            Self::Break => "`break`",
+            Self::Continue => "`continue`",
+            Self::Import => "`import`",
+            Self::Pub => "`pub`",
+            Self::Comment => "a comment",
+            Self::Illegal => "an illegal character",
+            Self::Eof => "end of file",
+        }
+    }
+}
@@ -1,14 +1,25 @@
 #![allow(dead_code)]
 
+use std::process::ExitCode;
+
+use clap::Parser;
+
+mod bytecode;
 mod checking;
+mod cli;
 mod common;
+mod diagnostics;
+mod emitting;
+mod evaluating;
+mod ir;
 mod lexing;
 mod module;
 mod parsing;
+mod repl;
 
 #[cfg(test)]
 mod tests;
 
-fn main() {
-    println!("Hello, world!");
+fn main() -> ExitCode {
+    cli::commands::run(cli::Cli::parse())
 }
@@ -0,0 +1,234 @@
+use std::{
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+/// The file that marks a directory as a project
+pub const MANIFEST_FILE_NAME: &str = "alloy.toml";
+
+/// A project's `alloy.toml`:
+///
+/// ```toml
+/// [package]
+/// name = "hello"
+/// version = "0.1.0"
+/// edition = "2025"        # optional, the latest edition by default
+/// entry = "main.alloy"    # optional, relative to `source`
+/// source = "src"          # optional, relative to the manifest, `.` by default
+/// features = ["feature"]  # optional
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Version,
+    pub edition: Edition,
+    pub entry: PathBuf,
+    pub source: PathBuf,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A revision of the language a project is written against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edition {
+    E2025,
+}
+
+impl Edition {
+    pub const LATEST: Self = Self::E2025;
+    const ALL: [(Self, &str); 1] = [(Self::E2025, "2025")];
+}
+
+const PACKAGE_FIELDS: [&str; 6] = ["name", "version", "edition", "entry", "source", "features"];
+
+impl Manifest {
+    /// Reads the manifest of a project directory, if it has one
+    pub fn find(dir: &Path) -> Result<Option<Self>, String> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let src = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        Self::parse(&src)
+            .map(Some)
+            .map_err(|e| format!("invalid manifest {}: {e}", path.display()))
+    }
+
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let table = src
+            .parse::<Table>()
+            .map_err(|e| e.to_string().trim_end().to_string())?;
+        let package = match table.get("package") {
+            Some(Value::Table(package)) => package,
+            Some(value) => {
+                return Err(format!(
+                    "`package` should be a table, found {}",
+                    value.type_str()
+                ));
+            }
+            None => return Err("missing section `[package]`".to_string()),
+        };
+        if let Some(key) = table.keys().find(|key| *key != "package") {
+            return Err(format!(
+                "unknown key `{key}`, expected only a `[package]` section"
+            ));
+        }
+        if let Some(key) = package
+            .keys()
+            .find(|key| !PACKAGE_FIELDS.contains(&key.as_str()))
+        {
+            return Err(format!(
+                "unknown field `package.{key}`, expected one of {}",
+                PACKAGE_FIELDS.map(|field| format!("`{field}`")).join(", ")
+            ));
+        }
+
+        let name = string(package, "name")?.ok_or("missing field `package.name`")?;
+        if !is_valid_name(name) {
+            return Err(format!(
+                "invalid `package.name` \"{name}\", expected letters, digits, `_` or `-`, \
+                 starting with a letter"
+            ));
+        }
+        let version = string(package, "version")?.ok_or("missing field `package.version`")?;
+        let version = parse_version(version).ok_or_else(|| {
+            format!("invalid `package.version` \"{version}\", expected a version like \"0.1.0\"")
+        })?;
+        let edition = match string(package, "edition")? {
+            Some(edition) => Edition::ALL
+                .iter()
+                .find(|(_, name)| *name == edition)
+                .map(|(edition, _)| *edition)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown `package.edition` \"{edition}\", expected one of {}",
+                        Edition::ALL
+                            .map(|(_, name)| format!("\"{name}\""))
+                            .join(", ")
+                    )
+                })?,
+            None => Edition::LATEST,
+        };
+        let entry = relative_path(package, "entry")?.unwrap_or_else(|| PathBuf::from("main.alloy"));
+        if entry.extension().is_none_or(|ext| ext != "alloy") {
+            return Err(format!(
+                "invalid `package.entry` \"{}\", expected an .alloy file",
+                entry.display()
+            ));
+        }
+        let source = relative_path(package, "source")?.unwrap_or_default();
+        let features = features(package)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            version,
+            edition,
+            entry,
+            source,
+            features,
+        })
+    }
+}
+
+fn string<'a>(package: &'a Table, field: &str) -> Result<Option<&'a str>, String> {
+    match package.get(field) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(value) => Err(format!(
+            "`package.{field}` should be a string, found {}",
+            value.type_str()
+        )),
+        None => Ok(None),
+    }
+}
+
+/// A path that has to stay inside the project directory
+fn relative_path(package: &Table, field: &str) -> Result<Option<PathBuf>, String> {
+    let Some(path) = string(package, field)? else {
+        return Ok(None);
+    };
+    let mut normalized = PathBuf::new();
+    // `.` components are dropped so the paths of modules match however the manifest spells them
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "invalid `package.{field}` \"{path}\", expected a path inside the project \
+                     directory"
+                ));
+            }
+        }
+    }
+    Ok(Some(normalized))
+}
+
+fn features(package: &Table) -> Result<Vec<String>, String> {
+    let features = match package.get("features") {
+        Some(Value::Array(features)) => features,
+        Some(value) => {
+            return Err(format!(
+                "`package.features` should be an array, found {}",
+                value.type_str()
+            ));
+        }
+        None => return Ok(vec![]),
+    };
+    let mut names = vec![];
+    for feature in features {
+        let Value::String(name) = feature else {
+            return Err(format!(
+                "`package.features` should only contain strings, found {}",
+                feature.type_str()
+            ));
+        };
+        if !is_valid_name(name) {
+            return Err(format!("invalid feature name \"{name}\""));
+        }
+        if names.contains(name) {
+            return Err(format!("feature \"{name}\" is enabled more than once"));
+        }
+        names.push(name.clone());
+    }
+    Ok(names)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `major.minor.patch`, without pre-release or build metadata
+fn parse_version(version: &str) -> Option<Version> {
+    let mut parts = version.split('.').map(|part| {
+        // `01` is ambiguous, so like semver only `0` may start with a zero
+        let is_number = !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+        if !is_number || (part.len() > 1 && part.starts_with('0')) {
+            return None;
+        }
+        part.parse::<u64>().ok()
+    });
+    let version = Version {
+        major: parts.next()??,
+        minor: parts.next()??,
+        patch: parts.next()??,
+    };
+    parts.next().is_none().then_some(version)
+}
@@ -1,2 +1,3 @@
+pub mod manifest;
 pub mod module;
 pub mod tree;
@@ -6,13 +6,20 @@ use crate::{common::span::Span, lexing::token::Token};
 pub struct Module {
     pub src: String,
     pub file_path: PathBuf,
+    /// Where the module's chars start in the program, so that spans from different modules of
+    /// a program never overlap. Spans are in these program wide offsets.
+    pub offset: usize,
 }
 
 type IterItem = (char, usize);
 
 impl Module {
     pub fn new(src: String, file_path: PathBuf) -> Self {
-        Self { src, file_path }
+        Self {
+            src,
+            file_path,
+            offset: 0,
+        }
     }
 
     pub fn iter(&self) -> Chars<'_> {
@@ -20,13 +27,13 @@ impl Module {
     }
 
     pub fn token(&self, token: &Token) -> &str {
-        &self.src[token.span.0..token.span.1]
+        self.span_slice(&token.span)
     }
 
     pub fn slice(&self, start: usize, end: usize) -> &str {
         // SAFETY: `start` and `end` are UTF-8 char indices, not byte indexes
         // for ascii, this should work fine though
-        &self.src[start..end]
+        &self.src[start - self.offset..end - self.offset]
     }
 
     pub fn span_slice(&self, span: &Span) -> &str {
@@ -36,4 +43,38 @@ impl Module {
     pub fn ln(&self) -> usize {
         self.src.len()
     }
+
+    /// The offset just past the module's last char
+    pub fn end(&self) -> usize {
+        self.offset + self.iter().count()
+    }
+
+    /// Whether an offset is in the module, or just past its end
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.offset..=self.end()).contains(&offset)
+    }
+
+    /// 1-based line and column of a char offset
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let mut line = 1;
+        let mut col = 1;
+        for c in self.iter().take(offset - self.offset) {
+            if c == '\n' {
+                line += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
+        (line, col)
+    }
+
+    /// Text of a 1-based line, without its line terminator
+    pub fn line(&self, line: usize) -> &str {
+        self.src
+            .lines()
+            .nth(line - 1)
+            .unwrap_or_default()
+            .trim_end_matches('\r')
+    }
 }
@@ -1,31 +1,110 @@
-use std::{ffi::OsStr, fs, path::PathBuf};
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::module::module::Module;
+use crate::{
+    common::expression::ExpressionKind,
+    lexing::Lexer,
+    module::{
+        manifest::{MANIFEST_FILE_NAME, Manifest},
+        module::Module,
+    },
+    parsing::Parser,
+};
 
 pub struct ModuleTree {
     pub entry_path: PathBuf,
     pub modules: Vec<Module>,
+    /// The manifest of a project directory, files and directories without one have none
+    pub manifest: Option<Manifest>,
 }
 
 impl ModuleTree {
-    pub fn new(path: &PathBuf, entry_file_name: Option<&OsStr>) -> Self {
-        let mut modules = vec![];
-        walk_dir_and_read_modules(path, &mut modules).expect("Failed to read modules from path");
+    pub fn new(path: &Path, entry_file_name: Option<&OsStr>) -> Self {
+        Self::load(path, entry_file_name).expect("Failed to load module tree")
+    }
+
+    /// Loads the entry module of a directory or the file itself, and every module it imports,
+    /// directly or not. A directory with an `alloy.toml` starts at the entry in the manifest's
+    /// source directory, one without starts at `main.alloy`. `entry_file_name` overrides either
+    /// entry.
+    pub fn load(path: &Path, entry_file_name: Option<&OsStr>) -> Result<Self, String> {
+        if !path.exists() {
+            return Err(format!("{} not found", path.display()));
+        }
+        let manifest = if path.is_dir() {
+            Manifest::find(path)?
+        } else {
+            None
+        };
+        let source_dir = manifest.as_ref().map_or_else(
+            || path.to_path_buf(),
+            |manifest| path.join(&manifest.source),
+        );
+        if manifest.is_some() && !source_dir.is_dir() {
+            return Err(format!(
+                "source directory {} not found",
+                source_dir.display()
+            ));
+        }
 
         let entry_path = if path.is_file() {
-            path.clone()
+            path.to_path_buf()
         } else {
-            path.join(entry_file_name.unwrap_or(OsStr::new("main.alloy")))
+            let default_entry = manifest.as_ref().map_or_else(
+                || OsStr::new("main.alloy"),
+                |manifest| manifest.entry.as_os_str(),
+            );
+            source_dir.join(entry_file_name.unwrap_or(default_entry))
         };
 
-        let tree = Self {
+        if !entry_path.is_file() {
+            let hint = if path.is_dir() && manifest.is_none() {
+                format!(", add an {MANIFEST_FILE_NAME} to use another entry")
+            } else {
+                String::new()
+            };
+            return Err(format!(
+                "entry module {} not found{hint}",
+                entry_path.display()
+            ));
+        }
+
+        // files nothing imports are never read, so one that can't be doesn't stop the program
+        let mut modules = vec![read_alloy_file(&entry_path)?];
+        let mut next = 0;
+        while next < modules.len() {
+            for file_path in imported_files(&modules[next]) {
+                // a missing module is reported by the checker, where the import has a span
+                if file_path.is_file() && !modules.iter().any(|m| m.file_path == file_path) {
+                    modules.push(read_alloy_file(&file_path)?);
+                }
+            }
+            next += 1;
+        }
+
+        // the entry starts at 0, so a program without imports has the spans of a lone module
+        let mut offset = modules[0].end() + 1;
+        for module in &mut modules[1..] {
+            module.offset = offset;
+            offset = module.end() + 1;
+        }
+
+        Ok(Self {
             entry_path,
             modules,
-        };
-
-		tree.entry();
+            manifest,
+        })
+    }
 
-		tree
+    /// The module a program wide offset is in
+    pub fn module_at(&self, offset: usize) -> &Module {
+        self.modules
+            .iter()
+            .find(|m| m.contains(offset))
+            .unwrap_or_else(|| self.entry())
     }
 
     pub fn entry(&self) -> &Module {
@@ -38,33 +117,26 @@ impl ModuleTree {
     }
 }
 
-fn walk_dir_and_read_modules<'a>(
-    path: &PathBuf,
-    modules: &'a mut Vec<Module>,
-) -> Result<(), String> {
-    if path.is_file() {
-        modules.push(read_alloy_file(path)?);
-        return Ok(());
-    }
-
-    for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            walk_dir_and_read_modules(&path, modules)?;
-        } else if path.extension().is_some_and(|ext| ext == "alloy") {
-            modules.push(read_alloy_file(&path)?);
-        }
-    }
-
-    Ok(())
+/// The files a module's imports name, `a/b.alloy` next to the module for `import a.b`
+fn imported_files(module: &Module) -> Vec<PathBuf> {
+    let (ast, _) = Parser::new(Lexer::new(module)).parse_partial();
+    let dir = module.file_path.parent().unwrap_or_else(|| Path::new(""));
+    ast.0
+        .iter()
+        .filter_map(|expr| {
+            let ExpressionKind::Import(path) = &expr.kind else {
+                return None;
+            };
+            let mut file_path = dir.to_path_buf();
+            file_path.extend(path);
+            file_path.set_extension("alloy");
+            Some(file_path)
+        })
+        .collect()
 }
 
 fn read_alloy_file(path: &PathBuf) -> Result<Module, String> {
-    if !path.is_file() {
-        return Err("Expected a file".to_string());
-    }
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read module {}: {e}", path.display()))?;
     Ok(Module::new(content, path.clone()))
 }
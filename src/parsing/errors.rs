@@ -24,4 +24,14 @@ impl ParseError {
     pub fn no_prefix_parse(token: &Token) -> Self {
         ParseError::NoPrefixParse(token.clone())
     }
+
+    /// The code `alloy explain` describes the error by, a code is never reused or renumbered
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::NoPrefixParse(_) => "E0001",
+            Self::Expected(..) => "E0002",
+            Self::Syntax(..) => "E0003",
+            Self::UnexpectedEof => "E0004",
+        }
+    }
 }
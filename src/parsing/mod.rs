@@ -3,13 +3,17 @@ use std::iter::Peekable;
 use crate::{
     common::{
         ast::Ast,
-        expression::{Expression, ExpressionKind, InfixOp, PrefixOp, TypeIdent},
+        expression::{
+            Expression, ExpressionKind, Field, FieldInit, InfixOp, MatchArm, Param, Pattern,
+            PatternKind, PrefixOp, TypeIdent, TypeValue, Variant,
+        },
         span::Span,
     },
     lexing::{
         Lexer,
         token::{Token, TokenKind},
     },
+    module::module::Module,
     parsing::precedence::Precedence,
 };
 use errors::ParseError;
@@ -18,21 +22,115 @@ pub mod errors;
 pub mod precedence;
 
 pub struct Parser<'a> {
+    module: &'a Module,
     tokens: Peekable<Lexer<'a>>,
+    // disabled while parsing `if`/`while` conditions, where `{` starts the body instead
+    struct_literals: bool,
+    // errors recovered from so far, each left an `Error` node in the `Ast`
+    errors: Vec<ParseError>,
+    // the end of the last consumed token
+    last_end: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: Lexer<'a>) -> Self {
+        let module = tokens.module();
         let tokens = tokens.peekable();
-        Self { tokens }
+        Self {
+            module,
+            tokens,
+            struct_literals: true,
+            errors: vec![],
+            last_end: module.offset,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Ast, Vec<ParseError>> {
+        let (ast, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(ast)
+        } else {
+            Err(errors)
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Ast, ParseError> {
+    /// Parses as much of a module as it can, returning every error along with an `Ast` that has an
+    /// `Error` node in place of each expression that didn't parse
+    pub fn parse_partial(&mut self) -> (Ast, Vec<ParseError>) {
         let mut exprs = vec![];
-        while self.tokens.peek().is_some() {
-            exprs.push(self.parse_expression(&Precedence::Lowest)?);
+        while let Some(token) = self.tokens.peek() {
+            let start = token.span.0;
+            match self.parse_recovering() {
+                Ok(expr) => {
+                    // a `}` without a `{` ends nothing at the top level, recovery skips over it
+                    if matches!(expr.kind, ExpressionKind::Error)
+                        && self.peek_kind() == &TokenKind::RBrace
+                    {
+                        self.consume().expect("Peeked a token");
+                    }
+                    exprs.push(expr);
+                }
+                Err(err) => {
+                    // the module ended in the middle of an expression, so there's nothing after it
+                    // to recover with
+                    exprs.push(Expression::new(
+                        ExpressionKind::Error,
+                        Span::new(start, self.last_end),
+                    ));
+                    self.errors.push(err);
+                    break;
+                }
+            }
+        }
+        (Ast(exprs), std::mem::take(&mut self.errors))
+    }
+
+    /// Parses an expression of a block or the top level. When that fails, the error is recorded
+    /// and the tokens up to where the next expression starts are skipped and replaced by an
+    /// `Error` node, so that one mistake doesn't hide the ones after it.
+    fn parse_recovering(&mut self) -> Result<Expression, ParseError> {
+        let start = self
+            .tokens
+            .peek()
+            .map_or(self.last_end, |token| token.span.0);
+        let err = match self.parse_with_struct_literals(true) {
+            Ok(expr) => return Ok(expr),
+            Err(ParseError::UnexpectedEof) => return Err(ParseError::eof()),
+            Err(err) => err,
+        };
+        self.errors.push(err);
+        if self.last_end <= start && self.peek_kind() != &TokenKind::RBrace {
+            // the token that failed to parse is still next, so it has to go for the loop to move on
+            self.consume()?;
+        }
+        self.synchronize();
+        Ok(Expression::new(
+            ExpressionKind::Error,
+            Span::new(start, self.last_end.max(start)),
+        ))
+    }
+
+    /// Skips to the start of the next expression in the enclosing block, a declaration or a name
+    /// at the start of a line, or to the `}` closing the block
+    fn synchronize(&mut self) {
+        let module = self.module;
+        let mut depth = 0;
+        while let Some(token) = self.tokens.peek() {
+            let starts_line = module.slice(self.last_end, token.span.0).contains('\n');
+            match token.kind {
+                TokenKind::RBrace if depth == 0 => return,
+                TokenKind::Fn | TokenKind::Type | TokenKind::Pub | TokenKind::Import
+                    if depth == 0 =>
+                {
+                    return;
+                }
+                TokenKind::Ident if depth == 0 && starts_line => return,
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth -= 1,
+                _ => {}
+            }
+            self.consume().expect("Peeked a token");
         }
-        Ok(Ast(exprs))
     }
 
     fn parse_expression(&mut self, precedence: &Precedence) -> Result<Expression, ParseError> {
@@ -57,6 +155,8 @@ impl<'a> Parser<'a> {
                 }
                 TokenKind::DoubleEquals => self.parse_infix_expression(expr, InfixOp::Equals)?,
                 TokenKind::NotEquals => self.parse_infix_expression(expr, InfixOp::NotEquals)?,
+                TokenKind::DoubleAmpersand => self.parse_infix_expression(expr, InfixOp::And)?,
+                TokenKind::DoublePipe => self.parse_infix_expression(expr, InfixOp::Or)?,
 
                 TokenKind::Comment => {
                     self.consume()?;
@@ -65,19 +165,45 @@ impl<'a> Parser<'a> {
 
                 TokenKind::Dot => {
                     self.consume()?;
-                    let (name, args, span) = self.parse_function_call(&expr.span)?;
-                    let total_span = expr.span.to(&span);
-                    Expression::new(
-                        ExpressionKind::MethodCall {
-                            caller: Box::new(expr),
-                            name,
-                            args,
-                        },
-                        total_span,
-                    )
+                    let name = self.expect(TokenKind::Ident)?;
+                    if self.peek_kind() == &TokenKind::LParen {
+                        let (args, end) = self.parse_call_args()?;
+                        let span = expr.span.to(&end);
+                        Expression::new(
+                            ExpressionKind::MethodCall {
+                                caller: Box::new(expr),
+                                name: name.text,
+                                args,
+                                function: None,
+                            },
+                            span,
+                        )
+                    } else if self.struct_literals
+                        && self.peek_kind() == &TokenKind::LBrace
+                        && self.next_on_same_line(&name.span)
+                        && let ExpressionKind::Ident { name: namespace, .. } = &expr.kind
+                    {
+                        // a struct from an imported module, like `shapes.Point { x: 1 }`
+                        let name = format!("{namespace}.{}", name.text);
+                        self.parse_struct_literal(name, &expr.span)?
+                    } else {
+                        let span = expr.span.to(&name.span);
+                        Expression::new(
+                            ExpressionKind::FieldAccess {
+                                target: Box::new(expr),
+                                field: name.text,
+                            },
+                            span,
+                        )
+                    }
                 }
 
-                _ => return Err(ParseError::syntax(&self.consume()?, "illegal token found")),
+                _ => {
+                    return Err(ParseError::syntax(
+                        &self.peek_token()?,
+                        "illegal token found",
+                    ));
+                }
             };
         }
 
@@ -112,25 +238,56 @@ impl<'a> Parser<'a> {
             TokenKind::Boolean => self.parse_boolean(),
             TokenKind::Number => self.parse_number(),
             TokenKind::Type => self.parse_type_decl(),
+            TokenKind::Fn => self.parse_function_decl(),
             TokenKind::Exclamation => self.parse_prefix_expression(PrefixOp::Not),
+            TokenKind::Minus => self.parse_prefix_expression(PrefixOp::Negate),
+            TokenKind::Tilde => self.parse_prefix_expression(PrefixOp::BitNot),
             TokenKind::LBrace => self.parse_block_expression(),
+            TokenKind::LParen => self.parse_group_expression(),
+            TokenKind::If => self.parse_if_expression(),
+            TokenKind::Match => self.parse_match_expression(),
+            TokenKind::While => self.parse_while_expression(),
+            TokenKind::Loop => self.parse_loop_expression(),
+            TokenKind::Break => self.parse_break_expression(),
+            TokenKind::Continue => {
+                let token = self.consume()?;
+                Ok(Expression::new(ExpressionKind::Continue, token.span))
+            }
+            TokenKind::Import => self.parse_import(),
+            TokenKind::Pub => self.parse_pub(),
             TokenKind::Ident => {
                 let token = self.consume()?;
+                let same_line = self.next_on_same_line(&token.span);
+                if self.struct_literals && self.peek_kind() == &TokenKind::LBrace && same_line {
+                    return self.parse_struct_literal(token.text, &token.span);
+                }
                 match self.peek_kind() {
                     TokenKind::Colon => self.parse_variable_declaration(token),
                     TokenKind::Equals => self.parse_variable_assignment(token),
+                    // a group on the next line starts a new expression rather than a call
+                    TokenKind::LParen if same_line => {
+                        let (args, end) = self.parse_call_args()?;
+                        Ok(Expression::new(
+                            ExpressionKind::FunctionCall {
+                                name: token.text,
+                                args,
+                                function: None,
+                            },
+                            token.span.to(&end),
+                        ))
+                    }
                     _ => self.parse_variable_usage(token),
                 }
             }
-            _ => Err(ParseError::no_prefix_parse(&self.consume()?)),
+            _ => Err(ParseError::no_prefix_parse(&self.peek_token()?)),
         }
     }
 
     fn parse_block_expression(&mut self) -> Result<Expression, ParseError> {
         let start = self.expect(TokenKind::LBrace)?;
         let mut exprs = vec![];
-        while self.peek_kind() != &TokenKind::RBrace {
-            exprs.push(self.parse_expression(&Precedence::Lowest)?);
+        while !matches!(self.peek_kind(), TokenKind::RBrace | TokenKind::Eof) {
+            exprs.push(self.parse_recovering()?);
         }
         let end = self.expect(TokenKind::RBrace)?;
         Ok(Expression::new(
@@ -139,8 +296,213 @@ impl<'a> Parser<'a> {
         ))
     }
 
+    fn parse_group_expression(&mut self) -> Result<Expression, ParseError> {
+        // grouping only affects precedence, so the inner expression is returned as is
+        self.expect(TokenKind::LParen)?;
+        let expr = self.parse_with_struct_literals(true)?;
+        self.expect(TokenKind::RParen)?;
+        Ok(expr)
+    }
+
+    fn parse_with_struct_literals(&mut self, allowed: bool) -> Result<Expression, ParseError> {
+        let outer = std::mem::replace(&mut self.struct_literals, allowed);
+        let expr = self.parse_expression(&Precedence::Lowest);
+        self.struct_literals = outer;
+        expr
+    }
+
+    fn parse_struct_literal(&mut self, name: String, start: &Span) -> Result<Expression, ParseError> {
+        self.expect(TokenKind::LBrace)?;
+        let mut fields = vec![];
+        while self.peek_kind() != &TokenKind::RBrace {
+            let name = self.expect(TokenKind::Ident)?;
+            self.expect(TokenKind::Colon)?;
+            let value = self.parse_with_struct_literals(true)?;
+            fields.push(FieldInit {
+                name: name.text,
+                value,
+            });
+            if self.peek_kind() == &TokenKind::Comma {
+                self.consume()?;
+            }
+        }
+        let end = self.expect(TokenKind::RBrace)?;
+        Ok(Expression::new(
+            ExpressionKind::StructLiteral { name, fields },
+            start.to(&end.span),
+        ))
+    }
+
+    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::If)?;
+        let condition = self.parse_with_struct_literals(false)?;
+        let then = self.parse_block_expression()?;
+
+        let otherwise = if self.peek_kind() == &TokenKind::Else {
+            self.consume()?;
+            if self.peek_kind() == &TokenKind::If {
+                Some(self.parse_if_expression()?)
+            } else {
+                Some(self.parse_block_expression()?)
+            }
+        } else {
+            None
+        };
+
+        let end = otherwise.as_ref().map_or(&then.span, |otherwise| &otherwise.span);
+        let span = start.span.to(end);
+        Ok(Expression::new(
+            ExpressionKind::If {
+                condition: Box::new(condition),
+                then: Box::new(then),
+                otherwise: otherwise.map(Box::new),
+            },
+            span,
+        ))
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::Match)?;
+        let scrutinee = self.parse_with_struct_literals(false)?;
+        self.expect(TokenKind::LBrace)?;
+        let mut arms = vec![];
+        while self.peek_kind() != &TokenKind::RBrace {
+            if self.peek_kind() == &TokenKind::Comment {
+                self.consume()?;
+                continue;
+            }
+            let pattern = self.parse_pattern()?;
+            self.expect(TokenKind::FatArrow)?;
+            let body = self.parse_with_struct_literals(true)?;
+            arms.push(MatchArm { pattern, body });
+            if self.peek_kind() == &TokenKind::Comma {
+                self.consume()?;
+            }
+        }
+        let end = self.expect(TokenKind::RBrace)?;
+        Ok(Expression::new(
+            ExpressionKind::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            },
+            start.span.to(&end.span),
+        ))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let token = self.consume()?;
+        match token.kind {
+            TokenKind::Ident if token.text == "_" => Ok(Pattern {
+                kind: PatternKind::Wildcard,
+                span: token.span,
+            }),
+            TokenKind::Ident if self.peek_kind() == &TokenKind::Dot => {
+                // `Shape.Circle`, or `shapes.Shape.Circle` for an enum from an imported module
+                let (mut path, span) = self.parse_path(token.clone())?;
+                let name = path.pop().expect("A path after a `.` has two segments");
+                let mut args = vec![];
+                let end = if self.peek_kind() == &TokenKind::LParen {
+                    self.consume()?;
+                    while self.peek_kind() != &TokenKind::RParen {
+                        args.push(self.parse_pattern()?);
+                        if self.peek_kind() == &TokenKind::Comma {
+                            self.consume()?;
+                        }
+                    }
+                    self.expect(TokenKind::RParen)?.span
+                } else {
+                    span
+                };
+                Ok(Pattern {
+                    kind: PatternKind::Variant {
+                        ty: TypeIdent::new(path.join(".")),
+                        name,
+                        args,
+                    },
+                    span: token.span.to(&end),
+                })
+            }
+            TokenKind::Ident => Ok(Pattern {
+                kind: PatternKind::Binding {
+                    name: token.text,
+                    var: None,
+                },
+                span: token.span,
+            }),
+            TokenKind::Boolean => Ok(Pattern {
+                kind: PatternKind::LiteralBool(token.text == "true"),
+                span: token.span,
+            }),
+            TokenKind::Number | TokenKind::Minus => {
+                let (negative, number) = if token.kind == TokenKind::Minus {
+                    (true, self.expect(TokenKind::Number)?)
+                } else {
+                    (false, token.clone())
+                };
+                let cleaned: String = number.text.chars().filter(|c| *c != '_').collect();
+                if cleaned.contains('.') {
+                    return Err(ParseError::syntax(
+                        &number,
+                        "only integer literals can be used as patterns",
+                    ));
+                }
+                let value: isize = cleaned
+                    .parse()
+                    .map_err(|_| ParseError::syntax(&number, "invalid int"))?;
+                Ok(Pattern {
+                    kind: PatternKind::LiteralInt(if negative { -value } else { value }),
+                    span: token.span.to(&number.span),
+                })
+            }
+            _ => Err(ParseError::expected(
+                &token,
+                vec![TokenKind::Ident, TokenKind::Number, TokenKind::Boolean],
+            )),
+        }
+    }
+
+    fn parse_while_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::While)?;
+        let condition = self.parse_with_struct_literals(false)?;
+        let body = self.parse_block_expression()?;
+        let span = start.span.to(&body.span);
+        Ok(Expression::new(
+            ExpressionKind::While {
+                condition: Box::new(condition),
+                body: Box::new(body),
+            },
+            span,
+        ))
+    }
+
+    fn parse_loop_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::Loop)?;
+        let body = self.parse_block_expression()?;
+        let span = start.span.to(&body.span);
+        Ok(Expression::new(ExpressionKind::Loop(Box::new(body)), span))
+    }
+
+    fn parse_break_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::Break)?;
+        // `break` only carries a value that starts on the same line
+        let has_value = !matches!(
+            self.peek_kind(),
+            TokenKind::RBrace | TokenKind::RParen | TokenKind::Comma | TokenKind::Comment
+        ) && self.next_on_same_line(&start.span);
+        if !has_value {
+            return Ok(Expression::new(ExpressionKind::Break(None), start.span));
+        }
+
+        let value = self.parse_expression(&Precedence::Lowest)?;
+        let span = start.span.to(&value.span);
+        Ok(Expression::new(
+            ExpressionKind::Break(Some(Box::new(value))),
+            span,
+        ))
+    }
+
     fn parse_prefix_expression(&mut self, op: PrefixOp) -> Result<Expression, ParseError> {
-        let start = self.expect(TokenKind::Exclamation)?;
+        let start = self.consume()?;
         let expr = self.parse_expression(&Precedence::Prefix)?;
         let new_span = start.span.to(&expr.span);
         Ok(Expression::new(
@@ -193,19 +555,166 @@ impl<'a> Parser<'a> {
         let name = self.expect(TokenKind::Ident)?;
         self.expect(TokenKind::Colon)?;
         self.expect(TokenKind::Equals)?;
-        let (value, value_span) = self.parse_type()?;
+        let (value, value_span) = match self.peek_kind() {
+            TokenKind::Struct => self.parse_struct_type()?,
+            TokenKind::Enum => self.parse_enum_type()?,
+            _ => {
+                let (ty, span) = self.parse_type()?;
+                (TypeValue::Alias(ty), span)
+            }
+        };
         Ok(Expression::new(
             ExpressionKind::TypeDecl {
                 name: name.text,
                 value,
+                public: false,
+                ty: None,
             },
             token.span.to(&value_span),
         ))
     }
 
+    fn parse_struct_type(&mut self) -> Result<(TypeValue, Span), ParseError> {
+        let start = self.expect(TokenKind::Struct)?;
+        self.expect(TokenKind::LBrace)?;
+        let mut fields = vec![];
+        while self.peek_kind() != &TokenKind::RBrace {
+            let name = self.expect(TokenKind::Ident)?;
+            self.expect(TokenKind::Colon)?;
+            let (ty, _) = self.parse_type()?;
+            fields.push(Field {
+                name: name.text,
+                ty,
+            });
+            if self.peek_kind() == &TokenKind::Comma {
+                self.consume()?;
+            }
+        }
+        let end = self.expect(TokenKind::RBrace)?;
+        Ok((TypeValue::Struct(fields), start.span.to(&end.span)))
+    }
+
+    fn parse_enum_type(&mut self) -> Result<(TypeValue, Span), ParseError> {
+        let start = self.expect(TokenKind::Enum)?;
+        self.expect(TokenKind::LBrace)?;
+        let mut variants = vec![];
+        while self.peek_kind() != &TokenKind::RBrace {
+            let name = self.expect(TokenKind::Ident)?;
+            let mut payload = vec![];
+            if self.peek_kind() == &TokenKind::LParen {
+                self.consume()?;
+                while self.peek_kind() != &TokenKind::RParen {
+                    payload.push(self.parse_type()?.0);
+                    if self.peek_kind() == &TokenKind::Comma {
+                        self.consume()?;
+                    }
+                }
+                self.expect(TokenKind::RParen)?;
+            }
+            variants.push(Variant {
+                name: name.text,
+                payload,
+            });
+            if self.peek_kind() == &TokenKind::Comma {
+                self.consume()?;
+            }
+        }
+        let end = self.expect(TokenKind::RBrace)?;
+        Ok((TypeValue::Enum(variants), start.span.to(&end.span)))
+    }
+
+    fn parse_function_decl(&mut self) -> Result<Expression, ParseError> {
+        let token = self.expect(TokenKind::Fn)?;
+        let name = self.expect(TokenKind::Ident)?;
+        self.expect(TokenKind::LParen)?;
+        let mut params = vec![];
+        while self.peek_kind() != &TokenKind::RParen {
+            let param = self.expect(TokenKind::Ident)?;
+            self.expect(TokenKind::Colon)?;
+            let (ty, _) = self.parse_type()?;
+            params.push(Param {
+                name: param.text,
+                ty,
+                var: None,
+            });
+            if self.peek_kind() == &TokenKind::Comma {
+                self.consume()?;
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+
+        let return_ty = if self.peek_kind() == &TokenKind::Colon {
+            self.consume()?;
+            Some(self.parse_type()?.0)
+        } else {
+            None
+        };
+
+        let body = self.parse_block_expression()?;
+        let span = token.span.to(&body.span);
+        Ok(Expression::new(
+            ExpressionKind::FunctionDecl {
+                name: name.text,
+                params,
+                return_ty,
+                body: Box::new(body),
+                public: false,
+                function: None,
+            },
+            span,
+        ))
+    }
+
     fn parse_type(&mut self) -> Result<(TypeIdent, Span), ParseError> {
-        let token = self.expect(TokenKind::Ident)?;
-        Ok((TypeIdent(token.text), token.span))
+        let first = self.expect(TokenKind::Ident)?;
+        let (path, span) = self.parse_path(first)?;
+        Ok((TypeIdent::new(path.join(".")), span))
+    }
+
+    /// `pub` before a declaration, like `pub fn area(...)`
+    fn parse_pub(&mut self) -> Result<Expression, ParseError> {
+        let token = self.expect(TokenKind::Pub)?;
+        let mut decl = self.get_first_expression()?;
+        match &mut decl.kind {
+            ExpressionKind::VariableDecl { public, .. }
+            | ExpressionKind::TypeDecl { public, .. }
+            | ExpressionKind::FunctionDecl { public, .. }
+                if !*public =>
+            {
+                *public = true;
+            }
+            _ => {
+                return Err(ParseError::syntax(
+                    &token,
+                    "`pub` can only be used on a variable, type or function declaration",
+                ));
+            }
+        }
+        decl.span = token.span.to(&decl.span);
+        Ok(decl)
+    }
+
+    fn parse_import(&mut self) -> Result<Expression, ParseError> {
+        let start = self.expect(TokenKind::Import)?;
+        let first = self.expect(TokenKind::Ident)?;
+        let (path, span) = self.parse_path(first)?;
+        Ok(Expression::new(
+            ExpressionKind::Import(path),
+            start.span.to(&span),
+        ))
+    }
+
+    /// A name followed by `.name`s, like `geometry.shapes`
+    fn parse_path(&mut self, first: Token) -> Result<(Vec<String>, Span), ParseError> {
+        let mut end = first.span.clone();
+        let mut path = vec![first.text];
+        while self.peek_kind() == &TokenKind::Dot {
+            self.consume()?;
+            let segment = self.expect(TokenKind::Ident)?;
+            end = segment.span;
+            path.push(segment.text);
+        }
+        Ok((path, first.span.to(&end)))
     }
 
     fn parse_variable_declaration(&mut self, start: Token) -> Result<Expression, ParseError> {
@@ -235,6 +744,8 @@ impl<'a> Parser<'a> {
                 value: Box::new(value),
                 mutable,
                 ty: ty.map(|(ty, _)| ty),
+                public: false,
+                var: None,
             },
             span,
         ))
@@ -248,6 +759,7 @@ impl<'a> Parser<'a> {
             ExpressionKind::VariableAssignment {
                 name: start.text,
                 value: Box::new(value),
+                var: None,
             },
             span,
         ))
@@ -255,23 +767,22 @@ impl<'a> Parser<'a> {
 
     fn parse_variable_usage(&mut self, start: Token) -> Result<Expression, ParseError> {
         Ok(Expression::new(
-            ExpressionKind::Ident(start.text),
+            ExpressionKind::Ident {
+                name: start.text,
+                var: None,
+            },
             start.span,
         ))
     }
 
-    fn parse_function_call(
-        &mut self,
-        start: &Span,
-    ) -> Result<(String, Vec<Expression>, Span), ParseError> {
-        let ident = self.expect(TokenKind::Ident)?;
+    fn parse_call_args(&mut self) -> Result<(Vec<Expression>, Span), ParseError> {
         self.expect(TokenKind::LParen)?;
         let mut args = vec![];
         loop {
             match self.peek_kind() {
                 TokenKind::RParen => break,
                 _ => {
-                    args.push(self.parse_expression(&Precedence::Lowest)?);
+                    args.push(self.parse_with_struct_literals(true)?);
                     if self.peek_kind() == &TokenKind::Comma {
                         self.consume()?;
                     }
@@ -279,21 +790,34 @@ impl<'a> Parser<'a> {
             }
         }
         let end = self.expect(TokenKind::RParen)?;
-        let span = start.to(&end.span);
-        Ok((ident.text, args, span))
+        Ok((args, end.span))
     }
 
+    fn next_on_same_line(&mut self, span: &Span) -> bool {
+        let module = self.module;
+        self.tokens
+            .peek()
+            .is_some_and(|token| !module.slice(span.1, token.span.0).contains('\n'))
+    }
+
+    /// Consumes the next token if it is an `exp`, an unexpected token is left for recovery to skip
     fn expect(&mut self, exp: TokenKind) -> Result<Token, ParseError> {
-        let token = self.consume()?;
+        let token = self.peek_token()?;
         if token.kind == exp {
-            Ok(token)
+            self.consume()
         } else {
             Err((&ParseError::expected)(&token, vec![exp]))
         }
     }
 
     fn consume(&mut self) -> Result<Token, ParseError> {
-        Ok(self.tokens.next().ok_or(ParseError::eof())?)
+        let token = self.tokens.next().ok_or_else(ParseError::eof)?;
+        self.last_end = token.span.1;
+        Ok(token)
+    }
+
+    fn peek_token(&mut self) -> Result<Token, ParseError> {
+        self.tokens.peek().cloned().ok_or_else(ParseError::eof)
     }
 
     fn peek_kind(&mut self) -> &TokenKind {
@@ -3,27 +3,29 @@ use crate::lexing::token::TokenKind::{self, *};
 #[derive(PartialEq, PartialOrd)]
 pub enum Precedence {
     Lowest,
+    Or,         // ||
+    And,        // &&
     Equality,   // == or !=
     Comparison, // <, <=, >, >=
     Sum,        // + or -
     Product,    // * or /
     Order,      // ^
-    Group,      // { }
-    Prefix,     // !X or -X
+    Prefix,     // !X, -X or ~X
     Dot,
 }
 
 impl Precedence {
     pub fn of(kind: &TokenKind) -> Self {
         match kind {
-            DoubleEquals | NotEquals => Precedence::Equality,
-            LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => Precedence::Comparison,
-            Plus | Minus => Precedence::Sum,
-            Asterisk | Slash => Precedence::Product,
-            Caret => Precedence::Order,
-            LBrace => Precedence::Group,
-            Dot => Precedence::Dot,
-            _ => Precedence::Lowest,
+            DoublePipe => Self::Or,
+            DoubleAmpersand => Self::And,
+            DoubleEquals | NotEquals => Self::Equality,
+            LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => Self::Comparison,
+            Plus | Minus => Self::Sum,
+            Asterisk | Slash => Self::Product,
+            Caret => Self::Order,
+            Dot => Self::Dot,
+            _ => Self::Lowest,
         }
     }
 }
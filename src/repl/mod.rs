@@ -0,0 +1,206 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    checking::Checker,
+    common::ast::Ast,
+    diagnostics::Diagnostic,
+    evaluating::{Evaluator, value::Value},
+    lexing::{Lexer, token::TokenKind},
+    module::module::Module,
+    parsing::Parser,
+};
+
+const PROMPT: &str = ">> ";
+// shown while an input has unclosed brackets
+const CONTINUATION_PROMPT: &str = ".. ";
+
+const HELP: &str = "\
+:type <expr>    print the type of an expression without running it
+:tokens <expr>  print the tokens of an expression
+:help           print this message
+:quit           end the session, like end of input
+";
+
+/// An interactive session, each input is checked and run against the declarations made by the
+/// inputs before it
+///
+/// Inputs are appended to one growing source, so diagnostics point at the right line even for
+/// runtime errors in functions declared by earlier inputs
+pub struct Repl {
+    module: Module,
+    checker: Checker,
+    evaluator: Evaluator,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            module: Module::new(String::new(), PathBuf::from("<repl>")),
+            checker: Checker::new(),
+            evaluator: Evaluator::new(),
+        }
+    }
+
+    /// Reads inputs until the input ends or `:quit`, it evaluates so it has to run inside
+    /// `with_evaluation_stack`
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        let mut buffer = String::new();
+        prompt(output, PROMPT)?;
+        for line in input.lines() {
+            buffer.push_str(&line?);
+            buffer.push('\n');
+            if is_incomplete(&buffer) {
+                prompt(output, CONTINUATION_PROMPT)?;
+                continue;
+            }
+            let Some(reply) = self.eval(&std::mem::take(&mut buffer)) else {
+                return Ok(());
+            };
+            write!(output, "{reply}")?;
+            prompt(output, PROMPT)?;
+        }
+        writeln!(output)
+    }
+
+    /// Handles one complete input, returning what to print or `None` to end the session
+    pub fn eval(&mut self, input: &str) -> Option<String> {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix(':') else {
+            return Some(if input.is_empty() {
+                String::new()
+            } else {
+                self.run_input(input)
+            });
+        };
+        let (name, rest) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        Some(match name {
+            "quit" | "q" => return None,
+            "help" | "h" => HELP.to_string(),
+            "type" | "t" if !rest.trim().is_empty() => self.type_of(rest.trim()),
+            "tokens" if !rest.trim().is_empty() => tokens(rest.trim()),
+            "type" | "t" | "tokens" => format!("error: `:{name}` needs an expression\n"),
+            _ => format!("error: unknown command `:{name}`, see `:help`\n"),
+        })
+    }
+
+    fn run_input(&mut self, input: &str) -> String {
+        let offset = self.append(input);
+        let ast = match Parser::new(Lexer::starting_at(&self.module, offset)).parse() {
+            Ok(ast) => ast,
+            Err(errors) => {
+                return errors
+                    .iter()
+                    .map(|err| self.render(&Diagnostic::from_parse_error(err, &self.module)))
+                    .collect();
+            }
+        };
+
+        // an input that doesn't check declares nothing
+        let snapshot = self.checker.clone();
+        let checked = match self.checker.check(Ast(ast.0.clone())) {
+            Ok(checked) => checked,
+            Err(errors) => {
+                let rendered = errors
+                    .iter()
+                    .map(|err| self.render(&Diagnostic::from_check_error(err, &self.checker)))
+                    .collect();
+                self.checker = snapshot;
+                return rendered;
+            }
+        };
+
+        let mut value = Value::Unit;
+        for (index, expr) in checked.0.iter().enumerate() {
+            match self.evaluator.evaluate(&Ast(vec![expr.clone()])) {
+                Ok(result) => value = result,
+                Err(err) => {
+                    // the expressions before the failing one ran, so only their declarations stay
+                    self.checker = snapshot;
+                    self.checker
+                        .check(Ast(ast.0[..index].to_vec()))
+                        .expect("Expressions that checked before check again");
+                    return self.render(&Diagnostic::from_runtime_error(&err));
+                }
+            }
+        }
+        if value == Value::Unit {
+            return String::new();
+        }
+        let ty = checked.0.last().map_or("Unit", |expr| expr.ty().0);
+        format!("{value}: {ty}\n")
+    }
+
+    fn type_of(&mut self, input: &str) -> String {
+        let offset = self.append(input);
+        let ast = match Parser::new(Lexer::starting_at(&self.module, offset)).parse() {
+            Ok(ast) => ast,
+            Err(errors) => {
+                return errors
+                    .iter()
+                    .map(|err| self.render(&Diagnostic::from_parse_error(err, &self.module)))
+                    .collect();
+            }
+        };
+        // checked against a copy, so declarations in the expression don't stick
+        let mut checker = self.checker.clone();
+        match checker.check(ast) {
+            Ok(ast) => format!("{}\n", ast.0.last().map_or("Unit", |expr| expr.ty().0)),
+            Err(errors) => errors
+                .iter()
+                .map(|err| self.render(&Diagnostic::from_check_error(err, &checker)))
+                .collect(),
+        }
+    }
+
+    /// Adds an input to the session's source, returning the offset it starts at
+    fn append(&mut self, input: &str) -> usize {
+        let offset = self.module.end();
+        self.module.src.push_str(input);
+        self.module.src.push('\n');
+        offset
+    }
+
+    fn render(&self, diagnostic: &Diagnostic) -> String {
+        format!("{}\n", diagnostic.render(&self.module))
+    }
+}
+
+/// Whether an input opens more brackets than it closes, so it continues on the next line
+pub fn is_incomplete(input: &str) -> bool {
+    let module = Module::new(input.to_string(), PathBuf::from("<repl>"));
+    let mut depth = 0;
+    for token in Lexer::new(&module) {
+        match token.kind {
+            TokenKind::LBrace | TokenKind::LParen => depth += 1,
+            TokenKind::RBrace | TokenKind::RParen => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+/// The tokens of an input, printed like `alloy tokens` does
+fn tokens(input: &str) -> String {
+    let module = Module::new(input.to_string(), PathBuf::from("<repl>"));
+    let mut output = String::new();
+    for token in Lexer::new(&module) {
+        writeln!(
+            output,
+            "{:?} {:?} {}..{}",
+            token.kind, token.text, token.span.0, token.span.1
+        )
+        .unwrap();
+    }
+    output
+}
+
+fn prompt(output: &mut impl Write, prompt: &str) -> io::Result<()> {
+    write!(output, "{prompt}")?;
+    output.flush()
+}
@@ -1,12 +1,31 @@
-use std::env;
+use std::{env, fs, io::Cursor, path::Path, process::Command};
 
 use insta::glob;
 
 use crate::{
-    checking::Checker,
+    bytecode::{compiler::Compiler, disassembler::disassemble, vm::Vm},
+    checking::{Checker, imports::load_program},
+    common::ast::Ast,
+    diagnostics::{
+        Diagnostic,
+        explain::{EXPLANATIONS, explain},
+    },
+    emitting::{
+        c::{self, CEmitter},
+        llvm::{self, LlvmEmitter},
+        wat::{self, WatEmitter},
+    },
+    evaluating::{Evaluator, value::Value, with_evaluation_stack},
+    ir::{
+        interpreter::Interpreter,
+        lowering::Lowerer,
+        passes::{MAX_OPT_LEVEL, PassManager},
+        printer, verifier,
+    },
     lexing::Lexer,
-    module::{module::Module, tree::ModuleTree},
+    module::{manifest::Manifest, module::Module, tree::ModuleTree},
     parsing::Parser,
+    repl::Repl,
 };
 
 fn lexing(module: &Module) {
@@ -44,10 +63,309 @@ fn checking(module: &Module) {
     insta::assert_debug_snapshot!(checked.unwrap());
 }
 
+fn evaluating(module: &Module) {
+    let output = with_evaluation_stack(|| {
+        let lexer = Lexer::new(module);
+        let mut parser = Parser::new(lexer);
+        let mut checker = Checker::new();
+        let mut evaluator = Evaluator::new();
+
+        let ast = checker.check(parser.parse().unwrap()).unwrap();
+        let value = evaluator.evaluate(&ast);
+
+        let mut output = evaluator
+            .globals()
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>();
+        output.push(format!("=> {}", value.unwrap()));
+        output.join("\n")
+    });
+
+    // snapshot settings are per thread, so the assertion has to happen out here
+    insta::assert_snapshot!(output);
+}
+
+fn compiling(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let program = Compiler::new().compile(&ast).unwrap();
+
+    // the virtual machine has to agree with the tree-walker on every program, compared as printed
+    // because a `NaN` never equals itself
+    let expected = with_evaluation_stack(|| Evaluator::new().evaluate(&ast).unwrap());
+    assert_eq!(
+        Vm::new(&program).run().unwrap().to_string(),
+        expected.to_string()
+    );
+    insta::assert_snapshot!(disassemble(&program));
+}
+
+fn lowering(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let ir = Lowerer::new().lower(&ast).unwrap();
+    let printed_ir = printer::print(&ir);
+    if let Err(errors) = verifier::verify(&ir) {
+        panic!("invalid IR: {errors:#?}\n{printed_ir}");
+    }
+
+    let (expected, actual) = with_evaluation_stack(|| {
+        (
+            Evaluator::new().evaluate(&ast).unwrap(),
+            Interpreter::new(&ir).run().unwrap(),
+        )
+    });
+    assert_eq!(actual.to_string(), expected.to_string());
+    insta::assert_snapshot!(printed_ir);
+}
+
+fn optimizing(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let expected = with_evaluation_stack(|| Evaluator::new().evaluate(&ast).unwrap());
+    let mut optimized = String::new();
+    for level in 1..=MAX_OPT_LEVEL {
+        let mut ir = Lowerer::new().lower(&ast).unwrap();
+        PassManager::for_level(level).run(&mut ir);
+        let actual = with_evaluation_stack(|| Interpreter::new(&ir).run().unwrap());
+        assert_eq!(
+            actual.to_string(),
+            expected.to_string(),
+            "-O{level} changed the result"
+        );
+        optimized = printer::print(&ir);
+    }
+    // compared with the `lowering` snapshot, this shows what the passes did
+    insta::assert_snapshot!(optimized);
+}
+
+fn emitting_c(tree: &ModuleTree) {
+    let module = tree.entry();
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let source = CEmitter::new(tree).emit(&ast).unwrap();
+
+    // the executable has to print what `alloy run` prints, when a C compiler is around to build it
+    let name = module.file_path.file_stem().unwrap().to_string_lossy();
+    let binary = env::temp_dir().join(format!("alloy-{}-{name}", std::process::id()));
+    let c_path = binary.with_extension("c");
+    fs::write(&c_path, &source).unwrap();
+    let compiled = Command::new("cc")
+        .args(["-std=c99", "-O2", "-o"])
+        .arg(&binary)
+        .arg(&c_path)
+        .arg("-lm")
+        .status();
+    if let Ok(status) = compiled {
+        assert!(status.success(), "cc rejected {}", c_path.display());
+        let output = Command::new(&binary).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), printed(&ast));
+        fs::remove_file(&binary).unwrap();
+    }
+    fs::remove_file(&c_path).unwrap();
+
+    insta::assert_snapshot!(source.strip_prefix(c::PRELUDE).unwrap());
+}
+
+fn emitting_llvm(tree: &ModuleTree) {
+    let module = tree.entry();
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let source = LlvmEmitter::new(tree).emit(&ast).unwrap();
+
+    // the module has to print what `alloy run` prints, when LLVM's interpreter is around to run it
+    let name = module.file_path.file_stem().unwrap().to_string_lossy();
+    let ll_path = env::temp_dir().join(format!("alloy-{}-{name}.ll", std::process::id()));
+    fs::write(&ll_path, &source).unwrap();
+    // opaque pointers are opt-in before LLVM 15, which doesn't know the flag any more
+    let output = Command::new("lli")
+        .arg("-opaque-pointers")
+        .arg(&ll_path)
+        .output()
+        .ok()
+        .and_then(|output| {
+            if String::from_utf8_lossy(&output.stderr).contains("Unknown command line argument") {
+                Command::new("lli").arg(&ll_path).output().ok()
+            } else {
+                Some(output)
+            }
+        });
+    if let Some(output) = output {
+        assert!(
+            output.status.success(),
+            "lli rejected {}: {}",
+            ll_path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout), printed(&ast));
+    }
+    fs::remove_file(&ll_path).unwrap();
+
+    insta::assert_snapshot!(source.strip_prefix(llvm::PRELUDE).unwrap());
+}
+
+fn emitting_wat(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let source = WatEmitter::new().emit(&ast).unwrap();
+
+    // `main` has to return what `alloy run` prints, when wabt and wasmtime are around to run it,
+    // except for modules importing `Float` powers from a host that wasmtime doesn't provide
+    let value = with_evaluation_stack(|| Evaluator::new().evaluate(&ast).unwrap());
+    if let Some(expected) = invoked(&value).filter(|_| !source.contains("(import ")) {
+        let name = module.file_path.file_stem().unwrap().to_string_lossy();
+        let wat_path = env::temp_dir().join(format!("alloy-{}-{name}.wat", std::process::id()));
+        let wasm_path = wat_path.with_extension("wasm");
+        fs::write(&wat_path, &source).unwrap();
+        let assembled = Command::new("wat2wasm")
+            .arg(&wat_path)
+            .arg("-o")
+            .arg(&wasm_path)
+            .status();
+        if let Ok(status) = assembled {
+            assert!(status.success(), "wat2wasm rejected {}", wat_path.display());
+            let output = Command::new("wasmtime")
+                .args(["run", "--invoke", "main"])
+                .arg(&wasm_path)
+                .output();
+            if let Ok(output) = output {
+                assert!(
+                    output.status.success(),
+                    "wasmtime failed to run {}: {}",
+                    wat_path.display(),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+            }
+            fs::remove_file(&wasm_path).unwrap();
+        }
+        fs::remove_file(&wat_path).unwrap();
+    }
+
+    insta::assert_snapshot!(source.replace(wat::PRELUDE, ""));
+}
+
+/// What `wasmtime --invoke main` prints for a value `main` returns, a line for each of the wasm
+/// values it is flattened to, or `None` for an enum, whose payloads depend on every variant
+fn invoked(value: &Value) -> Option<String> {
+    match value {
+        Value::Unit => Some(String::new()),
+        Value::Int(value) => Some(format!("{value}\n")),
+        Value::Float(value) => Some(format!("{value}\n")),
+        Value::Bool(value) => Some(format!("{}\n", i32::from(*value))),
+        Value::Struct { fields, .. } => fields.iter().map(|(_, field)| invoked(field)).collect(),
+        Value::Variant { .. } => None,
+    }
+}
+
+/// What `alloy run` prints for a program
+fn printed(checked: &Ast) -> String {
+    let value = with_evaluation_stack(|| Evaluator::new().evaluate(checked).unwrap());
+    if value == Value::Unit {
+        String::new()
+    } else {
+        format!("{value}\n")
+    }
+}
+
+fn runtime_errors(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    let ast = checker.check(parser.parse().unwrap()).unwrap();
+    let err = with_evaluation_stack(|| Evaluator::new().evaluate(&ast).unwrap_err());
+    let program = Compiler::new().compile(&ast).unwrap();
+    let vm_err = Vm::new(&program).run().unwrap_err();
+    let ir = Lowerer::new().lower(&ast).unwrap();
+    let ir_err = with_evaluation_stack(|| Interpreter::new(&ir).run().unwrap_err());
+    let mut optimized = Lowerer::new().lower(&ast).unwrap();
+    PassManager::for_level(MAX_OPT_LEVEL).run(&mut optimized);
+    let optimized_err = with_evaluation_stack(|| Interpreter::new(&optimized).run().unwrap_err());
+
+    colored::control::set_override(false);
+    let rendered = Diagnostic::from_runtime_error(&err).render(module);
+    assert_eq!(
+        Diagnostic::from_runtime_error(&vm_err).render(module),
+        rendered
+    );
+    assert_eq!(
+        Diagnostic::from_runtime_error(&ir_err).render(module),
+        rendered
+    );
+    assert_eq!(
+        Diagnostic::from_runtime_error(&optimized_err).render(module),
+        rendered
+    );
+    insta::assert_snapshot!(rendered);
+}
+
+fn check_errors(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    // syntax errors are covered by the rendering snapshots
+    let Ok(ast) = parser.parse() else {
+        return;
+    };
+    let result = checker.check(ast);
+
+    assert!(result.is_err());
+    insta::assert_debug_snapshot!(result.unwrap_err());
+}
+
+fn rendering(module: &Module) {
+    let lexer = Lexer::new(module);
+    let mut parser = Parser::new(lexer);
+    let mut checker = Checker::new();
+
+    // what did parse is checked too, like `alloy check` does
+    let (ast, parse_errors) = parser.parse_partial();
+    let check_errors = checker.check(ast).err().unwrap_or_default();
+    let diagnostics = parse_errors
+        .iter()
+        .map(|err| Diagnostic::from_parse_error(err, module))
+        .chain(
+            check_errors
+                .iter()
+                .map(|err| Diagnostic::from_check_error(err, &checker)),
+        )
+        .collect::<Vec<_>>();
+    assert!(!diagnostics.is_empty());
+
+    colored::control::set_override(false);
+    let rendered = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.render(module))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(rendered);
+}
+
 #[test]
 fn snapshot_tests() {
-    let root_dir = env::current_dir().unwrap();
-    let cases_dir = root_dir.join("src").join("tests").join("cases");
+    // relative so that locations in the generated C don't contain machine specific paths
+    let cases_dir = Path::new("src").join("tests").join("cases");
 
     glob!("cases/*.alloy", |path| {
         let module_tree = ModuleTree::new(&cases_dir, path.file_name());
@@ -56,5 +374,271 @@ fn snapshot_tests() {
         lexing(entry);
         parsing(entry);
         checking(entry);
+        evaluating(entry);
+        compiling(entry);
+        lowering(entry);
+        optimizing(entry);
+        emitting_c(&module_tree);
+        emitting_llvm(&module_tree);
+        emitting_wat(entry);
+    });
+}
+
+#[test]
+fn error_snapshot_tests() {
+    // relative so that rendered diagnostics don't contain machine specific paths
+    let cases_dir = Path::new("src").join("tests").join("cases").join("errors");
+
+    glob!("cases/errors/*.alloy", |path| {
+        let module_tree = ModuleTree::new(&cases_dir, path.file_name());
+        let entry = module_tree.entry();
+
+        check_errors(entry);
+        rendering(entry);
+    });
+}
+
+#[test]
+fn runtime_error_snapshot_tests() {
+    let cases_dir = Path::new("src").join("tests").join("cases").join("runtime");
+
+    glob!("cases/runtime/*.alloy", |path| {
+        let module_tree = ModuleTree::new(&cases_dir, path.file_name());
+
+        runtime_errors(module_tree.entry());
+    });
+}
+
+/// Checks a program of several modules, rendering diagnostics against the module they are in
+fn check_program(tree: &ModuleTree) -> Result<Ast, String> {
+    let diagnostics = match diagnose(tree) {
+        Ok(ast) => return Ok(ast),
+        Err(diagnostics) => diagnostics,
+    };
+
+    colored::control::set_override(false);
+    Err(diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let span = diagnostic.span.as_ref().unwrap();
+            diagnostic.render(tree.module_at(span.0))
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Checks a program of several modules like `alloy check` does, returning the diagnostics it
+/// reports if any
+fn diagnose(tree: &ModuleTree) -> Result<Ast, Vec<Diagnostic>> {
+    let mut checker = Checker::new();
+    let diagnostics = match load_program(tree) {
+        Ok(modules) => {
+            let mut diagnostics = modules
+                .iter()
+                .flat_map(|module| {
+                    module
+                        .parse_errors
+                        .iter()
+                        .map(|err| Diagnostic::from_parse_error(err, module.module))
+                })
+                .collect::<Vec<_>>();
+            match checker.check_program(modules) {
+                Ok(ast) if diagnostics.is_empty() => return Ok(ast),
+                Ok(_) => {}
+                Err(errors) => diagnostics.extend(
+                    errors
+                        .iter()
+                        .map(|err| Diagnostic::from_check_error(err, &checker)),
+                ),
+            }
+            diagnostics
+        }
+        Err(err) => vec![Diagnostic::from_check_error(&err, &checker)],
+    };
+    Err(diagnostics)
+}
+
+#[test]
+fn import_snapshot_tests() {
+    glob!("imports/*/main.alloy", |path| {
+        let project_dir = path
+            .strip_prefix(env::current_dir().unwrap())
+            .unwrap_or(path);
+        let module_tree = ModuleTree::new(project_dir.parent().unwrap(), None);
+
+        match check_program(&module_tree) {
+            Ok(checked) => {
+                // declarations from every module end up in one program, so every back end runs it
+                let ir = Lowerer::new().lower(&checked).unwrap();
+                let program = Compiler::new().compile(&checked).unwrap();
+                let expected = with_evaluation_stack(|| {
+                    let expected = Evaluator::new().evaluate(&checked).unwrap();
+                    assert_eq!(Interpreter::new(&ir).run().unwrap(), expected);
+                    expected
+                });
+                assert_eq!(Vm::new(&program).run().unwrap(), expected);
+                insta::assert_snapshot!(printer::print(&ir));
+            }
+            Err(rendered) => insta::assert_snapshot!(rendered),
+        }
+    });
+}
+
+#[test]
+fn import_from_entry_file() {
+    // a file runs with the modules beside it, the same as its directory does
+    let project_dir = Path::new("src").join("tests").join("imports").join("valid");
+    let from_dir = check_program(&ModuleTree::new(&project_dir, None)).unwrap();
+    let entry_path = project_dir.join("main.alloy");
+    let from_file = check_program(&ModuleTree::new(&entry_path, None)).unwrap();
+
+    let (expected, actual) = with_evaluation_stack(|| {
+        (
+            Evaluator::new().evaluate(&from_dir).unwrap(),
+            Evaluator::new().evaluate(&from_file).unwrap(),
+        )
     });
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn only_imported_modules_are_read() {
+    // the cases share a directory without importing each other
+    let cases_dir = Path::new("src").join("tests").join("cases");
+    let module_tree = ModuleTree::new(&cases_dir.join("numbers.alloy"), None);
+    assert_eq!(module_tree.modules.len(), 1);
+
+    // `geometry/units.alloy` is only reached through `geometry/shapes.alloy`
+    let project_dir = Path::new("src").join("tests").join("imports").join("valid");
+    let module_tree = ModuleTree::new(&project_dir, None);
+    let mut read = module_tree
+        .modules
+        .iter()
+        .map(|module| module.file_path.strip_prefix(&project_dir).unwrap())
+        .collect::<Vec<_>>();
+    read.sort();
+    assert_eq!(
+        read,
+        [
+            "geometry/shapes.alloy",
+            "geometry/units.alloy",
+            "main.alloy",
+            "math.alloy"
+        ]
+        .map(Path::new)
+    );
+}
+
+#[test]
+fn manifest_snapshot_tests() {
+    glob!("manifests/*.toml", |path| {
+        let src = fs::read_to_string(path).unwrap();
+        insta::assert_debug_snapshot!(Manifest::parse(&src));
+    });
+}
+
+#[test]
+fn project_manifest() {
+    let project_dir = Path::new("src").join("tests").join("project");
+    let module_tree = ModuleTree::new(&project_dir, None);
+
+    assert_eq!(
+        module_tree
+            .manifest
+            .as_ref()
+            .map(|manifest| &manifest.name[..]),
+        Some("project")
+    );
+    assert_eq!(
+        module_tree.entry_path,
+        project_dir.join("src").join("app.alloy")
+    );
+    let ast = Checker::new()
+        .check(
+            Parser::new(Lexer::new(module_tree.entry()))
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+    let value = with_evaluation_stack(|| Evaluator::new().evaluate(&ast));
+    assert_eq!(value.unwrap(), Value::Int(42));
+}
+
+#[test]
+fn repl_session() {
+    // declarations from earlier inputs stay visible, unless their input failed
+    let session = "\
+a :: 2
+type A := Int
+b: A : 3
+fn square(n: Int): Int {
+    n * n
+}
+square(a) + b
+:type square(b) > a
+:tokens a + 1
+c :: missing
+c
+d :: 9223372036854775807 + a
+d
+fn invert(n: Int): Int { n ^ -1 }
+invert(b)
+b
+:quit
+a
+";
+    colored::control::set_override(false);
+    let mut output = vec![];
+    with_evaluation_stack(|| Repl::new().run(Cursor::new(session), &mut output)).unwrap();
+    insta::assert_snapshot!(String::from_utf8(output).unwrap());
+}
+
+#[test]
+fn explanation_examples() {
+    let codes = EXPLANATIONS.iter().map(|e| e.code).collect::<Vec<_>>();
+    let mut sorted = codes.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    assert_eq!(codes, sorted, "codes are listed in order, once each");
+
+    // the examples are checked, so they can't drift from what the checker reports
+    let reported = |code: &str, example: &str, modules: &[(&str, &str)]| {
+        let dir = env::temp_dir().join(format!("alloy-{}-{code}-{example}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, source) in modules {
+            fs::write(dir.join(file_name), source).unwrap();
+        }
+        let diagnostics = diagnose(&ModuleTree::new(&dir, None)).err();
+        fs::remove_dir_all(&dir).unwrap();
+        diagnostics
+            .unwrap_or_default()
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect::<Vec<_>>()
+    };
+    for explanation in EXPLANATIONS {
+        let code = explanation.code;
+        let failing = reported(code, "failing", explanation.failing);
+        assert_eq!(failing.first(), Some(&Some(code)), "{code} example fails");
+        assert!(
+            reported(code, "fixed", explanation.fixed).is_empty(),
+            "{code} example is fixed"
+        );
+    }
+
+    insta::assert_snapshot!(explain("e0123").unwrap().render());
+}
+
+#[test]
+fn json_diagnostics() {
+    let cases_dir = Path::new("src").join("tests").join("cases").join("errors");
+    let tree = ModuleTree::new(&cases_dir, Some("suggestions.alloy".as_ref()));
+
+    let json = diagnose(&tree)
+        .unwrap_err()
+        .iter()
+        .map(|diagnostic| diagnostic.to_json(tree.entry()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(json);
 }